@@ -0,0 +1,251 @@
+use std::future::{ready, Ready};
+use std::net::IpAddr;
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::header::AUTHORIZATION;
+use actix_web::middleware::Next;
+use actix_web::{web, Error, FromRequest, HttpMessage, HttpRequest, ResponseError};
+use chrono::Utc;
+use db::api_keys::find_active_api_key;
+use shared::auth::TokenKind;
+use shared::types::{ApiScope, UserId};
+use shared::CexError;
+use uuid::Uuid;
+
+use crate::auth::signing::{
+    check_recv_window, hash_api_key, verify_signature, DEFAULT_RECV_WINDOW_MS, HEADER_API_KEY,
+    HEADER_RECV_WINDOW, HEADER_SIGNATURE, HEADER_TIMESTAMP, SIGNATURE_NONCE_TTL_MS,
+};
+use crate::routes::ApiError;
+use crate::server::AppState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthMethod {
+    /// Interactive session using a bearer access token.
+    Jwt,
+    /// Signed request made with the given API key.
+    ApiKey(Uuid),
+}
+
+/// The caller identity established by [`require_auth`]. Handlers take this
+/// instead of trusting a `user_id` in the request body.
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub user_id: UserId,
+    pub method: AuthMethod,
+    pub scopes: Vec<ApiScope>,
+}
+
+impl AuthUser {
+    pub fn require(&self, scope: ApiScope) -> Result<(), CexError> {
+        if self.scopes.contains(&scope) {
+            Ok(())
+        } else {
+            Err(CexError::Forbidden(format!(
+                "missing `{}` scope",
+                scope.as_str()
+            )))
+        }
+    }
+
+    /// Credential management is only allowed from an interactive session.
+    pub fn require_interactive(&self) -> Result<(), CexError> {
+        match self.method {
+            AuthMethod::Jwt => Ok(()),
+            AuthMethod::ApiKey(_) => Err(CexError::Forbidden(
+                "not allowed with an api key".to_string(),
+            )),
+        }
+    }
+}
+
+impl FromRequest for AuthUser {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(
+            req.extensions()
+                .get::<AuthUser>()
+                .cloned()
+                .ok_or_else(|| ApiError(unauthorized("missing credentials")).into()),
+        )
+    }
+}
+
+/// Rejects requests without a valid bearer access token or API key signature
+/// and records the authenticated user in the request extensions.
+pub async fn require_auth(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let result = if req.headers().contains_key(HEADER_API_KEY) {
+        authenticate_api_key(&mut req).await
+    } else {
+        authenticate_bearer(&req)
+    };
+    match result {
+        Ok(user) => {
+            req.extensions_mut().insert(user);
+            next.call(req)
+                .await
+                .map(ServiceResponse::map_into_left_body)
+        }
+        Err(err) => Ok(req
+            .into_response(ApiError(err).error_response())
+            .map_into_right_body()),
+    }
+}
+
+/// Header carrying the operator token for `/admin` endpoints.
+pub const HEADER_ADMIN_TOKEN: &str = "X-CEX-ADMIN-TOKEN";
+
+/// Guards operator endpoints with the shared `ADMIN_TOKEN`. When no token is
+/// configured the endpoints are disabled.
+pub async fn require_admin(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let result = app_state(&req).and_then(|state| {
+        let expected = state
+            .admin_token
+            .as_deref()
+            .ok_or_else(|| CexError::Forbidden("admin endpoints are disabled".to_string()))?;
+        let given =
+            header(&req, HEADER_ADMIN_TOKEN).map_err(|_| unauthorized("missing admin token"))?;
+        if constant_time_eq(given.as_bytes(), expected.as_bytes()) {
+            Ok(())
+        } else {
+            Err(unauthorized("invalid admin token"))
+        }
+    });
+    match result {
+        Ok(()) => next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_left_body),
+        Err(err) => Ok(req
+            .into_response(ApiError(err).error_response())
+            .map_into_right_body()),
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn authenticate_bearer(req: &ServiceRequest) -> Result<AuthUser, CexError> {
+    let state = app_state(req)?;
+    let token = bearer_token(req.request()).ok_or_else(|| unauthorized("missing bearer token"))?;
+    let claims = state.jwt.verify(token, TokenKind::Access)?;
+    Ok(AuthUser {
+        user_id: claims.sub,
+        method: AuthMethod::Jwt,
+        scopes: ApiScope::ALL.to_vec(),
+    })
+}
+
+async fn authenticate_api_key(req: &mut ServiceRequest) -> Result<AuthUser, CexError> {
+    let state = app_state(req)?;
+    let api_key = header(req, HEADER_API_KEY)?.to_string();
+    let signature = header(req, HEADER_SIGNATURE)?.to_string();
+    let timestamp_ms: i64 = header(req, HEADER_TIMESTAMP)?
+        .parse()
+        .map_err(|_| CexError::Validation(format!("invalid {HEADER_TIMESTAMP}")))?;
+    let recv_window_ms: i64 = match req.headers().get(HEADER_RECV_WINDOW) {
+        Some(v) => v
+            .to_str()
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .ok_or_else(|| CexError::Validation(format!("invalid {HEADER_RECV_WINDOW}")))?,
+        None => DEFAULT_RECV_WINDOW_MS,
+    };
+    check_recv_window(timestamp_ms, recv_window_ms, Utc::now().timestamp_millis())?;
+
+    let key = find_active_api_key(state.db.pool(), &hash_api_key(&api_key))
+        .await?
+        .ok_or_else(|| unauthorized("invalid api key"))?;
+
+    if let Some(allowed) = &key.allowed_ips {
+        let peer = req.peer_addr().map(|addr| addr.ip());
+        let permitted = peer.is_some_and(|ip| {
+            allowed
+                .iter()
+                .filter_map(|a| a.parse::<IpAddr>().ok())
+                .any(|a| a == ip)
+        });
+        if !permitted {
+            return Err(CexError::Forbidden("ip address not allowed".to_string()));
+        }
+    }
+
+    // The body is part of the signature; read it and hand it back to the handler.
+    let body = req
+        .extract::<web::Bytes>()
+        .await
+        .map_err(|e| CexError::Validation(format!("failed to read body: {e}")))?;
+    req.set_payload(Payload::from(body.clone()));
+
+    let path = req
+        .uri()
+        .path_and_query()
+        .map(|pq| pq.as_str())
+        .unwrap_or_else(|| req.path());
+    let secret = state.signer.derive_secret(&api_key);
+    if !verify_signature(
+        &secret,
+        timestamp_ms,
+        req.method().as_str(),
+        path,
+        &body,
+        &signature,
+    ) {
+        return Err(unauthorized("invalid signature"));
+    }
+
+    // A signature may only be used once while any receive window could still
+    // accept it.
+    let fresh = state
+        .redis
+        .set_nx(
+            &format!("apikey:sig:{signature}"),
+            SIGNATURE_NONCE_TTL_MS as u64,
+        )
+        .await?;
+    if !fresh {
+        return Err(unauthorized("replayed request"));
+    }
+
+    Ok(AuthUser {
+        user_id: key.user_id,
+        method: AuthMethod::ApiKey(key.key_id),
+        scopes: key.scopes,
+    })
+}
+
+fn app_state(req: &ServiceRequest) -> Result<web::Data<AppState>, CexError> {
+    req.app_data::<web::Data<AppState>>()
+        .cloned()
+        .ok_or_else(|| CexError::Internal("missing app state".to_string()))
+}
+
+fn header<'a>(req: &'a ServiceRequest, name: &str) -> Result<&'a str, CexError> {
+    req.headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| CexError::Validation(format!("missing {name} header")))
+}
+
+fn bearer_token(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+fn unauthorized(msg: &str) -> CexError {
+    CexError::Unauthorized(msg.to_string())
+}
@@ -0,0 +1,5 @@
+pub mod middleware;
+pub mod password;
+pub mod signing;
+
+pub use middleware::{require_admin, require_auth, AuthMethod, AuthUser};
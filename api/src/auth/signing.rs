@@ -0,0 +1,112 @@
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use shared::CexError;
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub const HEADER_API_KEY: &str = "X-CEX-APIKEY";
+pub const HEADER_TIMESTAMP: &str = "X-CEX-TIMESTAMP";
+pub const HEADER_SIGNATURE: &str = "X-CEX-SIGNATURE";
+pub const HEADER_RECV_WINDOW: &str = "X-CEX-RECV-WINDOW";
+
+pub const DEFAULT_RECV_WINDOW_MS: i64 = 5_000;
+pub const MAX_RECV_WINDOW_MS: i64 = 60_000;
+/// Tolerated clock skew for timestamps slightly ahead of the server clock.
+pub const MAX_FUTURE_SKEW_MS: i64 = 1_000;
+/// How long a used signature is remembered. The receive window header is not
+/// signed, so this covers the longest any timestamp can be accepted for
+/// rather than the window the request asked for.
+pub const SIGNATURE_NONCE_TTL_MS: i64 = MAX_RECV_WINDOW_MS + MAX_FUTURE_SKEW_MS;
+
+/// Derives per-key signing secrets from a server-side master secret, so the
+/// database only ever stores a hash of the public key and a leaked table
+/// cannot be used to sign requests.
+pub struct ApiKeySigner {
+    master: Vec<u8>,
+}
+
+impl ApiKeySigner {
+    pub fn new(master: &[u8]) -> Self {
+        Self {
+            master: master.to_vec(),
+        }
+    }
+
+    pub fn derive_secret(&self, api_key: &str) -> String {
+        let mut mac = HmacSha256::new_from_slice(&self.master).expect("hmac accepts any key size");
+        mac.update(api_key.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+}
+
+pub fn generate_api_key() -> String {
+    hex::encode(rand::random::<[u8; 32]>())
+}
+
+pub fn hash_api_key(api_key: &str) -> String {
+    hex::encode(Sha256::digest(api_key.as_bytes()))
+}
+
+/// The signed message is `timestamp + METHOD + path_and_query + body`.
+pub fn sign(secret: &str, timestamp_ms: i64, method: &str, path: &str, body: &[u8]) -> String {
+    hex::encode(
+        signing_mac(secret, timestamp_ms, method, path, body)
+            .finalize()
+            .into_bytes(),
+    )
+}
+
+pub fn verify_signature(
+    secret: &str,
+    timestamp_ms: i64,
+    method: &str,
+    path: &str,
+    body: &[u8],
+    signature_hex: &str,
+) -> bool {
+    let Ok(signature) = hex::decode(signature_hex) else {
+        return false;
+    };
+    signing_mac(secret, timestamp_ms, method, path, body)
+        .verify_slice(&signature)
+        .is_ok()
+}
+
+fn signing_mac(
+    secret: &str,
+    timestamp_ms: i64,
+    method: &str,
+    path: &str,
+    body: &[u8],
+) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("hmac accepts any key size");
+    mac.update(timestamp_ms.to_string().as_bytes());
+    mac.update(method.to_ascii_uppercase().as_bytes());
+    mac.update(path.as_bytes());
+    mac.update(body);
+    mac
+}
+
+/// Rejects requests whose timestamp falls outside the caller's receive window.
+pub fn check_recv_window(
+    timestamp_ms: i64,
+    recv_window_ms: i64,
+    now_ms: i64,
+) -> Result<(), CexError> {
+    if recv_window_ms <= 0 || recv_window_ms > MAX_RECV_WINDOW_MS {
+        return Err(CexError::Validation(format!(
+            "recv window must be between 1 and {MAX_RECV_WINDOW_MS} ms"
+        )));
+    }
+    if timestamp_ms > now_ms + MAX_FUTURE_SKEW_MS {
+        return Err(CexError::Unauthorized(
+            "timestamp is in the future".to_string(),
+        ));
+    }
+    if now_ms - timestamp_ms > recv_window_ms {
+        return Err(CexError::Unauthorized(
+            "timestamp outside recv window".to_string(),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use shared::types::{CancelRejected, OrderId, OrderStatus, OrderUpdate, UserId};
+use shared::Event;
+use tokio::sync::oneshot;
+
+pub const DEFAULT_CANCEL_TIMEOUT_MS: u64 = 2_000;
+
+/// Requesting user and the order they asked to cancel.
+type WaiterKey = (UserId, OrderId);
+
+/// What the engine did with a cancel request.
+#[derive(Debug, Clone)]
+pub enum CancelOutcome {
+    Cancelled(OrderUpdate),
+    Rejected(CancelRejected),
+}
+
+/// Cancel requests waiting for the engine's answer, resolved from the event
+/// channel. Keyed by requester as well as order so a rejected cancel from
+/// someone else never answers the owner's request.
+pub struct PendingCancels {
+    timeout: Duration,
+    waiters: Mutex<HashMap<WaiterKey, Vec<oneshot::Sender<CancelOutcome>>>>,
+}
+
+impl PendingCancels {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            waiters: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Registers interest in `user_id` cancelling `order_id`; call before
+    /// queueing the request so a fast reply is not missed. The registration
+    /// is removed when the returned waiter is dropped, answered or not.
+    pub fn register(&self, user_id: UserId, order_id: OrderId) -> CancelWaiter<'_> {
+        let (tx, rx) = oneshot::channel();
+        let key = (user_id, order_id);
+        self.waiters
+            .lock()
+            .unwrap()
+            .entry(key)
+            .or_default()
+            .push(tx);
+        CancelWaiter {
+            pending: self,
+            key,
+            rx: Some(rx),
+        }
+    }
+
+    /// Hands a cancel or cancel rejection to everyone waiting on that order.
+    pub fn resolve(&self, event: &Event) {
+        let (key, outcome) = match event {
+            Event::OrderUpdate(update) if update.status == OrderStatus::Cancelled => (
+                (update.user_id, update.order_id),
+                CancelOutcome::Cancelled(update.clone()),
+            ),
+            Event::CancelRejected(rejected) => (
+                (rejected.user_id, rejected.order_id),
+                CancelOutcome::Rejected(rejected.clone()),
+            ),
+            _ => return,
+        };
+        let waiters = self.waiters.lock().unwrap().remove(&key);
+        for tx in waiters.into_iter().flatten() {
+            let _ = tx.send(outcome.clone());
+        }
+    }
+
+    pub fn waiting(&self) -> usize {
+        self.waiters.lock().unwrap().values().map(Vec::len).sum()
+    }
+
+    /// Drops waiters that have given up.
+    fn prune(&self, key: WaiterKey) {
+        let mut waiters = self.waiters.lock().unwrap();
+        if let Some(list) = waiters.get_mut(&key) {
+            list.retain(|tx| !tx.is_closed());
+            if list.is_empty() {
+                waiters.remove(&key);
+            }
+        }
+    }
+}
+
+/// One registered cancel request. Dropping it, including when the request
+/// handling it is abandoned, unregisters it.
+pub struct CancelWaiter<'a> {
+    pending: &'a PendingCancels,
+    key: WaiterKey,
+    rx: Option<oneshot::Receiver<CancelOutcome>>,
+}
+
+impl CancelWaiter<'_> {
+    /// Waits for the outcome; `None` if the engine did not answer in time.
+    pub async fn wait(mut self) -> Option<CancelOutcome> {
+        let rx = self.rx.take()?;
+        tokio::time::timeout(self.pending.timeout, rx)
+            .await
+            .ok()
+            .and_then(Result::ok)
+    }
+}
+
+impl Drop for CancelWaiter<'_> {
+    fn drop(&mut self) {
+        self.rx.take();
+        self.pending.prune(self.key);
+    }
+}
@@ -1,4 +1,10 @@
+pub mod auth;
+pub mod cancels;
+pub mod config;
+pub mod market_data;
+pub mod rate_limit;
 pub mod routes;
 pub mod server;
 
+pub use config::ApiConfig;
 pub use server::run;
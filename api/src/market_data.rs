@@ -0,0 +1,218 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::RwLock;
+
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use shared::types::{DepthLevel, DepthSnapshot, Trade};
+use shared::Event;
+
+/// Recent trades retained per pair for `/trades`.
+pub const MAX_RECENT_TRADES: usize = 500;
+
+pub const TICKER_WINDOW_HOURS: i64 = 24;
+
+/// Public market data kept in memory from the engine's event channel.
+#[derive(Default)]
+pub struct MarketDataCache {
+    pairs: RwLock<HashMap<String, PairData>>,
+}
+
+#[derive(Default)]
+struct PairData {
+    depth: Option<DepthSnapshot>,
+    /// Newest trade first.
+    trades: VecDeque<Trade>,
+    /// One bucket per minute over the ticker window, oldest first.
+    minutes: VecDeque<MinuteBar>,
+}
+
+#[derive(Debug, Clone)]
+struct MinuteBar {
+    start: DateTime<Utc>,
+    open: Decimal,
+    high: Decimal,
+    low: Decimal,
+    close: Decimal,
+    volume: Decimal,
+    quote_volume: Decimal,
+    trade_count: u64,
+}
+
+/// Rolling 24 hour statistics for a pair. Prices are absent when nothing
+/// traded in the window.
+#[derive(Debug, Clone, Serialize)]
+pub struct Ticker24h {
+    pub pair: String,
+    pub open: Option<Decimal>,
+    pub high: Option<Decimal>,
+    pub low: Option<Decimal>,
+    pub last: Option<Decimal>,
+    pub price_change: Option<Decimal>,
+    pub price_change_percent: Option<Decimal>,
+    pub volume: Decimal,
+    pub quote_volume: Decimal,
+    pub trade_count: u64,
+    pub open_time: DateTime<Utc>,
+    pub close_time: DateTime<Utc>,
+}
+
+impl MarketDataCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Folds an engine event into the cache; events other than trades and
+    /// depth snapshots are ignored. A depth snapshot older than the one held
+    /// is dropped, so a bootstrap snapshot and live events can race safely.
+    pub fn apply(&self, event: &Event) {
+        match event {
+            Event::TradeExecuted(trade) => self.record_trade(trade.clone()),
+            Event::DepthSnapshot {
+                pair,
+                bids,
+                asks,
+                ts,
+            } => {
+                let levels = |side: &[(Decimal, Decimal)]| {
+                    side.iter()
+                        .map(|&(price, quantity)| DepthLevel { price, quantity })
+                        .collect()
+                };
+                let snapshot = DepthSnapshot {
+                    pair: pair.clone(),
+                    bids: levels(bids),
+                    asks: levels(asks),
+                    timestamp: *ts,
+                };
+                let mut pairs = self.pairs.write().expect("market data lock poisoned");
+                let depth = &mut pairs.entry(pair.clone()).or_default().depth;
+                if !matches!(depth, Some(held) if held.timestamp > snapshot.timestamp) {
+                    *depth = Some(snapshot);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Adds a trade to the recent trades and ticker. A trade already held is
+    /// ignored, so live events that overlap a bootstrap count once.
+    pub fn record_trade(&self, trade: Trade) {
+        let mut pairs = self.pairs.write().expect("market data lock poisoned");
+        let data = pairs.entry(trade.pair.clone()).or_default();
+        if data.trades.iter().any(|t| t.trade_id == trade.trade_id) {
+            return;
+        }
+        data.add_to_minutes(&trade);
+        data.trades.push_front(trade);
+        data.trades.truncate(MAX_RECENT_TRADES);
+    }
+
+    /// Order book for `pair` limited to the best `limit` levels per side. A
+    /// pair the engine has not reported yet has an empty book.
+    pub fn depth(&self, pair: &str, limit: usize) -> DepthSnapshot {
+        let pairs = self.pairs.read().expect("market data lock poisoned");
+        match pairs.get(pair).and_then(|d| d.depth.clone()) {
+            Some(snapshot) => snapshot.top(limit),
+            None => DepthSnapshot {
+                pair: pair.to_string(),
+                bids: Vec::new(),
+                asks: Vec::new(),
+                timestamp: Utc::now(),
+            },
+        }
+    }
+
+    /// Most recent trades for `pair`, newest first.
+    pub fn recent_trades(&self, pair: &str, limit: usize) -> Vec<Trade> {
+        let pairs = self.pairs.read().expect("market data lock poisoned");
+        pairs
+            .get(pair)
+            .map(|d| d.trades.iter().take(limit).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn ticker(&self, pair: &str, now: DateTime<Utc>) -> Ticker24h {
+        let open_time = now - Duration::hours(TICKER_WINDOW_HOURS);
+        let pairs = self.pairs.read().expect("market data lock poisoned");
+        let bars: Vec<&MinuteBar> = pairs
+            .get(pair)
+            .map(|d| d.minutes.iter().filter(|b| b.start > open_time).collect())
+            .unwrap_or_default();
+
+        let open = bars.first().map(|b| b.open);
+        let last = bars.last().map(|b| b.close);
+        let high = bars.iter().map(|b| b.high).max();
+        let low = bars.iter().map(|b| b.low).min();
+        let price_change = open.zip(last).map(|(o, l)| l - o);
+        let price_change_percent = open
+            .zip(price_change)
+            .filter(|(o, _)| !o.is_zero())
+            .map(|(o, change)| (change / o * Decimal::ONE_HUNDRED).round_dp(4));
+
+        Ticker24h {
+            pair: pair.to_string(),
+            open,
+            high,
+            low,
+            last,
+            price_change,
+            price_change_percent,
+            volume: bars.iter().map(|b| b.volume).sum(),
+            quote_volume: bars.iter().map(|b| b.quote_volume).sum(),
+            trade_count: bars.iter().map(|b| b.trade_count).sum(),
+            open_time,
+            close_time: now,
+        }
+    }
+}
+
+impl PairData {
+    fn add_to_minutes(&mut self, trade: &Trade) {
+        let start = trade
+            .timestamp
+            .duration_trunc(Duration::minutes(1))
+            .unwrap_or(trade.timestamp);
+        let notional = trade.price * trade.quantity;
+
+        // Trades normally arrive in order, so the target bucket is the last one;
+        // a late trade is folded into the bucket it belongs to if still retained.
+        let pos = self.minutes.iter().rposition(|b| b.start <= start);
+        match pos {
+            Some(i) if self.minutes[i].start == start => {
+                let is_latest = i == self.minutes.len() - 1;
+                let bar = &mut self.minutes[i];
+                bar.high = bar.high.max(trade.price);
+                bar.low = bar.low.min(trade.price);
+                if is_latest {
+                    bar.close = trade.price;
+                }
+                bar.volume += trade.quantity;
+                bar.quote_volume += notional;
+                bar.trade_count += 1;
+            }
+            _ => {
+                let bar = MinuteBar {
+                    start,
+                    open: trade.price,
+                    high: trade.price,
+                    low: trade.price,
+                    close: trade.price,
+                    volume: trade.quantity,
+                    quote_volume: notional,
+                    trade_count: 1,
+                };
+                let at = pos.map_or(0, |i| i + 1);
+                self.minutes.insert(at, bar);
+            }
+        }
+
+        let Some(newest) = self.minutes.back().map(|b| b.start) else {
+            return;
+        };
+        let cutoff = newest - Duration::hours(TICKER_WINDOW_HOURS);
+        while self.minutes.front().is_some_and(|b| b.start <= cutoff) {
+            self.minutes.pop_front();
+        }
+    }
+}
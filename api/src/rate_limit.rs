@@ -0,0 +1,180 @@
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue, RETRY_AFTER};
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpMessage, HttpResponse};
+use redis::RateLimitDecision;
+use shared::CexError;
+use tracing::warn;
+
+use crate::auth::{AuthMethod, AuthUser};
+use crate::server::AppState;
+
+pub const HEADER_LIMIT: &str = "x-ratelimit-limit";
+pub const HEADER_REMAINING: &str = "x-ratelimit-remaining";
+
+const READ_WEIGHT: u32 = 1;
+const WRITE_WEIGHT: u32 = 2;
+const CREDENTIAL_WEIGHT: u32 = 5;
+/// The most any one request costs; every bucket must hold at least this.
+pub const MAX_ENDPOINT_WEIGHT: u32 = CREDENTIAL_WEIGHT;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BucketSpec {
+    pub capacity: u32,
+    pub refill_per_sec: f64,
+}
+
+impl BucketSpec {
+    /// A bucket that never refills would lock callers out for good, and one
+    /// smaller than a request's cost could never admit it.
+    pub fn validate(&self, name: &str) -> Result<(), CexError> {
+        if !(self.refill_per_sec.is_finite() && self.refill_per_sec > 0.0) {
+            return Err(CexError::Validation(format!(
+                "{name} refill must be a positive number of tokens per second, got {}",
+                self.refill_per_sec
+            )));
+        }
+        if self.capacity < MAX_ENDPOINT_WEIGHT {
+            return Err(CexError::Validation(format!(
+                "{name} capacity must be at least {MAX_ENDPOINT_WEIGHT}, the largest request cost, got {}",
+                self.capacity
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Token-bucket sizes for the per-IP and per-identity (user or API key) limits.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitConfig {
+    pub per_ip: BucketSpec,
+    pub per_user: BucketSpec,
+}
+
+impl RateLimitConfig {
+    pub fn validate(&self) -> Result<(), CexError> {
+        self.per_ip.validate("per-IP rate limit")?;
+        self.per_user.validate("per-user rate limit")
+    }
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            per_ip: BucketSpec {
+                capacity: 120,
+                refill_per_sec: 20.0,
+            },
+            per_user: BucketSpec {
+                capacity: 60,
+                refill_per_sec: 10.0,
+            },
+        }
+    }
+}
+
+/// Tokens charged per request. Writes and credential checks cost more than
+/// reads; returning 0 exempts an endpoint entirely.
+pub fn endpoint_weight(method: &Method, path: &str) -> u32 {
+    match (method.as_str(), path) {
+        (_, "/health") | (_, "/metrics") => 0,
+        ("POST", "/auth/login") | ("POST", "/auth/register") => CREDENTIAL_WEIGHT,
+        ("POST", _) | ("DELETE", _) => WRITE_WEIGHT,
+        _ => READ_WEIGHT,
+    }
+}
+
+/// Applied to every request, keyed by the peer address.
+pub async fn limit_by_ip(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let key = req.peer_addr().map(|addr| format!("rl:ip:{}", addr.ip()));
+    limit(req, next, key, |config| config.per_ip).await
+}
+
+/// Applied behind authentication, keyed by API key or, for bearer tokens, user.
+pub async fn limit_by_user(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let key = req
+        .extensions()
+        .get::<AuthUser>()
+        .map(|user| match user.method {
+            AuthMethod::ApiKey(key_id) => format!("rl:key:{key_id}"),
+            AuthMethod::Jwt => format!("rl:user:{}", user.user_id),
+        });
+    limit(req, next, key, |config| config.per_user).await
+}
+
+async fn limit(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+    key: Option<String>,
+    bucket: impl Fn(&RateLimitConfig) -> BucketSpec,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let cost = endpoint_weight(req.method(), req.path());
+    let state = req.app_data::<web::Data<AppState>>().cloned();
+    let (Some(key), Some(state)) = (key, state) else {
+        return next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_left_body);
+    };
+    if cost == 0 {
+        return next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_left_body);
+    }
+
+    let spec = bucket(&state.rate_limits);
+    let decision = match state
+        .redis
+        .take_tokens(&key, spec.capacity, spec.refill_per_sec, cost)
+        .await
+    {
+        Ok(decision) => decision,
+        Err(err) => {
+            // Fail open: an unavailable limiter should not take the API down with it.
+            warn!("rate limiter unavailable: {err}");
+            return next
+                .call(req)
+                .await
+                .map(ServiceResponse::map_into_left_body);
+        }
+    };
+
+    if !decision.allowed {
+        let mut resp = HttpResponse::TooManyRequests().body("rate limit exceeded");
+        let retry_after_secs = decision.retry_after_ms.div_ceil(1000).max(1);
+        resp.headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(retry_after_secs));
+        set_limit_headers(resp.headers_mut(), &decision);
+        return Ok(req.into_response(resp).map_into_right_body());
+    }
+
+    let mut resp = next.call(req).await?;
+    set_limit_headers(resp.headers_mut(), &decision);
+    Ok(resp.map_into_left_body())
+}
+
+/// Inner limiters run first on the way out, so keep the most specific
+/// (per-identity) values when both limiters apply.
+fn set_limit_headers(
+    headers: &mut actix_web::http::header::HeaderMap,
+    decision: &RateLimitDecision,
+) {
+    let limit = HeaderName::from_static(HEADER_LIMIT);
+    if headers.contains_key(&limit) {
+        return;
+    }
+    headers.insert(limit, HeaderValue::from(decision.limit));
+    headers.insert(
+        HeaderName::from_static(HEADER_REMAINING),
+        HeaderValue::from(decision.remaining),
+    );
+}
@@ -0,0 +1,137 @@
+use actix_web::{delete, get, post, web, HttpResponse, Responder};
+use redis::queues::QUEUE_ORDER_CANCEL;
+use redis::DeadLetter;
+use serde::{Deserialize, Serialize};
+use shared::types::{MarketState, MarketStateRequest, OrderSide, UserId};
+use shared::{CexError, Envelope, Event};
+use uuid::Uuid;
+
+use crate::routes::error_response;
+use crate::routes::orders::enqueue_cancel_all;
+use crate::server::AppState;
+
+pub const DEFAULT_DEAD_LETTER_LIMIT: usize = 50;
+pub const MAX_DEAD_LETTER_LIMIT: usize = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct DeadLetterQuery {
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeadLetterPage {
+    /// Total number of dead letters, not just this page.
+    pub depth: u64,
+    pub dead_letters: Vec<DeadLetter>,
+}
+
+#[get("/dead-letters")]
+pub async fn list_dead_letters_route(
+    state: web::Data<AppState>,
+    query: web::Query<DeadLetterQuery>,
+) -> impl Responder {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_DEAD_LETTER_LIMIT)
+        .clamp(1, MAX_DEAD_LETTER_LIMIT);
+    let page = async {
+        Ok::<_, CexError>(DeadLetterPage {
+            depth: state.redis.dead_letter_depth().await?,
+            dead_letters: state
+                .redis
+                .list_dead_letters(query.offset.unwrap_or(0), limit)
+                .await?,
+        })
+    };
+    match page.await {
+        Ok(page) => HttpResponse::Ok().json(page),
+        Err(err) => error_response(err),
+    }
+}
+
+#[get("/dead-letters/{id}")]
+pub async fn get_dead_letter_route(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    match state.redis.get_dead_letter(path.into_inner()).await {
+        Ok(Some(letter)) => HttpResponse::Ok().json(letter),
+        Ok(None) => error_response(CexError::NotFound("dead letter".to_string())),
+        Err(err) => error_response(err),
+    }
+}
+
+/// Pushes the original payload back onto the queue or stream it came from.
+#[post("/dead-letters/{id}/redrive")]
+pub async fn redrive_dead_letter_route(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    match state.redis.redrive_dead_letter(path.into_inner()).await {
+        Ok(Some(letter)) => HttpResponse::Ok().json(letter),
+        Ok(None) => error_response(CexError::NotFound("dead letter".to_string())),
+        Err(err) => error_response(err),
+    }
+}
+
+#[delete("/dead-letters/{id}")]
+pub async fn discard_dead_letter_route(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    match state.redis.discard_dead_letter(path.into_inner()).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => error_response(CexError::NotFound("dead letter".to_string())),
+        Err(err) => error_response(err),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AdminCancelAllRequest {
+    pub user_id: UserId,
+    pub pair: Option<String>,
+    pub side: Option<OrderSide>,
+}
+
+/// Kill switch: cancels a user's orders on their behalf.
+#[post("/orders/cancel-all")]
+pub async fn cancel_all_route(
+    state: web::Data<AppState>,
+    payload: web::Json<AdminCancelAllRequest>,
+) -> impl Responder {
+    let req = payload.into_inner();
+    match enqueue_cancel_all(&state, req.user_id, req.pair, req.side).await {
+        Ok(accepted) => HttpResponse::Accepted().json(accepted),
+        Err(err) => error_response(err),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MarketStateBody {
+    pub state: MarketState,
+}
+
+/// Moves a market between pre-open, auction, continuous, halted and closed.
+/// Queued with cancels so a backlog of new orders does not hold it up; it is
+/// not ordered against orders already in that backlog.
+#[post("/markets/{pair}/state")]
+pub async fn market_state_route(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+    payload: web::Json<MarketStateBody>,
+) -> impl Responder {
+    let request = MarketStateRequest {
+        pair: path.into_inner(),
+        state: payload.state,
+    };
+    let result = async {
+        let envelope = Envelope::new("api", Event::SetMarketState(request));
+        let body = envelope.encode(state.queue_codec)?;
+        state.redis.push_bytes(QUEUE_ORDER_CANCEL, &body).await
+    };
+    match result.await {
+        Ok(()) => HttpResponse::Accepted().json(payload.into_inner()),
+        Err(err) => error_response(err),
+    }
+}
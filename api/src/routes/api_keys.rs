@@ -0,0 +1,119 @@
+use std::net::IpAddr;
+
+use actix_web::{delete, get, post, web, HttpResponse, Responder};
+use chrono::Utc;
+use db::api_keys::{insert_api_key, list_api_keys, revoke_api_key};
+use serde::{Deserialize, Serialize};
+use shared::types::{ApiKey, ApiScope};
+use shared::CexError;
+use uuid::Uuid;
+
+use crate::auth::signing::{generate_api_key, hash_api_key};
+use crate::auth::AuthUser;
+use crate::routes::error_response;
+use crate::server::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub label: String,
+    pub scopes: Vec<ApiScope>,
+    pub allowed_ips: Option<Vec<String>>,
+}
+
+/// Returned once at creation; neither the key nor the secret can be retrieved later.
+#[derive(Debug, Serialize)]
+pub struct CreateApiKeyResponse {
+    pub key_id: Uuid,
+    pub api_key: String,
+    pub secret: String,
+    pub scopes: Vec<ApiScope>,
+    pub allowed_ips: Option<Vec<String>>,
+}
+
+#[post("/api-keys")]
+pub async fn create_api_key_route(
+    state: web::Data<AppState>,
+    user: AuthUser,
+    payload: web::Json<CreateApiKeyRequest>,
+) -> impl Responder {
+    match handle_create(&state, &user, payload.into_inner()).await {
+        Ok(resp) => HttpResponse::Created().json(resp),
+        Err(err) => error_response(err),
+    }
+}
+
+async fn handle_create(
+    state: &AppState,
+    user: &AuthUser,
+    req: CreateApiKeyRequest,
+) -> Result<CreateApiKeyResponse, CexError> {
+    user.require_interactive()?;
+    if req.scopes.is_empty() {
+        return Err(CexError::Validation(
+            "at least one scope is required".to_string(),
+        ));
+    }
+    let mut scopes = req.scopes;
+    scopes.sort_by_key(|s| s.as_str());
+    scopes.dedup();
+    let allowed_ips = req
+        .allowed_ips
+        .map(|ips| {
+            ips.iter()
+                .map(|ip| {
+                    ip.trim()
+                        .parse::<IpAddr>()
+                        .map(|ip| ip.to_string())
+                        .map_err(|_| CexError::Validation(format!("invalid ip address: {ip}")))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .transpose()?;
+
+    let api_key = generate_api_key();
+    let record = ApiKey {
+        key_id: Uuid::new_v4(),
+        user_id: user.user_id,
+        label: req.label,
+        key_hash: hash_api_key(&api_key),
+        scopes: scopes.clone(),
+        allowed_ips: allowed_ips.clone(),
+        created_at: Utc::now(),
+        revoked_at: None,
+    };
+    insert_api_key(state.db.pool(), &record).await?;
+    Ok(CreateApiKeyResponse {
+        key_id: record.key_id,
+        secret: state.signer.derive_secret(&api_key),
+        api_key,
+        scopes,
+        allowed_ips,
+    })
+}
+
+#[get("/api-keys")]
+pub async fn list_api_keys_route(state: web::Data<AppState>, user: AuthUser) -> impl Responder {
+    if let Err(err) = user.require_interactive() {
+        return error_response(err);
+    }
+    match list_api_keys(state.db.pool(), user.user_id).await {
+        Ok(keys) => HttpResponse::Ok().json(keys),
+        Err(err) => error_response(err),
+    }
+}
+
+#[delete("/api-keys/{key_id}")]
+pub async fn revoke_api_key_route(
+    state: web::Data<AppState>,
+    user: AuthUser,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    if let Err(err) = user.require_interactive() {
+        return error_response(err);
+    }
+    match revoke_api_key(state.db.pool(), user.user_id, path.into_inner()).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => error_response(CexError::NotFound("api key".to_string())),
+        Err(err) => error_response(err),
+    }
+}
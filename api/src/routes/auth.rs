@@ -0,0 +1,115 @@
+use actix_web::{post, web, HttpResponse, Responder};
+use db::users::{find_user_by_email, find_user_by_id, insert_user};
+use serde::{Deserialize, Serialize};
+use shared::auth::{TokenKind, TokenPair};
+use shared::types::{User, UserId};
+use shared::CexError;
+
+use crate::auth::password::{dummy_hash, hash_password, verify_password, MIN_PASSWORD_LEN};
+use crate::routes::error_response;
+use crate::server::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct CredentialsRequest {
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RegisterResponse {
+    pub user_id: UserId,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+#[post("/auth/register")]
+pub async fn register_route(
+    state: web::Data<AppState>,
+    payload: web::Json<CredentialsRequest>,
+) -> impl Responder {
+    match handle_register(&state, payload.into_inner()).await {
+        Ok(resp) => HttpResponse::Created().json(resp),
+        Err(err) => error_response(err),
+    }
+}
+
+async fn handle_register(
+    state: &AppState,
+    req: CredentialsRequest,
+) -> Result<RegisterResponse, CexError> {
+    let email = req.email.trim().to_lowercase();
+    if !email.contains('@') {
+        return Err(CexError::Validation("invalid email".to_string()));
+    }
+    if req.password.len() < MIN_PASSWORD_LEN {
+        return Err(CexError::Validation(format!(
+            "password must be at least {MIN_PASSWORD_LEN} characters"
+        )));
+    }
+    // Argon2 is slow by design; keep it off the async workers.
+    let hash = web::block(move || hash_password(&req.password))
+        .await
+        .map_err(|e| CexError::Internal(format!("password hashing failed: {e}")))??;
+    let user = User::new(email, hash);
+    insert_user(state.db.pool(), &user).await?;
+    Ok(RegisterResponse {
+        user_id: user.user_id,
+    })
+}
+
+#[post("/auth/login")]
+pub async fn login_route(
+    state: web::Data<AppState>,
+    payload: web::Json<CredentialsRequest>,
+) -> impl Responder {
+    match handle_login(&state, payload.into_inner()).await {
+        Ok(tokens) => HttpResponse::Ok().json(tokens),
+        Err(err) => error_response(err),
+    }
+}
+
+async fn handle_login(state: &AppState, req: CredentialsRequest) -> Result<TokenPair, CexError> {
+    let email = req.email.trim().to_lowercase();
+    let user = find_user_by_email(state.db.pool(), &email).await?;
+    // Unknown emails still pay for a verification, so timing does not tell
+    // which ones are registered.
+    let hash = user.as_ref().map_or_else(
+        || dummy_hash().to_string(),
+        |user| user.password_hash.clone(),
+    );
+    let verified = web::block(move || verify_password(&req.password, &hash))
+        .await
+        .map_err(|e| CexError::Internal(format!("password verification failed: {e}")))?;
+    match user {
+        Some(user) if verified => state.jwt.issue_pair(user.user_id),
+        _ => Err(CexError::Unauthorized(
+            "invalid email or password".to_string(),
+        )),
+    }
+}
+
+#[post("/auth/refresh")]
+pub async fn refresh_route(
+    state: web::Data<AppState>,
+    payload: web::Json<RefreshRequest>,
+) -> impl Responder {
+    match handle_refresh(&state, payload.into_inner()).await {
+        Ok(tokens) => HttpResponse::Ok().json(tokens),
+        Err(err) => error_response(err),
+    }
+}
+
+async fn handle_refresh(state: &AppState, req: RefreshRequest) -> Result<TokenPair, CexError> {
+    let claims = state.jwt.verify(&req.refresh_token, TokenKind::Refresh)?;
+    // Refuse to mint tokens for users that have since been removed.
+    if find_user_by_id(state.db.pool(), claims.sub)
+        .await?
+        .is_none()
+    {
+        return Err(CexError::Unauthorized("unknown user".to_string()));
+    }
+    state.jwt.issue_pair(claims.sub)
+}
@@ -0,0 +1,103 @@
+use actix_web::{get, web, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use db::candles::list_candles;
+use serde::Deserialize;
+use shared::types::{Candle, CandleInterval};
+use shared::CexError;
+
+use crate::market_data::MAX_RECENT_TRADES;
+use crate::routes::error_response;
+use crate::server::AppState;
+
+pub const DEFAULT_DEPTH_LIMIT: usize = 100;
+pub const MAX_DEPTH_LIMIT: usize = 1000;
+pub const DEFAULT_TRADES_LIMIT: usize = 100;
+pub const DEFAULT_KLINES_LIMIT: i64 = 500;
+pub const MAX_KLINES_LIMIT: i64 = 1000;
+
+#[derive(Debug, Deserialize)]
+pub struct PairQuery {
+    pub pair: String,
+    pub limit: Option<usize>,
+}
+
+#[get("/depth")]
+pub async fn depth_route(
+    state: web::Data<AppState>,
+    query: web::Query<PairQuery>,
+) -> impl Responder {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_DEPTH_LIMIT)
+        .clamp(1, MAX_DEPTH_LIMIT);
+    HttpResponse::Ok().json(state.market_data.depth(&query.pair, limit))
+}
+
+#[get("/trades")]
+pub async fn trades_route(
+    state: web::Data<AppState>,
+    query: web::Query<PairQuery>,
+) -> impl Responder {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_TRADES_LIMIT)
+        .clamp(1, MAX_RECENT_TRADES);
+    HttpResponse::Ok().json(state.market_data.recent_trades(&query.pair, limit))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TickerQuery {
+    pub pair: String,
+}
+
+#[get("/ticker/24hr")]
+pub async fn ticker_route(
+    state: web::Data<AppState>,
+    query: web::Query<TickerQuery>,
+) -> impl Responder {
+    HttpResponse::Ok().json(state.market_data.ticker(&query.pair, Utc::now()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct KlinesQuery {
+    pub pair: String,
+    pub interval: String,
+    /// Inclusive bounds on the bar open time, in milliseconds since the epoch.
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[get("/klines")]
+pub async fn klines_route(
+    state: web::Data<AppState>,
+    query: web::Query<KlinesQuery>,
+) -> impl Responder {
+    match handle_klines(&state, query.into_inner()).await {
+        Ok(candles) => HttpResponse::Ok().json(candles),
+        Err(err) => error_response(err),
+    }
+}
+
+async fn handle_klines(state: &AppState, query: KlinesQuery) -> Result<Vec<Candle>, CexError> {
+    let interval: CandleInterval = query.interval.parse()?;
+    let start = query.start.map(millis_to_time).transpose()?;
+    let end = query.end.map(millis_to_time).transpose()?;
+    if let (Some(start), Some(end)) = (start, end) {
+        if start > end {
+            return Err(CexError::Validation(
+                "start must not be after end".to_string(),
+            ));
+        }
+    }
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_KLINES_LIMIT)
+        .clamp(1, MAX_KLINES_LIMIT);
+    list_candles(state.db.pool(), &query.pair, interval, start, end, limit).await
+}
+
+fn millis_to_time(ms: i64) -> Result<DateTime<Utc>, CexError> {
+    DateTime::from_timestamp_millis(ms)
+        .ok_or_else(|| CexError::Validation(format!("invalid timestamp: {ms}")))
+}
@@ -0,0 +1,70 @@
+use std::fmt::Write as _;
+
+use actix_web::{get, web, HttpResponse, Responder};
+use redis::HandleLatency;
+
+use crate::routes::error_response;
+use crate::server::AppState;
+
+/// Prometheus text exposition of operational gauges. The engine latency
+/// section is left out when its summary cannot be loaded.
+#[get("/metrics")]
+pub async fn metrics(state: web::Data<AppState>) -> impl Responder {
+    let depth = match state.redis.dead_letter_depth().await {
+        Ok(depth) => depth,
+        Err(err) => return error_response(err),
+    };
+    let latency = state.redis.engine_latency().await.unwrap_or_else(|err| {
+        tracing::warn!("engine latency summary unavailable: {err}");
+        Vec::new()
+    });
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(render_metrics(depth, &latency))
+}
+
+pub fn render_metrics(dead_letter_depth: u64, engine_latency: &[HandleLatency]) -> String {
+    let mut text = format!(
+        "# HELP cex_dead_letter_depth Messages waiting in the dead letter store.\n\
+         # TYPE cex_dead_letter_depth gauge\n\
+         cex_dead_letter_depth {dead_letter_depth}\n"
+    );
+    if engine_latency.is_empty() {
+        return text;
+    }
+    text.push_str(
+        "# HELP cex_engine_handle_seconds Time the engine took to handle a queued message.\n\
+         # TYPE cex_engine_handle_seconds summary\n",
+    );
+    for latency in engine_latency {
+        let kind = &latency.kind;
+        for (quantile, micros) in [
+            ("0.5", latency.p50_us),
+            ("0.9", latency.p90_us),
+            ("0.99", latency.p99_us),
+            ("0.999", latency.p999_us),
+            ("1", latency.max_us),
+        ] {
+            let _ = writeln!(
+                text,
+                "cex_engine_handle_seconds{{kind=\"{kind}\",quantile=\"{quantile}\"}} {}",
+                seconds(micros as f64)
+            );
+        }
+        let _ = writeln!(
+            text,
+            "cex_engine_handle_seconds_sum{{kind=\"{kind}\"}} {}",
+            seconds(latency.mean_us * latency.count as f64)
+        );
+        let _ = writeln!(
+            text,
+            "cex_engine_handle_seconds_count{{kind=\"{kind}\"}} {}",
+            latency.count
+        );
+    }
+    text
+}
+
+fn seconds(micros: f64) -> f64 {
+    micros / 1_000_000.0
+}
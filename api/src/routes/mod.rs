@@ -1,12 +1,101 @@
-use actix_web::web;
+use std::fmt;
 
+use actix_web::http::StatusCode;
+use actix_web::middleware::from_fn;
+use actix_web::{web, HttpResponse, ResponseError};
+use shared::CexError;
+
+use crate::auth::{require_admin, require_auth};
+use crate::rate_limit::limit_by_user;
+
+pub mod admin;
+pub mod api_keys;
+pub mod auth;
 pub mod health;
+pub mod market_data;
 pub mod markets;
+pub mod metrics;
 pub mod orders;
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
-    cfg.service(orders::new_order_route)
-        .service(orders::cancel_order_route)
+    cfg.service(auth::register_route)
+        .service(auth::login_route)
+        .service(auth::refresh_route)
         .service(markets::list_markets)
-        .service(health::health);
+        .service(market_data::depth_route)
+        .service(market_data::trades_route)
+        .service(market_data::ticker_route)
+        .service(market_data::klines_route)
+        .service(health::health)
+        .service(metrics::metrics)
+        .service(
+            web::scope("/admin")
+                .wrap(from_fn(require_admin))
+                .service(admin::list_dead_letters_route)
+                .service(admin::get_dead_letter_route)
+                .service(admin::redrive_dead_letter_route)
+                .service(admin::discard_dead_letter_route)
+                .service(admin::cancel_all_route)
+                .service(admin::market_state_route),
+        )
+        // Everything below requires a bearer token; keep this scope last since
+        // an empty prefix matches every remaining path.
+        .service(
+            web::scope("")
+                .wrap(from_fn(limit_by_user))
+                .wrap(from_fn(require_auth))
+                .service(orders::new_order_route)
+                .service(orders::cancel_order_route)
+                .service(orders::cancel_all_route)
+                .service(orders::countdown_cancel_route)
+                .service(orders::get_order_route)
+                .service(orders::open_orders_route)
+                .service(orders::order_history_route)
+                .service(api_keys::create_api_key_route)
+                .service(api_keys::list_api_keys_route)
+                .service(api_keys::revoke_api_key_route),
+        );
+}
+
+pub(crate) fn error_response(err: CexError) -> HttpResponse {
+    ApiError(err).error_response()
+}
+
+/// Adapter so middleware and extractors can fail with a `CexError`.
+#[derive(Debug)]
+pub struct ApiError(pub CexError);
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self.0 {
+            CexError::Validation(_) => StatusCode::BAD_REQUEST,
+            CexError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            CexError::Forbidden(_) => StatusCode::FORBIDDEN,
+            CexError::NotFound(_) => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let body = match &self.0 {
+            CexError::Validation(msg)
+            | CexError::Unauthorized(msg)
+            | CexError::Forbidden(msg)
+            | CexError::NotFound(msg) => msg.clone(),
+            err => err.to_string(),
+        };
+        HttpResponse::build(self.status_code()).body(body)
+    }
+}
+
+impl From<CexError> for ApiError {
+    fn from(err: CexError) -> Self {
+        ApiError(err)
+    }
 }
@@ -1,54 +1,142 @@
-use actix_web::{post, web, HttpResponse, Responder};
+use actix_web::http::StatusCode;
+use actix_web::{get, post, web, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use db::orders::{find_order, list_open_orders, list_order_history, HistoryCursor};
 use redis::queues::{QUEUE_ORDER_CANCEL, QUEUE_ORDER_NEW};
 use redis::RedisManager;
-use serde::Deserialize;
-use shared::types::{new_order as build_new_order, CancelOrder, OrderSide, OrderType};
-use shared::{to_json, CexError, Envelope, Event};
+use serde::{Deserialize, Serialize};
+use shared::types::{
+    new_order as build_new_order, ApiScope, CancelAll, CancelOrder, CancelRejectReason,
+    CountdownCancel, NewOrder, Order, OrderGroup, OrderSide, OrderType, StopLeg, TakeProfitLeg,
+    UserId,
+};
+use shared::{CexError, Codec, Envelope, Event};
 use uuid::Uuid;
 
+use crate::auth::AuthUser;
+use crate::cancels::CancelOutcome;
+use crate::routes::error_response;
 use crate::server::AppState;
 
 #[derive(Debug, Deserialize)]
 pub struct NewOrderRequest {
-    pub user_id: Uuid,
     pub pair: String,
     pub side: OrderSide,
     pub order_type: OrderType,
+    /// Required for limit orders; ignored for market orders, which may omit it.
+    #[serde(default)]
     pub price: rust_decimal::Decimal,
+    /// Zero or omitted for market buys sized by `quote_quantity`.
+    #[serde(default)]
     pub quantity: rust_decimal::Decimal,
+    #[serde(default)]
+    pub quote_quantity: Option<rust_decimal::Decimal>,
+    #[serde(default)]
+    pub max_slippage_bps: Option<u32>,
+    #[serde(default)]
+    pub group: Option<OrderGroupRequest>,
+}
+
+impl NewOrderRequest {
+    /// Checks the fields the order type needs; the engine re-checks the rest.
+    pub fn validate(&self) -> Result<(), CexError> {
+        self.order_type.check_price(self.price)
+    }
+}
+
+/// Linked orders to place with the order; leg ids are assigned here and
+/// returned with the queued order.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OrderGroupRequest {
+    Oco {
+        stop_price: rust_decimal::Decimal,
+        #[serde(default)]
+        stop_limit_price: Option<rust_decimal::Decimal>,
+    },
+    Bracket {
+        take_profit_price: rust_decimal::Decimal,
+        stop_loss_price: rust_decimal::Decimal,
+        #[serde(default)]
+        stop_loss_limit_price: Option<rust_decimal::Decimal>,
+    },
+}
+
+impl From<OrderGroupRequest> for OrderGroup {
+    fn from(req: OrderGroupRequest) -> Self {
+        let stop = |trigger_price, limit_price| StopLeg {
+            order_id: Uuid::new_v4(),
+            trigger_price,
+            limit_price,
+        };
+        match req {
+            OrderGroupRequest::Oco {
+                stop_price,
+                stop_limit_price,
+            } => OrderGroup::Oco {
+                stop: stop(stop_price, stop_limit_price),
+            },
+            OrderGroupRequest::Bracket {
+                take_profit_price,
+                stop_loss_price,
+                stop_loss_limit_price,
+            } => OrderGroup::Bracket {
+                take_profit: TakeProfitLeg {
+                    order_id: Uuid::new_v4(),
+                    price: take_profit_price,
+                },
+                stop_loss: stop(stop_loss_price, stop_loss_limit_price),
+            },
+        }
+    }
 }
 
 #[post("/order/new")]
 pub async fn new_order_route(
     state: web::Data<AppState>,
+    user: AuthUser,
     payload: web::Json<NewOrderRequest>,
 ) -> impl Responder {
-    match handle_new_order(state.redis.clone(), payload.into_inner()).await {
-        Ok(_) => HttpResponse::Ok().finish(),
+    match handle_new_order(
+        state.redis.clone(),
+        state.queue_codec,
+        user,
+        payload.into_inner(),
+    )
+    .await
+    {
+        Ok(order) => HttpResponse::Ok().json(order),
         Err(err) => error_response(err),
     }
 }
 
 async fn handle_new_order(
     redis: std::sync::Arc<RedisManager>,
+    codec: Codec,
+    user: AuthUser,
     req: NewOrderRequest,
-) -> Result<(), CexError> {
-    let order = build_new_order(
-        req.user_id,
+) -> Result<NewOrder, CexError> {
+    user.require(ApiScope::Trade)?;
+    req.validate()?;
+    let mut order = build_new_order(
+        user.user_id,
         req.pair,
         req.side,
         req.order_type,
         req.price,
         req.quantity,
     );
-    let envelope = Envelope::new("api", Event::OrderNew(order));
-    let body = to_json(&envelope)?;
-    redis.push(QUEUE_ORDER_NEW, &body).await
+    order.quote_quantity = req.quote_quantity;
+    order.max_slippage_bps = req.max_slippage_bps;
+    order.group = req.group.map(OrderGroup::from);
+    let envelope = Envelope::new("api", Event::OrderNew(order.clone()));
+    let body = envelope.encode(codec)?;
+    redis.push_bytes(QUEUE_ORDER_NEW, &body).await?;
+    Ok(order)
 }
 
 #[derive(Debug, Deserialize)]
 pub struct CancelOrderRequest {
-    pub user_id: Uuid,
     pub order_id: Uuid,
     pub pair: String,
 }
@@ -56,36 +144,281 @@ pub struct CancelOrderRequest {
 #[post("/order/cancel")]
 pub async fn cancel_order_route(
     state: web::Data<AppState>,
+    user: AuthUser,
     payload: web::Json<CancelOrderRequest>,
 ) -> impl Responder {
-    match handle_cancel_order(state.redis.clone(), payload.into_inner()).await {
-        Ok(_) => HttpResponse::Ok().finish(),
-        Err(err) => error_response(err),
+    if let Err(err) = user.require(ApiScope::Trade) {
+        return error_response(err);
+    }
+    let order_id = payload.order_id;
+    // Register first so an engine reply racing the push is not missed.
+    let reply = state.cancels.register(user.user_id, order_id);
+    if let Err(err) =
+        enqueue_cancel(&state.redis, state.queue_codec, &user, payload.into_inner()).await
+    {
+        return error_response(err);
+    }
+    match reply.wait().await {
+        Some(CancelOutcome::Cancelled(update)) => HttpResponse::Ok().json(update),
+        Some(CancelOutcome::Rejected(rejected)) => {
+            HttpResponse::build(reject_status(rejected.reason)).json(rejected)
+        }
+        // Still queued; the outcome will show up on the order.
+        None => HttpResponse::Accepted().finish(),
     }
 }
 
-async fn handle_cancel_order(
-    redis: std::sync::Arc<RedisManager>,
+async fn enqueue_cancel(
+    redis: &RedisManager,
+    codec: Codec,
+    user: &AuthUser,
     req: CancelOrderRequest,
 ) -> Result<(), CexError> {
     let cancel = CancelOrder {
         order_id: req.order_id,
-        user_id: req.user_id,
+        user_id: user.user_id,
         pair: req.pair,
     };
-    let envelope = Envelope::new(
-        "api",
-        Event::OrderCancel {
-            order_id: cancel.order_id,
-        },
-    );
-    let body = to_json(&envelope)?;
-    redis.push(QUEUE_ORDER_CANCEL, &body).await
+    let envelope = Envelope::new("api", Event::CancelRequest(cancel));
+    let body = envelope.encode(codec)?;
+    redis.push_bytes(QUEUE_ORDER_CANCEL, &body).await
 }
 
-fn error_response(err: CexError) -> HttpResponse {
-    match err {
-        CexError::Validation(msg) => HttpResponse::BadRequest().body(msg),
-        _ => HttpResponse::InternalServerError().body(err.to_string()),
+fn reject_status(reason: CancelRejectReason) -> StatusCode {
+    match reason {
+        CancelRejectReason::UnknownOrder => StatusCode::NOT_FOUND,
+        CancelRejectReason::NotOwner => StatusCode::FORBIDDEN,
+        CancelRejectReason::AlreadyFilled | CancelRejectReason::AlreadyClosed => {
+            StatusCode::CONFLICT
+        }
     }
 }
+
+#[derive(Debug, Deserialize)]
+pub struct CancelAllRequest {
+    pub pair: Option<String>,
+    pub side: Option<OrderSide>,
+}
+
+/// Returned by cancel-all; the engine publishes a summary with this id.
+#[derive(Debug, Serialize)]
+pub struct CancelAllAccepted {
+    pub request_id: Uuid,
+}
+
+#[post("/orders/cancel-all")]
+pub async fn cancel_all_route(
+    state: web::Data<AppState>,
+    user: AuthUser,
+    payload: web::Json<CancelAllRequest>,
+) -> impl Responder {
+    if let Err(err) = user.require(ApiScope::Trade) {
+        return error_response(err);
+    }
+    let req = payload.into_inner();
+    match enqueue_cancel_all(&state, user.user_id, req.pair, req.side).await {
+        Ok(accepted) => HttpResponse::Accepted().json(accepted),
+        Err(err) => error_response(err),
+    }
+}
+
+/// Queues a mass cancel for `user_id`; shared with the admin kill switch.
+pub async fn enqueue_cancel_all(
+    state: &AppState,
+    user_id: UserId,
+    pair: Option<String>,
+    side: Option<OrderSide>,
+) -> Result<CancelAllAccepted, CexError> {
+    let request = CancelAll {
+        request_id: Uuid::new_v4(),
+        user_id,
+        pair,
+        side,
+    };
+    let request_id = request.request_id;
+    let envelope = Envelope::new("api", Event::CancelAll(request));
+    let body = envelope.encode(state.queue_codec)?;
+    state.redis.push_bytes(QUEUE_ORDER_CANCEL, &body).await?;
+    Ok(CancelAllAccepted { request_id })
+}
+
+/// Shortest and longest dead-man's switch timeouts accepted; 0 disarms.
+pub const MIN_COUNTDOWN_MS: u64 = 1_000;
+pub const MAX_COUNTDOWN_MS: u64 = 600_000;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CountdownRequest {
+    pub timeout_ms: u64,
+}
+
+/// Dead-man's switch: unless called again within `timeout_ms`, all of the
+/// caller's orders are cancelled.
+#[post("/orders/countdown-cancel")]
+pub async fn countdown_cancel_route(
+    state: web::Data<AppState>,
+    user: AuthUser,
+    payload: web::Json<CountdownRequest>,
+) -> impl Responder {
+    let timeout_ms = payload.timeout_ms;
+    let result = async {
+        user.require(ApiScope::Trade)?;
+        if timeout_ms != 0 && !(MIN_COUNTDOWN_MS..=MAX_COUNTDOWN_MS).contains(&timeout_ms) {
+            return Err(CexError::Validation(format!(
+                "timeout_ms must be 0 or between {MIN_COUNTDOWN_MS} and {MAX_COUNTDOWN_MS}"
+            )));
+        }
+        let countdown = CountdownCancel {
+            user_id: user.user_id,
+            timeout_ms,
+        };
+        let envelope = Envelope::new("api", Event::CountdownCancel(countdown));
+        let body = envelope.encode(state.queue_codec)?;
+        state.redis.push_bytes(QUEUE_ORDER_CANCEL, &body).await
+    };
+    match result.await {
+        Ok(()) => HttpResponse::Ok().json(CountdownRequest { timeout_ms }),
+        Err(err) => error_response(err),
+    }
+}
+
+/// An order as returned by the query endpoints, with the open quantity spelled out.
+#[derive(Debug, Serialize)]
+pub struct OrderView {
+    #[serde(flatten)]
+    pub order: Order,
+    pub remaining: rust_decimal::Decimal,
+}
+
+impl From<Order> for OrderView {
+    fn from(order: Order) -> Self {
+        Self {
+            remaining: order.remaining(),
+            order,
+        }
+    }
+}
+
+pub const DEFAULT_HISTORY_LIMIT: i64 = 50;
+pub const MAX_HISTORY_LIMIT: i64 = 500;
+
+#[get("/order/{order_id}")]
+pub async fn get_order_route(
+    state: web::Data<AppState>,
+    user: AuthUser,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    if let Err(err) = user.require(ApiScope::Read) {
+        return error_response(err);
+    }
+    match find_order(state.db.pool(), user.user_id, path.into_inner()).await {
+        Ok(Some(order)) => HttpResponse::Ok().json(OrderView::from(order)),
+        Ok(None) => error_response(CexError::NotFound("order".to_string())),
+        Err(err) => error_response(err),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OpenOrdersQuery {
+    pub pair: Option<String>,
+}
+
+#[get("/orders/open")]
+pub async fn open_orders_route(
+    state: web::Data<AppState>,
+    user: AuthUser,
+    query: web::Query<OpenOrdersQuery>,
+) -> impl Responder {
+    if let Err(err) = user.require(ApiScope::Read) {
+        return error_response(err);
+    }
+    match list_open_orders(state.db.pool(), user.user_id, query.pair.as_deref()).await {
+        Ok(orders) => {
+            HttpResponse::Ok().json(orders.into_iter().map(OrderView::from).collect::<Vec<_>>())
+        }
+        Err(err) => error_response(err),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OrderHistoryQuery {
+    pub pair: Option<String>,
+    pub limit: Option<i64>,
+    /// Opaque cursor from a previous page's `next_cursor`.
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrderHistoryPage {
+    pub orders: Vec<OrderView>,
+    pub next_cursor: Option<String>,
+}
+
+#[get("/orders/history")]
+pub async fn order_history_route(
+    state: web::Data<AppState>,
+    user: AuthUser,
+    query: web::Query<OrderHistoryQuery>,
+) -> impl Responder {
+    match handle_order_history(&state, &user, query.into_inner()).await {
+        Ok(page) => HttpResponse::Ok().json(page),
+        Err(err) => error_response(err),
+    }
+}
+
+async fn handle_order_history(
+    state: &AppState,
+    user: &AuthUser,
+    query: OrderHistoryQuery,
+) -> Result<OrderHistoryPage, CexError> {
+    user.require(ApiScope::Read)?;
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_HISTORY_LIMIT)
+        .clamp(1, MAX_HISTORY_LIMIT);
+    let before = query.cursor.as_deref().map(decode_cursor).transpose()?;
+    let orders = list_order_history(
+        state.db.pool(),
+        user.user_id,
+        query.pair.as_deref(),
+        before,
+        limit,
+    )
+    .await?;
+    let next_cursor = if orders.len() as i64 == limit {
+        orders.last().map(|o| {
+            encode_cursor(HistoryCursor {
+                created_at: o.created_at,
+                order_id: o.order_id,
+            })
+        })
+    } else {
+        None
+    };
+    Ok(OrderHistoryPage {
+        orders: orders.into_iter().map(OrderView::from).collect(),
+        next_cursor,
+    })
+}
+
+pub fn encode_cursor(cursor: HistoryCursor) -> String {
+    format!(
+        "{}_{}",
+        cursor.created_at.timestamp_micros(),
+        cursor.order_id.simple()
+    )
+}
+
+pub fn decode_cursor(raw: &str) -> Result<HistoryCursor, CexError> {
+    let invalid = || CexError::Validation("invalid cursor".to_string());
+    let (micros, id) = raw.split_once('_').ok_or_else(invalid)?;
+    let created_at = micros
+        .parse()
+        .ok()
+        .and_then(DateTime::<Utc>::from_timestamp_micros)
+        .ok_or_else(invalid)?;
+    let order_id = Uuid::parse_str(id).map_err(|_| invalid())?;
+    Ok(HistoryCursor {
+        created_at,
+        order_id,
+    })
+}
@@ -1,19 +1,64 @@
 use actix_cors::Cors;
-use actix_web::{middleware::Logger, web, App, HttpServer};
+use actix_web::middleware::{from_fn, Logger};
+use actix_web::{web, App, HttpServer};
+use chrono::Utc;
+use db::Db;
 use redis::RedisManager;
-use shared::CexError;
+use shared::auth::JwtKeys;
+use shared::utils::Backoff;
+use shared::{CexError, Codec, Envelope, Event};
 use std::sync::Arc;
+use std::time::Duration;
+use tokio_stream::StreamExt;
 use tracing::info;
 
+use crate::auth::signing::ApiKeySigner;
+use crate::cancels::PendingCancels;
+use crate::config::ApiConfig;
+use crate::market_data::{MarketDataCache, MAX_RECENT_TRADES, TICKER_WINDOW_HOURS};
+use crate::rate_limit::{limit_by_ip, RateLimitConfig};
 use crate::routes;
 
 #[derive(Clone)]
 pub struct AppState {
     pub redis: Arc<RedisManager>,
+    /// Encoding used for envelopes pushed onto the engine queues.
+    pub queue_codec: Codec,
+    pub db: Arc<Db>,
+    pub jwt: Arc<JwtKeys>,
+    pub signer: Arc<ApiKeySigner>,
+    pub admin_token: Option<Arc<str>>,
+    pub rate_limits: RateLimitConfig,
+    /// Depth, recent trades and ticker stats fed from the event channel.
+    pub market_data: Arc<MarketDataCache>,
+    /// Cancel requests waiting for the engine to accept or reject them.
+    pub cancels: Arc<PendingCancels>,
 }
 
-pub async fn run(bind_addr: &str, redis_url: &str) -> Result<(), CexError> {
-    let redis = Arc::new(RedisManager::new(redis_url).await?);
+pub async fn run(config: ApiConfig) -> Result<(), CexError> {
+    let redis = Arc::new(RedisManager::new(&config.redis_url).await?);
+    let db = Arc::new(Db::new(&config.database_url, 5).await?);
+    db::migrate(db.pool()).await?;
+    let jwt = Arc::new(JwtKeys::with_ttls(
+        config.jwt_secret.as_bytes(),
+        config.access_ttl_secs,
+        config.refresh_ttl_secs,
+    ));
+    let signer = Arc::new(ApiKeySigner::new(config.api_key_secret.as_bytes()));
+    let admin_token: Option<Arc<str>> = config.admin_token.as_deref().map(Arc::from);
+    let queue_codec = config.queue_codec;
+    let rate_limits = config.rate_limits;
+    let bind_addr = config.bind_addr.as_str();
+    let market_data = Arc::new(MarketDataCache::new());
+    let cancels = Arc::new(PendingCancels::new(Duration::from_millis(
+        config.cancel_timeout_ms,
+    )));
+    spawn_event_feed(
+        redis.clone(),
+        db.clone(),
+        market_data.clone(),
+        cancels.clone(),
+    );
 
     info!(%bind_addr, "starting api server");
     HttpServer::new(move || {
@@ -21,7 +66,16 @@ pub async fn run(bind_addr: &str, redis_url: &str) -> Result<(), CexError> {
         App::new()
             .app_data(web::Data::new(AppState {
                 redis: redis.clone(),
+                queue_codec,
+                db: db.clone(),
+                jwt: jwt.clone(),
+                signer: signer.clone(),
+                admin_token: admin_token.clone(),
+                rate_limits,
+                market_data: market_data.clone(),
+                cancels: cancels.clone(),
             }))
+            .wrap(from_fn(limit_by_ip))
             .wrap(Logger::default())
             .wrap(cors)
             .configure(routes::configure)
@@ -32,3 +86,99 @@ pub async fn run(bind_addr: &str, redis_url: &str) -> Result<(), CexError> {
     .await
     .map_err(|e| CexError::Internal(format!("server error: {e}")))
 }
+
+/// How far back a market's log is searched for its latest depth snapshot.
+const DEPTH_BOOTSTRAP_SCAN: usize = 200;
+
+/// Keeps the market data cache up to date and resolves pending cancels from
+/// the engine's event channel for the lifetime of the process, resubscribing
+/// whenever the subscription drops.
+fn spawn_event_feed(
+    redis: Arc<RedisManager>,
+    db: Arc<Db>,
+    market_data: Arc<MarketDataCache>,
+    cancels: Arc<PendingCancels>,
+) {
+    tokio::spawn(async move {
+        let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_secs(5));
+        let mut trades_loaded = false;
+        loop {
+            match redis.subscribe_events().await {
+                Ok(sub) => {
+                    // Subscribed first, so nothing published while loading is lost.
+                    load_depth(&redis, &market_data).await;
+                    if !trades_loaded {
+                        trades_loaded = load_trades(&db, &market_data).await;
+                    }
+                    backoff.reset();
+                    let mut stream = redis::manager::subscriber_stream(sub);
+                    while let Some(msg) = stream.next().await {
+                        match msg {
+                            Ok(payload) => match Envelope::decode(payload.as_bytes()) {
+                                Ok(envelope) => {
+                                    market_data.apply(&envelope.event);
+                                    cancels.resolve(&envelope.event);
+                                }
+                                Err(err) => tracing::warn!("skipping undecodable event: {err}"),
+                            },
+                            Err(err) => {
+                                tracing::error!("event subscriber error: {err}");
+                                break;
+                            }
+                        }
+                    }
+                    tracing::warn!("event subscription ended, resubscribing");
+                }
+                Err(err) => tracing::error!("failed to subscribe to events: {err}"),
+            }
+            tokio::time::sleep(backoff.next_delay()).await;
+        }
+    });
+}
+
+/// Seeds recent trades and 24h tickers from the trades table; returns whether
+/// it succeeded. Runs once, as trades older than those retained would be
+/// counted twice by a reload.
+async fn load_trades(db: &Db, market_data: &MarketDataCache) -> bool {
+    let since = Utc::now() - chrono::Duration::hours(TICKER_WINDOW_HOURS);
+    match db::trades::list_recent_trades(db.pool(), since, MAX_RECENT_TRADES as i64).await {
+        Ok(trades) => {
+            for trade in trades {
+                market_data.record_trade(trade);
+            }
+            true
+        }
+        Err(err) => {
+            tracing::warn!("failed to load trades for bootstrap: {err}");
+            false
+        }
+    }
+}
+
+/// Seeds the cache with each market's latest depth snapshot from its event
+/// log, so `/depth` is right before the next order arrives.
+async fn load_depth(redis: &RedisManager, market_data: &MarketDataCache) {
+    let markets = match redis.markets_with_logs().await {
+        Ok(markets) => markets,
+        Err(err) => {
+            tracing::warn!("failed to list markets for depth bootstrap: {err}");
+            return;
+        }
+    };
+    for market in markets {
+        let entries = match redis.read_market_tail(&market, DEPTH_BOOTSTRAP_SCAN).await {
+            Ok(entries) => entries,
+            Err(err) => {
+                tracing::warn!(%market, "failed to read depth for bootstrap: {err}");
+                continue;
+            }
+        };
+        let latest = entries
+            .iter()
+            .filter_map(|entry| Envelope::decode(entry.payload.as_bytes()).ok())
+            .find(|envelope| matches!(envelope.event, Event::DepthSnapshot { .. }));
+        if let Some(envelope) = latest {
+            market_data.apply(&envelope.event);
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use api::cancels::{CancelOutcome, PendingCancels};
+use chrono::Utc;
+use rust_decimal::Decimal;
+use shared::types::{CancelOrder, CancelRejectReason, CancelRejected, OrderStatus, OrderUpdate};
+use shared::Event;
+use uuid::Uuid;
+
+fn cancelled(user_id: Uuid, order_id: Uuid) -> Event {
+    Event::OrderUpdate(OrderUpdate {
+        order_id,
+        user_id,
+        pair: "SOLUSDC".to_string(),
+        status: OrderStatus::Cancelled,
+        quantity: Decimal::ONE,
+        filled: Decimal::ZERO,
+        avg_price: Decimal::ZERO,
+        reason: None,
+        ts: Utc::now(),
+    })
+}
+
+fn rejected(user_id: Uuid, order_id: Uuid, reason: CancelRejectReason) -> Event {
+    let cancel = CancelOrder {
+        order_id,
+        user_id,
+        pair: "SOLUSDC".to_string(),
+    };
+    Event::CancelRejected(CancelRejected::new(&cancel, reason))
+}
+
+#[actix_rt::test]
+async fn rejection_only_answers_the_requester() {
+    let cancels = PendingCancels::new(Duration::from_secs(1));
+    let (owner, stranger, order_id) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+    let owner_reply = cancels.register(owner, order_id);
+    let stranger_reply = cancels.register(stranger, order_id);
+
+    cancels.resolve(&rejected(stranger, order_id, CancelRejectReason::NotOwner));
+    match stranger_reply.wait().await {
+        Some(CancelOutcome::Rejected(r)) => assert_eq!(r.reason, CancelRejectReason::NotOwner),
+        other => panic!("unexpected outcome: {other:?}"),
+    }
+    assert_eq!(cancels.waiting(), 1);
+
+    cancels.resolve(&cancelled(owner, order_id));
+    match owner_reply.wait().await {
+        Some(CancelOutcome::Cancelled(u)) => assert_eq!(u.order_id, order_id),
+        other => panic!("unexpected outcome: {other:?}"),
+    }
+    assert_eq!(cancels.waiting(), 0);
+}
+
+#[actix_rt::test]
+async fn unanswered_cancels_time_out_and_are_forgotten() {
+    let cancels = PendingCancels::new(Duration::from_millis(20));
+    let (user, order_id) = (Uuid::new_v4(), Uuid::new_v4());
+    let reply = cancels.register(user, order_id);
+    // Other orders' events do not resolve it.
+    cancels.resolve(&cancelled(user, Uuid::new_v4()));
+
+    assert!(reply.wait().await.is_none());
+    assert_eq!(cancels.waiting(), 0);
+}
+
+#[actix_rt::test]
+async fn abandoned_cancels_are_forgotten() {
+    let cancels = PendingCancels::new(Duration::from_secs(1));
+    let (user, order_id) = (Uuid::new_v4(), Uuid::new_v4());
+    let reply = cancels.register(user, order_id);
+    let other = cancels.register(user, order_id);
+    assert_eq!(cancels.waiting(), 2);
+
+    // As when the request is dropped mid-wait.
+    let _ = tokio::time::timeout(Duration::from_millis(10), reply.wait()).await;
+    assert_eq!(cancels.waiting(), 1);
+    drop(other);
+    assert_eq!(cancels.waiting(), 0);
+}
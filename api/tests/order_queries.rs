@@ -0,0 +1,46 @@
+use chrono::{TimeZone, Utc};
+use db::orders::HistoryCursor;
+use rust_decimal::Decimal;
+use shared::types::{new_order, Order, OrderSide, OrderType};
+use uuid::Uuid;
+
+use api::routes::orders::{decode_cursor, encode_cursor, OrderView};
+
+#[test]
+fn history_cursor_roundtrips() {
+    let cursor = HistoryCursor {
+        created_at: Utc.timestamp_micros(1_700_000_000_123_456).unwrap(),
+        order_id: Uuid::new_v4(),
+    };
+    assert_eq!(decode_cursor(&encode_cursor(cursor)).unwrap(), cursor);
+}
+
+#[test]
+fn rejects_malformed_cursors() {
+    for raw in [
+        "",
+        "abc",
+        "123",
+        "123_not-a-uuid",
+        "x_00000000000000000000000000000000",
+    ] {
+        assert!(decode_cursor(raw).is_err(), "{raw} should be rejected");
+    }
+}
+
+#[test]
+fn order_view_reports_remaining_quantity() {
+    let mut order = Order::from_new(new_order(
+        Uuid::new_v4(),
+        "SOLUSDC".to_string(),
+        OrderSide::Sell,
+        OrderType::Limit,
+        Decimal::new(30, 0),
+        Decimal::new(10, 0),
+    ));
+    order.filled = Decimal::new(4, 0);
+    let body = serde_json::to_value(OrderView::from(order)).unwrap();
+    assert_eq!(body["remaining"], "6");
+    assert_eq!(body["filled"], "4");
+    assert_eq!(body["status"], "new");
+}
@@ -0,0 +1,13 @@
+use api::auth::password::{dummy_hash, hash_password, verify_password};
+
+#[test]
+fn dummy_hash_is_a_real_hash_no_password_matches() {
+    let dummy = dummy_hash();
+    assert!(dummy.starts_with("$argon2"));
+    assert_eq!(dummy, dummy_hash());
+    assert!(!verify_password("", dummy));
+    assert!(!verify_password("password123", dummy));
+
+    let hash = hash_password("password123").unwrap();
+    assert!(verify_password("password123", &hash));
+}
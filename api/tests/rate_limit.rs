@@ -0,0 +1,54 @@
+use actix_web::http::Method;
+use api::rate_limit::{endpoint_weight, BucketSpec, RateLimitConfig, MAX_ENDPOINT_WEIGHT};
+use redis::RedisManager;
+use uuid::Uuid;
+
+#[test]
+fn health_is_exempt_and_auth_is_expensive() {
+    assert_eq!(endpoint_weight(&Method::GET, "/health"), 0);
+    assert_eq!(endpoint_weight(&Method::GET, "/markets"), 1);
+    assert!(
+        endpoint_weight(&Method::POST, "/auth/login")
+            > endpoint_weight(&Method::POST, "/order/new")
+    );
+}
+
+#[test]
+fn buckets_must_refill_and_hold_the_costliest_request() {
+    assert!(RateLimitConfig::default().validate().is_ok());
+    let bucket = |capacity, refill_per_sec| BucketSpec {
+        capacity,
+        refill_per_sec,
+    };
+    assert!(bucket(10, 0.0).validate("test").is_err());
+    assert!(bucket(10, -1.0).validate("test").is_err());
+    assert!(bucket(10, f64::NAN).validate("test").is_err());
+    assert!(bucket(MAX_ENDPOINT_WEIGHT - 1, 1.0)
+        .validate("test")
+        .is_err());
+    assert!(bucket(MAX_ENDPOINT_WEIGHT, 0.5).validate("test").is_ok());
+}
+
+#[tokio::test]
+async fn bucket_denies_once_drained_and_reports_retry_after() {
+    // Requires a running Redis at REDIS_URL; skip gracefully if not available
+    let redis_url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string());
+    let redis = match RedisManager::new(&redis_url).await {
+        Ok(r) if r.ping().await.is_ok() => r,
+        _ => {
+            eprintln!("skipping: redis not available at {redis_url}");
+            return;
+        }
+    };
+    let key = format!("rl:test:{}", Uuid::new_v4());
+
+    for expected_remaining in [2, 0] {
+        let decision = redis.take_tokens(&key, 4, 1.0, 2).await.unwrap();
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, expected_remaining);
+    }
+
+    let denied = redis.take_tokens(&key, 4, 1.0, 2).await.unwrap();
+    assert!(!denied.allowed);
+    assert!(denied.retry_after_ms > 0 && denied.retry_after_ms <= 2_000);
+}
@@ -0,0 +1,90 @@
+use api::auth::signing::{
+    check_recv_window, hash_api_key, sign, verify_signature, ApiKeySigner, MAX_FUTURE_SKEW_MS,
+    MAX_RECV_WINDOW_MS, SIGNATURE_NONCE_TTL_MS,
+};
+
+const NOW_MS: i64 = 1_700_000_000_000;
+
+#[test]
+fn signature_covers_timestamp_method_path_and_body() {
+    let secret = ApiKeySigner::new(b"master").derive_secret("key");
+    let body = br#"{"pair":"SOLUSDC"}"#;
+    let sig = sign(&secret, NOW_MS, "POST", "/order/new", body);
+
+    assert!(verify_signature(
+        &secret,
+        NOW_MS,
+        "POST",
+        "/order/new",
+        body,
+        &sig
+    ));
+    assert!(!verify_signature(
+        &secret,
+        NOW_MS + 1,
+        "POST",
+        "/order/new",
+        body,
+        &sig
+    ));
+    assert!(!verify_signature(
+        &secret,
+        NOW_MS,
+        "GET",
+        "/order/new",
+        body,
+        &sig
+    ));
+    assert!(!verify_signature(
+        &secret,
+        NOW_MS,
+        "POST",
+        "/order/cancel",
+        body,
+        &sig
+    ));
+    assert!(!verify_signature(
+        &secret,
+        NOW_MS,
+        "POST",
+        "/order/new",
+        b"{}",
+        &sig
+    ));
+    assert!(!verify_signature(
+        &secret,
+        NOW_MS,
+        "POST",
+        "/order/new",
+        body,
+        "not-hex"
+    ));
+}
+
+#[test]
+fn derived_secrets_depend_on_master_and_key() {
+    let a = ApiKeySigner::new(b"master-a");
+    let b = ApiKeySigner::new(b"master-b");
+    assert_eq!(a.derive_secret("key"), a.derive_secret("key"));
+    assert_ne!(a.derive_secret("key"), a.derive_secret("other"));
+    assert_ne!(a.derive_secret("key"), b.derive_secret("key"));
+    assert_ne!(hash_api_key("key"), a.derive_secret("key"));
+}
+
+#[test]
+fn recv_window_rejects_stale_and_future_timestamps() {
+    assert!(check_recv_window(NOW_MS - 4_000, 5_000, NOW_MS).is_ok());
+    assert!(check_recv_window(NOW_MS - 6_000, 5_000, NOW_MS).is_err());
+    assert!(check_recv_window(NOW_MS + 5_000, 5_000, NOW_MS).is_err());
+    assert!(check_recv_window(NOW_MS, MAX_RECV_WINDOW_MS + 1, NOW_MS).is_err());
+}
+
+#[test]
+fn nonces_outlive_every_window_a_timestamp_can_be_accepted_in() {
+    // Earliest use: the timestamp is as far ahead of the clock as allowed.
+    let first_use = NOW_MS - MAX_FUTURE_SKEW_MS;
+    assert!(check_recv_window(NOW_MS, 1, first_use).is_ok());
+    // Once the nonce expires, even the widest window has closed.
+    let expired = first_use + SIGNATURE_NONCE_TTL_MS + 1;
+    assert!(check_recv_window(NOW_MS, MAX_RECV_WINDOW_MS, expired).is_err());
+}
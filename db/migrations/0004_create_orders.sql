@@ -0,0 +1,18 @@
+-- Migration: order state read model projected from engine events
+CREATE TABLE IF NOT EXISTS orders (
+    order_id UUID PRIMARY KEY,
+    user_id UUID NOT NULL,
+    pair TEXT NOT NULL,
+    side TEXT NOT NULL,
+    order_type TEXT NOT NULL,
+    price NUMERIC NOT NULL,
+    quantity NUMERIC NOT NULL,
+    filled NUMERIC NOT NULL DEFAULT 0,
+    status TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS orders_user_history_idx ON orders (user_id, created_at DESC, order_id DESC);
+CREATE INDEX IF NOT EXISTS orders_user_open_idx ON orders (user_id, pair)
+    WHERE status IN ('new', 'partially_filled');
@@ -0,0 +1,16 @@
+-- Migration: OHLCV candles aggregated from executed trades
+CREATE TABLE IF NOT EXISTS candles (
+    pair TEXT NOT NULL,
+    interval TEXT NOT NULL,
+    open_time TIMESTAMPTZ NOT NULL,
+    open NUMERIC NOT NULL,
+    high NUMERIC NOT NULL,
+    low NUMERIC NOT NULL,
+    close NUMERIC NOT NULL,
+    volume NUMERIC NOT NULL,
+    quote_volume NUMERIC NOT NULL,
+    trade_count BIGINT NOT NULL,
+    first_trade_at TIMESTAMPTZ NOT NULL,
+    last_trade_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (pair, interval, open_time)
+);
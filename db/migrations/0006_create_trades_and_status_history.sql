@@ -0,0 +1,30 @@
+-- Migration: normalized trades and per-order status transitions
+CREATE TABLE IF NOT EXISTS trades (
+    trade_id UUID PRIMARY KEY,
+    pair TEXT NOT NULL,
+    price NUMERIC NOT NULL,
+    quantity NUMERIC NOT NULL,
+    buy_order_id UUID NOT NULL,
+    sell_order_id UUID NOT NULL,
+    -- Resolved from the orders read model; NULL if the order was never projected
+    buy_user_id UUID,
+    sell_user_id UUID,
+    executed_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS trades_pair_time_idx ON trades (pair, executed_at DESC);
+CREATE INDEX IF NOT EXISTS trades_buy_user_idx ON trades (buy_user_id, executed_at DESC);
+CREATE INDEX IF NOT EXISTS trades_sell_user_idx ON trades (sell_user_id, executed_at DESC);
+CREATE INDEX IF NOT EXISTS trades_buy_order_idx ON trades (buy_order_id);
+CREATE INDEX IF NOT EXISTS trades_sell_order_idx ON trades (sell_order_id);
+
+CREATE TABLE IF NOT EXISTS order_status_history (
+    id BIGSERIAL PRIMARY KEY,
+    order_id UUID NOT NULL REFERENCES orders (order_id),
+    status TEXT NOT NULL,
+    filled NUMERIC NOT NULL,
+    changed_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS order_status_history_order_idx
+    ON order_status_history (order_id, changed_at, id);
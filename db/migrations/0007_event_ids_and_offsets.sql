@@ -0,0 +1,9 @@
+-- Migration: deduplicate events by id and track consumer positions
+ALTER TABLE events ADD COLUMN IF NOT EXISTS event_id UUID;
+CREATE UNIQUE INDEX IF NOT EXISTS events_event_id_idx ON events (event_id);
+
+CREATE TABLE IF NOT EXISTS consumer_offsets (
+    consumer TEXT PRIMARY KEY,
+    stream_id TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,4 @@
+-- Migration: average fill price and reason codes from engine lifecycle events
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS avg_price NUMERIC NOT NULL DEFAULT 0;
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS reason TEXT;
+ALTER TABLE order_status_history ADD COLUMN IF NOT EXISTS reason TEXT;
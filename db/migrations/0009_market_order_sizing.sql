@@ -0,0 +1,3 @@
+-- Migration: quote-sized market orders and per-order slippage bands
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS quote_quantity NUMERIC;
+ALTER TABLE orders ADD COLUMN IF NOT EXISTS max_slippage_bps INTEGER;
@@ -0,0 +1,107 @@
+use chrono::{DateTime, Utc};
+use shared::types::{ApiKey, ApiScope, UserId};
+use shared::CexError;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const API_KEY_COLUMNS: &str =
+    "key_id, user_id, label, key_hash, scopes, allowed_ips, created_at, revoked_at";
+
+pub async fn insert_api_key(pool: &PgPool, key: &ApiKey) -> Result<(), CexError> {
+    let scopes: Vec<&str> = key.scopes.iter().map(ApiScope::as_str).collect();
+    sqlx::query(
+        "INSERT INTO api_keys (key_id, user_id, label, key_hash, scopes, allowed_ips, created_at) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7)",
+    )
+    .bind(key.key_id)
+    .bind(key.user_id)
+    .bind(&key.label)
+    .bind(&key.key_hash)
+    .bind(scopes)
+    .bind(&key.allowed_ips)
+    .bind(key.created_at)
+    .execute(pool)
+    .await
+    .map_err(|e| CexError::Internal(format!("insert api key failed: {e}")))?;
+    Ok(())
+}
+
+/// Looks up a non-revoked key by the hash of its public identifier.
+pub async fn find_active_api_key(
+    pool: &PgPool,
+    key_hash: &str,
+) -> Result<Option<ApiKey>, CexError> {
+    sqlx::query_as::<_, ApiKeyRow>(&format!(
+        "SELECT {API_KEY_COLUMNS} FROM api_keys WHERE key_hash = $1 AND revoked_at IS NULL"
+    ))
+    .bind(key_hash)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| CexError::Internal(format!("find api key failed: {e}")))?
+    .map(ApiKey::try_from)
+    .transpose()
+}
+
+pub async fn list_api_keys(pool: &PgPool, user_id: UserId) -> Result<Vec<ApiKey>, CexError> {
+    sqlx::query_as::<_, ApiKeyRow>(&format!(
+        "SELECT {API_KEY_COLUMNS} FROM api_keys WHERE user_id = $1 ORDER BY created_at"
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| CexError::Internal(format!("list api keys failed: {e}")))?
+    .into_iter()
+    .map(ApiKey::try_from)
+    .collect()
+}
+
+/// Revokes a key owned by `user_id`; returns false when no such active key exists.
+pub async fn revoke_api_key(
+    pool: &PgPool,
+    user_id: UserId,
+    key_id: Uuid,
+) -> Result<bool, CexError> {
+    let result = sqlx::query(
+        "UPDATE api_keys SET revoked_at = now() \
+         WHERE key_id = $1 AND user_id = $2 AND revoked_at IS NULL",
+    )
+    .bind(key_id)
+    .bind(user_id)
+    .execute(pool)
+    .await
+    .map_err(|e| CexError::Internal(format!("revoke api key failed: {e}")))?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[derive(sqlx::FromRow)]
+struct ApiKeyRow {
+    key_id: Uuid,
+    user_id: UserId,
+    label: String,
+    key_hash: String,
+    scopes: Vec<String>,
+    allowed_ips: Option<Vec<String>>,
+    created_at: DateTime<Utc>,
+    revoked_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<ApiKeyRow> for ApiKey {
+    type Error = CexError;
+
+    fn try_from(row: ApiKeyRow) -> Result<Self, Self::Error> {
+        Ok(ApiKey {
+            key_id: row.key_id,
+            user_id: row.user_id,
+            label: row.label,
+            key_hash: row.key_hash,
+            scopes: row
+                .scopes
+                .iter()
+                .map(|s| s.parse())
+                .collect::<Result<_, _>>()?,
+            allowed_ips: row.allowed_ips,
+            created_at: row.created_at,
+            revoked_at: row.revoked_at,
+        })
+    }
+}
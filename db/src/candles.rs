@@ -0,0 +1,114 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use shared::types::{Candle, CandleInterval, Trade};
+use shared::CexError;
+use sqlx::{PgExecutor, PgPool};
+
+const CANDLE_COLUMNS: &str = "pair, interval, open_time, open, high, low, close, volume, \
+     quote_volume, trade_count, first_trade_at, last_trade_at";
+
+/// Folds a trade into its `interval` bucket and returns the updated candle.
+/// Open and close follow trade time rather than arrival order, so a late trade
+/// only moves them if it is the earliest or latest in its bucket.
+pub async fn apply_trade<'e>(
+    executor: impl PgExecutor<'e>,
+    interval: CandleInterval,
+    trade: &Trade,
+) -> Result<Candle, CexError> {
+    sqlx::query_as::<_, CandleRow>(&format!(
+        "INSERT INTO candles ({CANDLE_COLUMNS}) \
+         VALUES ($1, $2, $3, $4, $4, $4, $4, $5, $6, 1, $7, $7) \
+         ON CONFLICT (pair, interval, open_time) DO UPDATE SET \
+             open = CASE WHEN EXCLUDED.first_trade_at < candles.first_trade_at \
+                 THEN EXCLUDED.open ELSE candles.open END, \
+             high = GREATEST(candles.high, EXCLUDED.high), \
+             low = LEAST(candles.low, EXCLUDED.low), \
+             close = CASE WHEN EXCLUDED.last_trade_at >= candles.last_trade_at \
+                 THEN EXCLUDED.close ELSE candles.close END, \
+             volume = candles.volume + EXCLUDED.volume, \
+             quote_volume = candles.quote_volume + EXCLUDED.quote_volume, \
+             trade_count = candles.trade_count + 1, \
+             first_trade_at = LEAST(candles.first_trade_at, EXCLUDED.first_trade_at), \
+             last_trade_at = GREATEST(candles.last_trade_at, EXCLUDED.last_trade_at) \
+         RETURNING {CANDLE_COLUMNS}"
+    ))
+    .bind(&trade.pair)
+    .bind(interval.as_str())
+    .bind(interval.open_time(trade.timestamp))
+    .bind(trade.price)
+    .bind(trade.quantity)
+    .bind(trade.price * trade.quantity)
+    .bind(trade.timestamp)
+    .fetch_one(executor)
+    .await
+    .map_err(|e| CexError::Internal(format!("upsert candle failed: {e}")))?
+    .try_into()
+}
+
+/// Candles opening within `[start, end]`, oldest first.
+pub async fn list_candles(
+    pool: &PgPool,
+    pair: &str,
+    interval: CandleInterval,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+    limit: i64,
+) -> Result<Vec<Candle>, CexError> {
+    // Without a start, return the most recent `limit` bars.
+    let rows = sqlx::query_as::<_, CandleRow>(&format!(
+        "SELECT {CANDLE_COLUMNS} FROM ( \
+             SELECT {CANDLE_COLUMNS} FROM candles \
+             WHERE pair = $1 AND interval = $2 \
+               AND ($3::TIMESTAMPTZ IS NULL OR open_time >= $3) \
+               AND ($4::TIMESTAMPTZ IS NULL OR open_time <= $4) \
+             ORDER BY CASE WHEN $3::TIMESTAMPTZ IS NULL THEN open_time END DESC, open_time \
+             LIMIT $5 \
+         ) page ORDER BY open_time"
+    ))
+    .bind(pair)
+    .bind(interval.as_str())
+    .bind(start)
+    .bind(end)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| CexError::Internal(format!("list candles failed: {e}")))?;
+    rows.into_iter().map(Candle::try_from).collect()
+}
+
+#[derive(sqlx::FromRow)]
+struct CandleRow {
+    pair: String,
+    interval: String,
+    open_time: DateTime<Utc>,
+    open: Decimal,
+    high: Decimal,
+    low: Decimal,
+    close: Decimal,
+    volume: Decimal,
+    quote_volume: Decimal,
+    trade_count: i64,
+    first_trade_at: DateTime<Utc>,
+    last_trade_at: DateTime<Utc>,
+}
+
+impl TryFrom<CandleRow> for Candle {
+    type Error = CexError;
+
+    fn try_from(row: CandleRow) -> Result<Self, Self::Error> {
+        Ok(Candle {
+            pair: row.pair,
+            interval: row.interval.parse()?,
+            open_time: row.open_time,
+            open: row.open,
+            high: row.high,
+            low: row.low,
+            close: row.close,
+            volume: row.volume,
+            quote_volume: row.quote_volume,
+            trade_count: row.trade_count,
+            first_trade_at: row.first_trade_at,
+            last_trade_at: row.last_trade_at,
+        })
+    }
+}
@@ -0,0 +1,54 @@
+use shared::CexError;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+/// Inserts a batch of raw events in one statement, skipping ids that are
+/// already stored. Returns the ids that were actually inserted.
+pub async fn insert_events<'e>(
+    executor: impl PgExecutor<'e>,
+    events: &[(Uuid, &str)],
+) -> Result<Vec<Uuid>, CexError> {
+    let (ids, payloads): (Vec<Uuid>, Vec<&str>) = events.iter().copied().unzip();
+    sqlx::query_scalar(
+        "INSERT INTO events (event_id, payload) \
+         SELECT * FROM UNNEST($1::UUID[], $2::TEXT[]) \
+         ON CONFLICT (event_id) DO NOTHING \
+         RETURNING event_id",
+    )
+    .bind(ids)
+    .bind(payloads)
+    .fetch_all(executor)
+    .await
+    .map_err(|e| CexError::Internal(format!("insert events failed: {e}")))
+}
+
+/// Last stream id committed by `consumer`, if it has run before.
+pub async fn load_offset<'e>(
+    executor: impl PgExecutor<'e>,
+    consumer: &str,
+) -> Result<Option<String>, CexError> {
+    sqlx::query_scalar("SELECT stream_id FROM consumer_offsets WHERE consumer = $1")
+        .bind(consumer)
+        .fetch_optional(executor)
+        .await
+        .map_err(|e| CexError::Internal(format!("load offset failed: {e}")))
+}
+
+/// Records how far `consumer` has got. Call it in the same transaction as the
+/// writes for the entries up to `stream_id`.
+pub async fn store_offset<'e>(
+    executor: impl PgExecutor<'e>,
+    consumer: &str,
+    stream_id: &str,
+) -> Result<(), CexError> {
+    sqlx::query(
+        "INSERT INTO consumer_offsets (consumer, stream_id) VALUES ($1, $2) \
+         ON CONFLICT (consumer) DO UPDATE SET stream_id = EXCLUDED.stream_id, updated_at = now()",
+    )
+    .bind(consumer)
+    .bind(stream_id)
+    .execute(executor)
+    .await
+    .map_err(|e| CexError::Internal(format!("store offset failed: {e}")))?;
+    Ok(())
+}
@@ -1,6 +1,13 @@
+pub mod api_keys;
+pub mod candles;
+pub mod events;
+pub mod orders;
+pub mod trades;
+pub mod users;
+
 use shared::CexError;
 use sqlx::postgres::PgPoolOptions;
-use sqlx::PgPool;
+use sqlx::{PgExecutor, PgPool};
 use tracing::info;
 
 pub struct Db {
@@ -18,15 +25,38 @@ impl Db {
         Ok(Self { pool })
     }
 
+    /// Builds a pool that only connects on first use; handy for tests and for
+    /// services that should start even while Postgres is still coming up.
+    pub fn connect_lazy(url: &str, max_connections: u32) -> Result<Self, CexError> {
+        let pool = PgPoolOptions::new()
+            .max_connections(max_connections)
+            .connect_lazy(url)
+            .map_err(|e| CexError::Internal(format!("db connection error: {e}")))?;
+        Ok(Self { pool })
+    }
+
+    /// Round-trips a trivial query to tell an unreachable database apart from
+    /// a statement that failed on its own.
+    pub async fn ping(&self) -> Result<(), CexError> {
+        sqlx::query("SELECT 1")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| CexError::Internal(format!("db ping failed: {e}")))?;
+        Ok(())
+    }
+
     pub fn pool(&self) -> &PgPool {
         &self.pool
     }
 }
 
-pub async fn insert_event(pool: &PgPool, payload: &str) -> Result<(), CexError> {
+pub async fn insert_event<'e>(
+    executor: impl PgExecutor<'e>,
+    payload: &str,
+) -> Result<(), CexError> {
     sqlx::query("INSERT INTO events (payload) VALUES ($1)")
         .bind(payload)
-        .execute(pool)
+        .execute(executor)
         .await
         .map_err(|e| CexError::Internal(format!("insert event failed: {e}")))?
         .rows_affected();
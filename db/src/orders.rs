@@ -0,0 +1,246 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use shared::types::{NewOrder, Order, OrderId, OrderReason, OrderStatus, OrderUpdate, UserId};
+use shared::CexError;
+use sqlx::{PgExecutor, PgPool};
+
+const ORDER_COLUMNS: &str = "order_id, user_id, pair, side, order_type, price, quantity, \
+     quote_quantity, max_slippage_bps, filled, avg_price, status, reason, created_at";
+
+/// Records an order accepted by the engine. Replays of the same event are ignored.
+pub async fn insert_accepted_order<'e>(
+    executor: impl PgExecutor<'e>,
+    order: &NewOrder,
+    at: DateTime<Utc>,
+) -> Result<(), CexError> {
+    sqlx::query(
+        "WITH inserted AS ( \
+             INSERT INTO orders (order_id, user_id, pair, side, order_type, price, quantity, \
+                 quote_quantity, max_slippage_bps, status, created_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $10, $11, 'new', $8) \
+             ON CONFLICT (order_id) DO NOTHING \
+             RETURNING order_id, status, filled \
+         ) \
+         INSERT INTO order_status_history (order_id, status, filled, changed_at) \
+         SELECT order_id, status, filled, $9 FROM inserted",
+    )
+    .bind(order.order_id)
+    .bind(order.user_id)
+    .bind(&order.pair)
+    .bind(order.side.as_str())
+    .bind(order.order_type.as_str())
+    .bind(order.price)
+    .bind(order.quantity)
+    .bind(order.created_at)
+    .bind(at)
+    .bind(order.quote_quantity)
+    .bind(order.max_slippage_bps.map(|bps| bps as i32))
+    .execute(executor)
+    .await
+    .map_err(|e| CexError::Internal(format!("insert order failed: {e}")))?;
+    Ok(())
+}
+
+pub async fn mark_cancelled<'e>(
+    executor: impl PgExecutor<'e>,
+    order_id: OrderId,
+    at: DateTime<Utc>,
+) -> Result<(), CexError> {
+    sqlx::query(
+        "WITH updated AS ( \
+             UPDATE orders SET status = 'cancelled', updated_at = now() \
+             WHERE order_id = $1 AND status IN ('new', 'partially_filled') \
+             RETURNING order_id, status, filled \
+         ) \
+         INSERT INTO order_status_history (order_id, status, filled, changed_at) \
+         SELECT order_id, status, filled, $2 FROM updated",
+    )
+    .bind(order_id)
+    .bind(at)
+    .execute(executor)
+    .await
+    .map_err(|e| CexError::Internal(format!("cancel order failed: {e}")))?;
+    Ok(())
+}
+
+/// Moves an order to the state reported by an engine lifecycle event. Events
+/// that would not change anything, such as replays or the acceptance of an
+/// order already recorded as new, leave no history row.
+pub async fn apply_order_update<'e>(
+    executor: impl PgExecutor<'e>,
+    update: &OrderUpdate,
+    at: DateTime<Utc>,
+) -> Result<(), CexError> {
+    sqlx::query(
+        "WITH updated AS ( \
+             UPDATE orders SET status = $2, filled = $3, avg_price = $4, reason = $5, \
+                 quantity = $7, updated_at = now() \
+             WHERE order_id = $1 \
+               AND (status, filled, reason) IS DISTINCT FROM ($2, $3, $5) \
+               AND status NOT IN ('filled', 'cancelled', 'rejected', 'expired') \
+             RETURNING order_id, status, filled, reason \
+         ) \
+         INSERT INTO order_status_history (order_id, status, filled, reason, changed_at) \
+         SELECT order_id, status, filled, reason, $6 FROM updated",
+    )
+    .bind(update.order_id)
+    .bind(update.status.as_str())
+    .bind(update.filled)
+    .bind(update.avg_price)
+    .bind(update.reason.map(|r| r.as_str()))
+    .bind(at)
+    // Quote-sized orders only learn their base quantity as they finish.
+    .bind(update.quantity)
+    .execute(executor)
+    .await
+    .map_err(|e| CexError::Internal(format!("apply order update failed: {e}")))?;
+    Ok(())
+}
+
+/// One recorded status transition of an order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusChange {
+    pub status: OrderStatus,
+    pub filled: Decimal,
+    pub reason: Option<OrderReason>,
+    pub changed_at: DateTime<Utc>,
+}
+
+/// Status transitions of an order, oldest first.
+pub async fn order_status_history(
+    pool: &PgPool,
+    order_id: OrderId,
+) -> Result<Vec<StatusChange>, CexError> {
+    sqlx::query_as::<_, (String, Decimal, Option<String>, DateTime<Utc>)>(
+        "SELECT status, filled, reason, changed_at FROM order_status_history \
+         WHERE order_id = $1 ORDER BY changed_at, id",
+    )
+    .bind(order_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| CexError::Internal(format!("order status history failed: {e}")))?
+    .into_iter()
+    .map(|(status, filled, reason, changed_at)| {
+        Ok(StatusChange {
+            status: status.parse()?,
+            filled,
+            reason: reason.map(|r| r.parse()).transpose()?,
+            changed_at,
+        })
+    })
+    .collect()
+}
+
+pub async fn find_order(
+    pool: &PgPool,
+    user_id: UserId,
+    order_id: OrderId,
+) -> Result<Option<Order>, CexError> {
+    sqlx::query_as::<_, OrderRow>(&format!(
+        "SELECT {ORDER_COLUMNS} FROM orders WHERE order_id = $1 AND user_id = $2"
+    ))
+    .bind(order_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| CexError::Internal(format!("find order failed: {e}")))?
+    .map(Order::try_from)
+    .transpose()
+}
+
+pub async fn list_open_orders(
+    pool: &PgPool,
+    user_id: UserId,
+    pair: Option<&str>,
+) -> Result<Vec<Order>, CexError> {
+    sqlx::query_as::<_, OrderRow>(&format!(
+        "SELECT {ORDER_COLUMNS} FROM orders \
+         WHERE user_id = $1 AND ($2::TEXT IS NULL OR pair = $2) \
+           AND status IN ('new', 'partially_filled') \
+         ORDER BY created_at, order_id"
+    ))
+    .bind(user_id)
+    .bind(pair)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| CexError::Internal(format!("list open orders failed: {e}")))?
+    .into_iter()
+    .map(Order::try_from)
+    .collect()
+}
+
+/// Keyset position for paging order history newest-first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistoryCursor {
+    pub created_at: DateTime<Utc>,
+    pub order_id: OrderId,
+}
+
+/// Returns up to `limit` orders older than `before`, newest first.
+pub async fn list_order_history(
+    pool: &PgPool,
+    user_id: UserId,
+    pair: Option<&str>,
+    before: Option<HistoryCursor>,
+    limit: i64,
+) -> Result<Vec<Order>, CexError> {
+    sqlx::query_as::<_, OrderRow>(&format!(
+        "SELECT {ORDER_COLUMNS} FROM orders \
+         WHERE user_id = $1 AND ($2::TEXT IS NULL OR pair = $2) \
+           AND ($3::TIMESTAMPTZ IS NULL OR (created_at, order_id) < ($3, $4)) \
+         ORDER BY created_at DESC, order_id DESC \
+         LIMIT $5"
+    ))
+    .bind(user_id)
+    .bind(pair)
+    .bind(before.map(|c| c.created_at))
+    .bind(before.map(|c| c.order_id))
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| CexError::Internal(format!("list order history failed: {e}")))?
+    .into_iter()
+    .map(Order::try_from)
+    .collect()
+}
+
+#[derive(sqlx::FromRow)]
+struct OrderRow {
+    order_id: OrderId,
+    user_id: UserId,
+    pair: String,
+    side: String,
+    order_type: String,
+    price: Decimal,
+    quantity: Decimal,
+    quote_quantity: Option<Decimal>,
+    max_slippage_bps: Option<i32>,
+    filled: Decimal,
+    avg_price: Decimal,
+    status: String,
+    reason: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+impl TryFrom<OrderRow> for Order {
+    type Error = CexError;
+
+    fn try_from(row: OrderRow) -> Result<Self, Self::Error> {
+        Ok(Order {
+            order_id: row.order_id,
+            user_id: row.user_id,
+            pair: row.pair,
+            side: row.side.parse()?,
+            order_type: row.order_type.parse()?,
+            price: row.price,
+            quantity: row.quantity,
+            quote_quantity: row.quote_quantity,
+            max_slippage_bps: row.max_slippage_bps.map(|bps| bps as u32),
+            filled: row.filled,
+            avg_price: row.avg_price,
+            status: row.status.parse()?,
+            reason: row.reason.map(|r| r.parse()).transpose()?,
+            created_at: row.created_at,
+        })
+    }
+}
@@ -0,0 +1,107 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use shared::types::{Trade, UserId};
+use shared::CexError;
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+/// Stores an executed trade, resolving both counterparties from the orders
+/// read model. Returns `false` if the trade was already recorded.
+pub async fn insert_trade<'e>(
+    executor: impl PgExecutor<'e>,
+    trade: &Trade,
+) -> Result<bool, CexError> {
+    let result = sqlx::query(
+        "INSERT INTO trades (trade_id, pair, price, quantity, buy_order_id, sell_order_id, \
+             buy_user_id, sell_user_id, executed_at) \
+         VALUES ($1, $2, $3, $4, $5, $6, \
+             (SELECT user_id FROM orders WHERE order_id = $5), \
+             (SELECT user_id FROM orders WHERE order_id = $6), $7) \
+         ON CONFLICT (trade_id) DO NOTHING",
+    )
+    .bind(trade.trade_id)
+    .bind(&trade.pair)
+    .bind(trade.price)
+    .bind(trade.quantity)
+    .bind(trade.buy_order_id)
+    .bind(trade.sell_order_id)
+    .bind(trade.timestamp)
+    .execute(executor)
+    .await
+    .map_err(|e| CexError::Internal(format!("insert trade failed: {e}")))?;
+    Ok(result.rows_affected() == 1)
+}
+
+/// Trades in which `user_id` was on either side, newest first.
+pub async fn list_user_trades(
+    pool: &PgPool,
+    user_id: UserId,
+    pair: Option<&str>,
+    limit: i64,
+) -> Result<Vec<Trade>, CexError> {
+    let rows = sqlx::query_as::<_, TradeRow>(
+        "SELECT trade_id, pair, price, quantity, buy_order_id, sell_order_id, executed_at \
+         FROM trades \
+         WHERE (buy_user_id = $1 OR sell_user_id = $1) AND ($2::TEXT IS NULL OR pair = $2) \
+         ORDER BY executed_at DESC, trade_id \
+         LIMIT $3",
+    )
+    .bind(user_id)
+    .bind(pair)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| CexError::Internal(format!("list trades failed: {e}")))?;
+    Ok(rows.into_iter().map(Trade::from).collect())
+}
+
+/// Every pair's trades since `since` plus its newest `per_pair` regardless of
+/// age, oldest first; enough to rebuild recent trades and 24h tickers.
+pub async fn list_recent_trades(
+    pool: &PgPool,
+    since: DateTime<Utc>,
+    per_pair: i64,
+) -> Result<Vec<Trade>, CexError> {
+    let rows = sqlx::query_as::<_, TradeRow>(
+        "SELECT trade_id, pair, price, quantity, buy_order_id, sell_order_id, executed_at \
+         FROM ( \
+             SELECT *, ROW_NUMBER() OVER ( \
+                 PARTITION BY pair ORDER BY executed_at DESC, trade_id DESC \
+             ) AS recency \
+             FROM trades \
+         ) ranked \
+         WHERE executed_at > $1 OR recency <= $2 \
+         ORDER BY executed_at, trade_id",
+    )
+    .bind(since)
+    .bind(per_pair)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| CexError::Internal(format!("list recent trades failed: {e}")))?;
+    Ok(rows.into_iter().map(Trade::from).collect())
+}
+
+#[derive(sqlx::FromRow)]
+struct TradeRow {
+    trade_id: Uuid,
+    pair: String,
+    price: Decimal,
+    quantity: Decimal,
+    buy_order_id: Uuid,
+    sell_order_id: Uuid,
+    executed_at: DateTime<Utc>,
+}
+
+impl From<TradeRow> for Trade {
+    fn from(row: TradeRow) -> Self {
+        Trade {
+            trade_id: row.trade_id,
+            pair: row.pair,
+            price: row.price,
+            quantity: row.quantity,
+            buy_order_id: row.buy_order_id,
+            sell_order_id: row.sell_order_id,
+            timestamp: row.executed_at,
+        }
+    }
+}
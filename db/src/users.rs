@@ -0,0 +1,64 @@
+use shared::types::{User, UserId};
+use shared::CexError;
+use sqlx::PgPool;
+
+/// Inserts a new user, mapping a duplicate email to a validation error.
+pub async fn insert_user(pool: &PgPool, user: &User) -> Result<(), CexError> {
+    sqlx::query(
+        "INSERT INTO users (user_id, email, password_hash, created_at) VALUES ($1, $2, $3, $4)",
+    )
+    .bind(user.user_id)
+    .bind(&user.email)
+    .bind(&user.password_hash)
+    .bind(user.created_at)
+    .execute(pool)
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => {
+            CexError::Validation("email already registered".to_string())
+        }
+        e => CexError::Internal(format!("insert user failed: {e}")),
+    })?;
+    Ok(())
+}
+
+pub async fn find_user_by_email(pool: &PgPool, email: &str) -> Result<Option<User>, CexError> {
+    sqlx::query_as::<_, UserRow>(
+        "SELECT user_id, email, password_hash, created_at FROM users WHERE email = $1",
+    )
+    .bind(email)
+    .fetch_optional(pool)
+    .await
+    .map(|row| row.map(User::from))
+    .map_err(|e| CexError::Internal(format!("find user failed: {e}")))
+}
+
+pub async fn find_user_by_id(pool: &PgPool, user_id: UserId) -> Result<Option<User>, CexError> {
+    sqlx::query_as::<_, UserRow>(
+        "SELECT user_id, email, password_hash, created_at FROM users WHERE user_id = $1",
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map(|row| row.map(User::from))
+    .map_err(|e| CexError::Internal(format!("find user failed: {e}")))
+}
+
+#[derive(sqlx::FromRow)]
+struct UserRow {
+    user_id: UserId,
+    email: String,
+    password_hash: String,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<UserRow> for User {
+    fn from(row: UserRow) -> Self {
+        User {
+            user_id: row.user_id,
+            email: row.email,
+            password_hash: row.password_hash,
+            created_at: row.created_at,
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use db::candles::apply_trade;
+use redis::queues::CHANNEL_EVENTS;
+use redis::RedisManager;
+use shared::types::{Candle, CandleInterval, Trade};
+use shared::{to_json, CexError, Envelope, Event};
+use sqlx::PgConnection;
+
+const CANDLES_SOURCE: &str = "candles";
+
+/// Updates every interval's bar for `trade` and returns the new bars.
+pub async fn aggregate(conn: &mut PgConnection, trade: &Trade) -> Result<Vec<Candle>, CexError> {
+    let mut candles = Vec::with_capacity(CandleInterval::ALL.len());
+    for interval in CandleInterval::ALL {
+        candles.push(apply_trade(&mut *conn, interval, trade).await?);
+    }
+    Ok(candles)
+}
+
+/// Pushes live candle updates to subscribers of the event channel.
+pub async fn publish(redis: &RedisManager, candles: Vec<Candle>) -> Result<(), CexError> {
+    for candle in candles {
+        let envelope = Envelope::new(CANDLES_SOURCE, Event::Candle(candle));
+        redis.publish(CHANNEL_EVENTS, &to_json(&envelope)?).await?;
+    }
+    Ok(())
+}
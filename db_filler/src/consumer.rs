@@ -0,0 +1,266 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use db::events::{insert_events, load_offset, store_offset};
+use db::Db;
+use redis::queues::STREAM_EVENTS;
+use redis::streams::{StreamEntry, STREAM_START};
+use redis::{DeadLetter, DeadLetterOrigin, RedisManager};
+use shared::utils::Backoff;
+use shared::{CexError, Envelope, SequenceCheck, SequenceTracker};
+use tokio::time::Instant;
+use tracing::{error, info, warn};
+
+use crate::candles;
+use crate::config::FillerConfig;
+use crate::projection;
+
+const FILLER_SOURCE: &str = "db_filler";
+
+/// A stream entry that decoded successfully and is waiting to be written.
+struct Pending {
+    /// Entry id on `STREAM_EVENTS`, or `None` for events refetched from a
+    /// market log, whose ids are not valid offsets.
+    offset: Option<String>,
+    envelope: Envelope,
+    payload: String,
+}
+
+/// Reads the engine's event stream and persists it in batches. Each batch is
+/// written together with the consumer offset, so a restart resumes after the
+/// last committed entry; event ids guard against anything delivered twice.
+pub struct EventConsumer {
+    db: Db,
+    redis: RedisManager,
+    config: FillerConfig,
+}
+
+impl EventConsumer {
+    pub fn new(db: Db, redis: RedisManager, config: FillerConfig) -> Self {
+        Self { db, redis, config }
+    }
+
+    pub async fn run(&self) -> Result<(), CexError> {
+        let mut cursor = load_offset(self.db.pool(), &self.config.consumer)
+            .await?
+            .unwrap_or_else(|| STREAM_START.to_string());
+        info!(consumer = %self.config.consumer, %cursor, "consuming event stream");
+
+        let flush_interval = Duration::from_millis(self.config.flush_interval_ms);
+        let mut read_backoff = self.backoff();
+        let mut batch: Vec<StreamEntry> = Vec::with_capacity(self.config.batch_size);
+        let mut deadline = Instant::now() + flush_interval;
+        let mut sequences = SequenceTracker::new();
+        loop {
+            let wait = deadline.saturating_duration_since(Instant::now());
+            let wanted = self.config.batch_size - batch.len();
+            match self
+                .redis
+                .read_stream(
+                    STREAM_EVENTS,
+                    &cursor,
+                    wanted,
+                    wait.as_millis().max(1) as usize,
+                )
+                .await
+            {
+                Ok(entries) => {
+                    read_backoff.reset();
+                    if let Some(last) = entries.last() {
+                        cursor = last.id.clone();
+                    }
+                    batch.extend(entries);
+                }
+                Err(err) => {
+                    error!("stream read failed: {err}");
+                    tokio::time::sleep(read_backoff.next_delay()).await;
+                }
+            }
+
+            if batch.len() >= self.config.batch_size || Instant::now() >= deadline {
+                if let Some(last) = batch.last() {
+                    let pending = self.decode_batch(&batch, &mut sequences).await;
+                    self.flush_with_retry(&pending, &last.id).await;
+                    batch.clear();
+                }
+                deadline = Instant::now() + flush_interval;
+            }
+        }
+    }
+
+    /// Entries that are not valid envelopes are dead-lettered right away;
+    /// retrying them would never help. Events missing from the stream are
+    /// refetched from their market's log and queued ahead of the entry that
+    /// revealed the gap.
+    async fn decode_batch(
+        &self,
+        batch: &[StreamEntry],
+        sequences: &mut SequenceTracker,
+    ) -> Vec<Pending> {
+        let mut pending = Vec::with_capacity(batch.len());
+        for entry in batch {
+            match Envelope::decode(entry.payload.as_bytes()) {
+                Ok(envelope) => {
+                    // Duplicates are kept: a re-driven dead letter repeats an
+                    // old sequence number, and event ids make the write idempotent.
+                    if let Some((market, sequence)) = envelope.position() {
+                        if let SequenceCheck::Gap { missing } = sequences.check(market, sequence) {
+                            warn!(%market, ?missing, "event gap, refetching from market log");
+                            self.refetch(market, *missing.start(), *missing.end(), &mut pending)
+                                .await;
+                        }
+                    }
+                    pending.push(Pending {
+                        offset: Some(entry.id.clone()),
+                        envelope,
+                        payload: entry.payload.clone(),
+                    });
+                }
+                Err(err) => {
+                    warn!(id = %entry.id, "undecodable stream entry: {err}");
+                    self.dead_letter(entry.payload.as_bytes(), err).await;
+                }
+            }
+        }
+        pending
+    }
+
+    async fn refetch(&self, market: &str, from: u64, to: u64, pending: &mut Vec<Pending>) {
+        let mut backoff = self.backoff();
+        let entries = loop {
+            match self.redis.read_market_range(market, from, to).await {
+                Ok(entries) => break entries,
+                Err(err) => {
+                    let delay = backoff.next_delay();
+                    error!(%market, ?delay, "failed to refetch event gap: {err}");
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        };
+        if entries.len() as u64 != to - from + 1 {
+            // Trimmed from the log; nothing more can be recovered.
+            error!(
+                %market,
+                from,
+                to,
+                found = entries.len(),
+                "market log no longer holds the whole gap"
+            );
+        }
+        for entry in entries {
+            match Envelope::decode(entry.payload.as_bytes()) {
+                Ok(envelope) => pending.push(Pending {
+                    offset: None,
+                    envelope,
+                    payload: entry.payload,
+                }),
+                Err(err) => self.dead_letter(entry.payload.as_bytes(), err).await,
+            }
+        }
+    }
+
+    /// Database errors are retried with backoff. If a batch keeps failing it
+    /// is replayed one event at a time so a single bad event is dead-lettered
+    /// instead of blocking the stream; nothing is dropped silently.
+    async fn flush_with_retry(&self, pending: &[Pending], last_id: &str) {
+        let mut backoff = self.backoff();
+        for attempt in 1..=self.config.max_attempts {
+            match self.flush(pending, Some(last_id)).await {
+                Ok(()) => return,
+                Err(err) => {
+                    let delay = backoff.next_delay();
+                    error!(attempt, ?delay, "failed to persist batch: {err}");
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+
+        warn!(
+            events = pending.len(),
+            "batch keeps failing, retrying events one at a time"
+        );
+        for event in pending {
+            self.flush_single(event).await;
+        }
+        // Covers any dead-lettered entries after the last event.
+        backoff.reset();
+        while let Err(err) = store_offset(self.db.pool(), &self.config.consumer, last_id).await {
+            error!("failed to store offset: {err}");
+            tokio::time::sleep(backoff.next_delay()).await;
+        }
+    }
+
+    async fn flush_single(&self, event: &Pending) {
+        let mut backoff = self.backoff();
+        loop {
+            let Err(err) = self
+                .flush(std::slice::from_ref(event), event.offset.as_deref())
+                .await
+            else {
+                return;
+            };
+            // With the database reachable the event itself is the problem.
+            if self.db.ping().await.is_ok() {
+                error!(event_id = %event.envelope.event_id, "dead-lettering event: {err}");
+                self.dead_letter(event.payload.as_bytes(), err).await;
+                return;
+            }
+            let delay = backoff.next_delay();
+            error!(?delay, "database unavailable: {err}");
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    async fn flush(&self, pending: &[Pending], last_id: Option<&str>) -> Result<(), CexError> {
+        let rows: Vec<_> = pending
+            .iter()
+            .map(|p| (p.envelope.event_id, p.payload.as_str()))
+            .collect();
+
+        let mut tx = self
+            .db
+            .pool()
+            .begin()
+            .await
+            .map_err(|e| CexError::Internal(format!("begin transaction failed: {e}")))?;
+        let mut fresh: HashSet<_> = insert_events(&mut *tx, &rows).await?.into_iter().collect();
+        let mut updated_candles = Vec::new();
+        for p in pending {
+            // Only project events seen for the first time, once each.
+            if fresh.remove(&p.envelope.event_id) {
+                updated_candles.extend(projection::project(&mut tx, &p.envelope).await?);
+            }
+        }
+        if let Some(last_id) = last_id {
+            store_offset(&mut *tx, &self.config.consumer, last_id).await?;
+        }
+        tx.commit()
+            .await
+            .map_err(|e| CexError::Internal(format!("commit failed: {e}")))?;
+
+        // Live candle updates are best effort; the bars themselves are committed.
+        if let Err(err) = candles::publish(&self.redis, updated_candles).await {
+            warn!("failed to publish candle updates: {err}");
+        }
+        Ok(())
+    }
+
+    async fn dead_letter(&self, payload: &[u8], err: impl ToString) {
+        let letter = DeadLetter::new(
+            FILLER_SOURCE,
+            DeadLetterOrigin::Stream(STREAM_EVENTS.to_string()),
+            payload,
+            err,
+        );
+        if let Err(err) = self.redis.push_dead_letter(&letter).await {
+            error!("failed to dead-letter event: {err}");
+        }
+    }
+
+    fn backoff(&self) -> Backoff {
+        Backoff::new(
+            Duration::from_millis(self.config.retry_initial_ms),
+            Duration::from_millis(self.config.retry_max_ms),
+        )
+    }
+}
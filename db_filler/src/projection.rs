@@ -0,0 +1,44 @@
+use db::orders::{apply_order_update, insert_accepted_order, mark_cancelled};
+use db::trades::insert_trade;
+use shared::types::Candle;
+use shared::{CexError, Envelope, Event};
+use sqlx::PgConnection;
+
+use crate::candles;
+
+/// Applies an engine event to the normalized tables. Returns the candles
+/// touched by a newly recorded trade so they can be published once the
+/// surrounding transaction commits.
+pub async fn project(
+    conn: &mut PgConnection,
+    envelope: &Envelope,
+) -> Result<Vec<Candle>, CexError> {
+    let at = envelope.emitted_at;
+    match &envelope.event {
+        Event::OrderNew(order) => insert_accepted_order(&mut *conn, order, at).await?,
+        Event::TradeExecuted(trade) => {
+            // A replayed trade must not be counted twice. Order fills arrive
+            // as separate lifecycle events.
+            if !insert_trade(&mut *conn, trade).await? {
+                return Ok(Vec::new());
+            }
+            return candles::aggregate(conn, trade).await;
+        }
+        Event::OrderUpdate(update) => apply_order_update(&mut *conn, update, at).await?,
+        // Engines before lifecycle events announced cancels this way.
+        Event::OrderCancel { order_id } => mark_cancelled(&mut *conn, *order_id, at).await?,
+        Event::DepthSnapshot { .. }
+        | Event::Candle(_)
+        | Event::CancelRequest(_)
+        | Event::CancelRejected(_)
+        | Event::CancelAll(_)
+        | Event::CancelAllDone(_)
+        | Event::CountdownCancel(_)
+        | Event::MarketHalted(_)
+        | Event::MarketResumed(_)
+        | Event::SetMarketState(_)
+        | Event::MarketStateChanged(_)
+        | Event::AuctionIndicative(_) => {}
+    }
+    Ok(Vec::new())
+}
@@ -0,0 +1,135 @@
+//! Deterministic order flow for the benchmarks.
+
+use rust_decimal::Decimal;
+use shared::types::{new_order, Order, OrderId, OrderSide, OrderType};
+use uuid::Uuid;
+
+pub const PAIR: &str = "SOLUSDC";
+/// Prices are whole ticks of 0.01 around this.
+const START_TICKS: i64 = 10_000;
+
+/// Cheap seeded generator, so every run replays the same flow.
+pub struct Lcg(u64);
+
+impl Lcg {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next(&mut self, bound: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        ((self.0 >> 33) as usize) % bound
+    }
+
+    fn chance(&mut self, percent: usize) -> bool {
+        self.next(100) < percent
+    }
+
+    fn side(&mut self) -> OrderSide {
+        if self.chance(50) {
+            OrderSide::Buy
+        } else {
+            OrderSide::Sell
+        }
+    }
+}
+
+pub enum Action {
+    Place(Order),
+    Cancel(OrderId),
+}
+
+pub fn limit(side: OrderSide, price: Decimal, quantity: Decimal) -> Order {
+    Order::from_new(new_order(
+        Uuid::new_v4(),
+        PAIR.to_string(),
+        side,
+        OrderType::Limit,
+        price,
+        quantity,
+    ))
+}
+
+pub fn market(side: OrderSide, quantity: Decimal) -> Order {
+    Order::from_new(new_order(
+        Uuid::new_v4(),
+        PAIR.to_string(),
+        side,
+        OrderType::Market,
+        Decimal::ZERO,
+        quantity,
+    ))
+}
+
+fn ticks(ticks: i64) -> Decimal {
+    Decimal::new(ticks, 2)
+}
+
+/// Limit orders around a mid price that wanders a tick at a time. Most rest
+/// a few ticks away; about one in five crosses the spread and trades.
+pub fn random_walk(count: usize, seed: u64) -> Vec<Order> {
+    let mut rng = Lcg::new(seed);
+    let mut mid = START_TICKS;
+    (0..count)
+        .map(|_| {
+            mid += rng.next(3) as i64 - 1;
+            let side = rng.side();
+            // Negative offsets cross the mid.
+            let offset = rng.next(25) as i64 - 5;
+            let price = match side {
+                OrderSide::Buy => mid - offset,
+                OrderSide::Sell => mid + offset,
+            };
+            limit(side, ticks(price), Decimal::from(1 + rng.next(10)))
+        })
+        .collect()
+}
+
+/// A market maker requoting ten levels a side around a moving mid: nine in
+/// ten actions cancel one of its quotes and place a fresh one, the rest are
+/// small market orders from takers.
+pub fn market_maker(count: usize, seed: u64) -> Vec<Action> {
+    let mut rng = Lcg::new(seed);
+    let mut mid = START_TICKS;
+    let mut quotes: Vec<OrderId> = Vec::new();
+    let mut actions = Vec::with_capacity(count);
+    let quote = |rng: &mut Lcg, mid: i64| {
+        let side = rng.side();
+        let level = 1 + rng.next(10) as i64;
+        let price = match side {
+            OrderSide::Buy => mid - level,
+            OrderSide::Sell => mid + level,
+        };
+        limit(side, ticks(price), Decimal::from(5))
+    };
+    while actions.len() < count {
+        if rng.chance(10) {
+            actions.push(Action::Place(market(rng.side(), Decimal::ONE)));
+            continue;
+        }
+        if quotes.len() >= 20 {
+            let stale = quotes.swap_remove(rng.next(quotes.len()));
+            actions.push(Action::Cancel(stale));
+        }
+        mid += rng.next(3) as i64 - 1;
+        let order = quote(&mut rng, mid);
+        quotes.push(order.order_id);
+        actions.push(Action::Place(order));
+    }
+    actions.truncate(count);
+    actions
+}
+
+/// Asks on `levels` consecutive ticks above the start price, `per_level`
+/// orders of one unit each.
+pub fn ladder(levels: usize, per_level: usize) -> Vec<Order> {
+    (0..levels as i64)
+        .flat_map(|level| {
+            (0..per_level)
+                .map(move |_| limit(OrderSide::Sell, ticks(START_TICKS + level), Decimal::ONE))
+        })
+        .collect()
+}
@@ -0,0 +1,135 @@
+//! Order book operations under generated flow: `cargo bench -p engine`.
+
+mod flow;
+
+use criterion::{
+    black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput,
+};
+use engine::orderbook::OrderBook;
+use flow::{Action, Lcg, PAIR};
+use rust_decimal::Decimal;
+use shared::types::{OrderId, OrderSide};
+
+const RESTING: usize = 100_000;
+const FLOW_LEN: usize = 10_000;
+
+/// `RESTING` asks spread evenly over `levels` prices from 1000 up.
+fn deep_book(levels: usize) -> (OrderBook, Vec<(OrderId, Decimal)>) {
+    let mut book = OrderBook::new(PAIR);
+    let resting = (0..RESTING)
+        .map(|i| {
+            let order = flow::limit(
+                OrderSide::Sell,
+                Decimal::from(1_000 + i % levels),
+                Decimal::ONE,
+            );
+            let resting = (order.order_id, order.price);
+            book.execute(order);
+            resting
+        })
+        .collect();
+    (book, resting)
+}
+
+fn cancel(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cancel_100k");
+    for levels in [10, 1_000] {
+        let (mut book, mut resting) = deep_book(levels);
+        let mut picks = Lcg::new(42);
+        // Cancels an order from anywhere in the book and rests a fresh one at
+        // the same price so the book stays the same size.
+        group.bench_function(BenchmarkId::from_parameter(levels), |b| {
+            b.iter(|| {
+                let slot = picks.next(resting.len());
+                let (order_id, price) = resting[slot];
+                assert!(book.cancel(black_box(order_id)));
+                let order = flow::limit(OrderSide::Sell, price, Decimal::ONE);
+                resting[slot] = (order.order_id, price);
+                book.execute(order);
+            })
+        });
+    }
+    group.finish();
+}
+
+fn depth(c: &mut Criterion) {
+    let mut group = c.benchmark_group("depth_100k");
+    for levels in [10, 1_000] {
+        let (book, _) = deep_book(levels);
+        group.bench_function(BenchmarkId::from_parameter(levels), |b| {
+            b.iter(|| black_box(book.depth()))
+        });
+    }
+    group.finish();
+}
+
+fn order_flow(c: &mut Criterion) {
+    let mut group = c.benchmark_group("flow");
+    group.throughput(Throughput::Elements(FLOW_LEN as u64));
+
+    let orders = flow::random_walk(FLOW_LEN, 7);
+    group.bench_function("random_walk", |b| {
+        b.iter_batched(
+            || (OrderBook::new(PAIR), orders.clone()),
+            |(mut book, orders)| {
+                for order in orders {
+                    black_box(book.execute(order));
+                }
+                book
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.bench_function("market_maker", |b| {
+        b.iter_batched(
+            || (OrderBook::new(PAIR), flow::market_maker(FLOW_LEN, 11)),
+            |(mut book, actions)| {
+                for action in actions {
+                    match action {
+                        Action::Place(order) => {
+                            black_box(book.execute(order));
+                        }
+                        Action::Cancel(order_id) => {
+                            black_box(book.cancel(order_id));
+                        }
+                    }
+                }
+                book
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn sweep(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sweep");
+    // Each market buy takes out `levels` of a 100-level ladder, ten orders
+    // per level.
+    for levels in [1, 10, 50] {
+        let ladder = flow::ladder(100, 10);
+        group.throughput(Throughput::Elements(levels * 10));
+        group.bench_function(BenchmarkId::from_parameter(levels), |b| {
+            b.iter_batched(
+                || {
+                    let mut book = OrderBook::new(PAIR);
+                    for order in ladder.clone() {
+                        book.execute(order);
+                    }
+                    book
+                },
+                |mut book| {
+                    let sweep = flow::market(OrderSide::Buy, Decimal::from(levels * 10));
+                    black_box(book.execute(sweep));
+                    book
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, cancel, depth, order_flow, sweep);
+criterion_main!(benches);
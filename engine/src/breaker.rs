@@ -0,0 +1,52 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use rust_decimal::Decimal;
+
+use crate::rules::VolatilityBreaker;
+
+/// Recent trade prices of one market, checked against its circuit breaker.
+#[derive(Debug)]
+pub struct VolatilityMonitor {
+    config: VolatilityBreaker,
+    prices: VecDeque<(Instant, Decimal)>,
+}
+
+impl VolatilityMonitor {
+    pub fn new(config: VolatilityBreaker) -> Self {
+        Self {
+            config,
+            prices: VecDeque::new(),
+        }
+    }
+
+    pub fn cooldown(&self) -> Duration {
+        Duration::from_secs(self.config.cooldown_secs)
+    }
+
+    /// Records a trade and reports whether prices within the window now span
+    /// more than the allowed move. The window starts over once it trips.
+    pub fn record(&mut self, now: Instant, price: Decimal) -> bool {
+        let window = Duration::from_secs(self.config.window_secs);
+        while let Some(&(at, _)) = self.prices.front() {
+            if now.duration_since(at) <= window {
+                break;
+            }
+            self.prices.pop_front();
+        }
+        self.prices.push_back((now, price));
+
+        let (low, high) = self
+            .prices
+            .iter()
+            .fold((price, price), |(low, high), &(_, p)| {
+                (low.min(p), high.max(p))
+            });
+        let tripped = low > Decimal::ZERO
+            && (high - low) * Decimal::from(10_000) > low * Decimal::from(self.config.max_move_bps);
+        if tripped {
+            self.prices.clear();
+        }
+        tripped
+    }
+}
@@ -0,0 +1,36 @@
+use std::collections::{HashMap, VecDeque};
+
+use shared::types::{OrderId, OrderStatus, UserId};
+
+/// Orders that left the book, remembered so a late cancel can be told why it
+/// failed. Only the most recent `capacity` are kept; older ones look unknown.
+pub struct ClosedOrders {
+    capacity: usize,
+    orders: HashMap<OrderId, (UserId, OrderStatus)>,
+    order: VecDeque<OrderId>,
+}
+
+impl ClosedOrders {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            orders: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    pub fn insert(&mut self, order_id: OrderId, owner: UserId, status: OrderStatus) {
+        if self.orders.insert(order_id, (owner, status)).is_none() {
+            self.order.push_back(order_id);
+        }
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.orders.remove(&oldest);
+            }
+        }
+    }
+
+    pub fn get(&self, order_id: OrderId) -> Option<(UserId, OrderStatus)> {
+        self.orders.get(&order_id).copied()
+    }
+}
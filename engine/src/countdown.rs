@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use shared::types::UserId;
+
+/// Dead-man's switches: a user's orders are all cancelled unless their
+/// countdown is refreshed before it runs out. Kept in memory only, so an
+/// engine restart disarms every switch.
+#[derive(Debug, Default)]
+pub struct Countdowns {
+    deadlines: HashMap<UserId, Instant>,
+}
+
+impl Countdowns {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts or restarts the countdown; a zero timeout disarms it.
+    pub fn arm(&mut self, user_id: UserId, timeout: Duration, now: Instant) {
+        if timeout.is_zero() {
+            self.deadlines.remove(&user_id);
+        } else {
+            self.deadlines.insert(user_id, now + timeout);
+        }
+    }
+
+    /// Users whose countdown ran out by `now`; their switches are disarmed.
+    pub fn take_expired(&mut self, now: Instant) -> Vec<UserId> {
+        let expired: Vec<UserId> = self
+            .deadlines
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(user, _)| *user)
+            .collect();
+        for user in &expired {
+            self.deadlines.remove(user);
+        }
+        expired
+    }
+
+    pub fn is_armed(&self, user_id: UserId) -> bool {
+        self.deadlines.contains_key(&user_id)
+    }
+}
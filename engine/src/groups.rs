@@ -0,0 +1,379 @@
+use std::collections::{HashMap, VecDeque};
+
+use chrono::Utc;
+use rust_decimal::Decimal;
+use shared::types::{
+    NewOrder, Order, OrderGroup, OrderId, OrderReason, OrderSide, OrderStatus, OrderType,
+    OrderUpdate, StopLeg, TakeProfitLeg,
+};
+use shared::Event;
+
+use crate::orderbook::{Execution, OrderBook};
+
+/// A stop leg waiting for the market to trade through its trigger.
+#[derive(Debug, Clone)]
+struct PendingStop {
+    /// What goes into the book once triggered.
+    order: NewOrder,
+    trigger_price: Decimal,
+    /// The limit order it is OCO'd with.
+    limit_leg: OrderId,
+}
+
+impl PendingStop {
+    fn triggered_by(&self, price: Decimal) -> bool {
+        match self.order.side {
+            OrderSide::Buy => price >= self.trigger_price,
+            OrderSide::Sell => price <= self.trigger_price,
+        }
+    }
+}
+
+/// Exits of a bracket whose entry has not finished filling yet.
+#[derive(Debug, Clone)]
+struct PendingBracket {
+    entry: NewOrder,
+    take_profit: TakeProfitLeg,
+    stop_loss: StopLeg,
+}
+
+impl PendingBracket {
+    /// The take-profit, OCO'd with the stop-loss, for what the entry filled.
+    fn exits(self, filled: Decimal) -> NewOrder {
+        NewOrder {
+            order_id: self.take_profit.order_id,
+            user_id: self.entry.user_id,
+            pair: self.entry.pair,
+            side: opposite(self.entry.side),
+            order_type: OrderType::Limit,
+            price: self.take_profit.price,
+            quantity: filled,
+            quote_quantity: None,
+            max_slippage_bps: None,
+            created_at: Utc::now(),
+            group: Some(OrderGroup::Oco {
+                stop: self.stop_loss,
+            }),
+        }
+    }
+}
+
+/// An order waiting to go through the book; triggered stops were announced
+/// when they were registered.
+struct Queued {
+    order: NewOrder,
+    announced: bool,
+}
+
+/// OCO and bracket links for one market, kept next to its [`OrderBook`].
+/// Every entry point runs the book and the links it sets off to completion,
+/// so no other command sees a group half-updated.
+#[derive(Debug, Default)]
+pub struct OrderGroups {
+    stops: HashMap<OrderId, PendingStop>,
+    /// Limit leg → stop leg.
+    oco: HashMap<OrderId, OrderId>,
+    /// Entry order → exits to place once it fills.
+    brackets: HashMap<OrderId, PendingBracket>,
+}
+
+impl OrderGroups {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs `new` through `book` along with everything it sets off, and
+    /// returns the resulting events in publication order.
+    pub fn submit(&mut self, book: &mut OrderBook, new: NewOrder) -> Vec<Event> {
+        let mut events = Vec::new();
+        let queue = VecDeque::from([Queued {
+            order: new,
+            announced: false,
+        }]);
+        self.drain(book, queue, &mut events);
+        events
+    }
+
+    /// Cancels a resting order or an untriggered stop leg together with
+    /// whatever it is linked to. `None` if neither exists.
+    pub fn cancel(&mut self, book: &mut OrderBook, order_id: OrderId) -> Option<Vec<Event>> {
+        let mut events = Vec::new();
+        let mut queue = VecDeque::new();
+        if let Some(update) = book.cancel_order(order_id) {
+            self.settle(vec![update], &mut events, &mut queue);
+        } else {
+            let stop = self.stops.remove(&order_id)?;
+            self.oco.remove(&stop.limit_leg);
+            let mut updates = vec![cancelled(&stop.order, OrderReason::UserCancelled)];
+            if let Some(mut linked) = book.cancel_order(stop.limit_leg) {
+                linked.reason = Some(OrderReason::LinkedOrder);
+                updates.push(linked);
+            }
+            self.settle(updates, &mut events, &mut queue);
+        }
+        self.drain(book, queue, &mut events);
+        Some(events)
+    }
+
+    /// Applies the group links of updates produced directly by the book,
+    /// such as a mass cancel.
+    pub fn apply(&mut self, book: &mut OrderBook, updates: Vec<OrderUpdate>) -> Vec<Event> {
+        let mut events = Vec::new();
+        let mut queue = VecDeque::new();
+        self.settle(updates, &mut events, &mut queue);
+        self.drain(book, queue, &mut events);
+        events
+    }
+
+    /// Settles trades the book made on its own, such as an auction uncross:
+    /// linked orders react and crossed stops trigger as after any fill.
+    pub fn absorb(&mut self, book: &mut OrderBook, execution: Execution) -> Vec<Event> {
+        let mut events = Vec::new();
+        let mut queue = VecDeque::new();
+        let last_price = execution.trades.last().map(|trade| trade.price);
+        events.extend(execution.trades.into_iter().map(Event::TradeExecuted));
+        self.settle(execution.updates, &mut events, &mut queue);
+        if let Some(price) = last_price {
+            self.trigger(book, price, &mut events, &mut queue);
+        }
+        self.drain(book, queue, &mut events);
+        events
+    }
+
+    /// An untriggered stop leg.
+    pub fn stop(&self, order_id: OrderId) -> Option<&NewOrder> {
+        self.stops.get(&order_id).map(|stop| &stop.order)
+    }
+
+    pub fn pending_stops(&self) -> usize {
+        self.stops.len()
+    }
+
+    pub fn pending_brackets(&self) -> usize {
+        self.brackets.len()
+    }
+
+    fn drain(
+        &mut self,
+        book: &mut OrderBook,
+        mut queue: VecDeque<Queued>,
+        events: &mut Vec<Event>,
+    ) {
+        while let Some(Queued {
+            order: new,
+            announced,
+        }) = queue.pop_front()
+        {
+            if !announced {
+                events.push(Event::OrderNew(new.clone()));
+            }
+            if let Some(reason) = group_rejection(&new) {
+                let mut order = Order::from_new(new);
+                order.status = OrderStatus::Rejected;
+                order.reason = Some(reason);
+                events.push(Event::OrderUpdate(order.update()));
+                continue;
+            }
+
+            let execution = book.execute(Order::from_new(new.clone()));
+            let mut updates = execution.updates.into_iter();
+            let Some(verdict) = updates.next() else {
+                continue;
+            };
+            if verdict.status == OrderStatus::Rejected {
+                events.push(Event::OrderUpdate(verdict));
+                continue;
+            }
+            if !announced {
+                events.push(Event::OrderUpdate(verdict));
+            }
+            // Register before settling so fills at placement already count.
+            if let Some(group) = new.group.clone() {
+                self.register(&new, group, events);
+            }
+            let last_price = execution.trades.last().map(|trade| trade.price);
+            events.extend(execution.trades.into_iter().map(Event::TradeExecuted));
+            self.settle(updates.collect(), events, &mut queue);
+            if let Some(price) = last_price {
+                self.trigger(book, price, events, &mut queue);
+            }
+        }
+    }
+
+    fn register(&mut self, new: &NewOrder, group: OrderGroup, events: &mut Vec<Event>) {
+        match group {
+            OrderGroup::Oco { stop } => {
+                let (order_type, price) = match stop.limit_price {
+                    Some(price) => (OrderType::Limit, price),
+                    None => (OrderType::Market, Decimal::ZERO),
+                };
+                let leg = NewOrder {
+                    order_id: stop.order_id,
+                    user_id: new.user_id,
+                    pair: new.pair.clone(),
+                    side: new.side,
+                    order_type,
+                    price,
+                    quantity: new.quantity,
+                    quote_quantity: None,
+                    max_slippage_bps: None,
+                    created_at: new.created_at,
+                    group: None,
+                };
+                events.push(Event::OrderNew(leg.clone()));
+                events.push(Event::OrderUpdate(Order::from_new(leg.clone()).update()));
+                self.oco.insert(new.order_id, leg.order_id);
+                self.stops.insert(
+                    leg.order_id,
+                    PendingStop {
+                        order: leg,
+                        trigger_price: stop.trigger_price,
+                        limit_leg: new.order_id,
+                    },
+                );
+            }
+            OrderGroup::Bracket {
+                take_profit,
+                stop_loss,
+            } => {
+                let mut entry = new.clone();
+                entry.group = None;
+                self.brackets.insert(
+                    new.order_id,
+                    PendingBracket {
+                        entry,
+                        take_profit,
+                        stop_loss,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Publishes `updates` and whatever they set off: a close of an OCO limit
+    /// leg cancels its stop, a partial fill shrinks the stop to what is left
+    /// open, and a bracket entry that is done filling, or is cancelled part
+    /// filled, queues exits for what it filled. Entries swept by a mass cancel
+    /// get no exits, so a cancel-all leaves nothing behind.
+    fn settle(
+        &mut self,
+        updates: Vec<OrderUpdate>,
+        events: &mut Vec<Event>,
+        queue: &mut VecDeque<Queued>,
+    ) {
+        let mut pending = VecDeque::from(updates);
+        while let Some(update) = pending.pop_front() {
+            if let Some(&stop_id) = self.oco.get(&update.order_id) {
+                let open = update.quantity - update.filled;
+                if update.status.is_terminal() || open <= Decimal::ZERO {
+                    self.oco.remove(&update.order_id);
+                    if let Some(stop) = self.stops.remove(&stop_id) {
+                        pending.push_back(cancelled(&stop.order, OrderReason::LinkedOrder));
+                    }
+                } else if let Some(stop) = self.stops.get_mut(&stop_id) {
+                    if stop.order.quantity != open {
+                        stop.order.quantity = open;
+                        pending.push_back(Order::from_new(stop.order.clone()).update());
+                    }
+                }
+            }
+            if update.status.is_terminal() {
+                if let Some(bracket) = self.brackets.remove(&update.order_id) {
+                    let swept = update.reason == Some(OrderReason::MassCancel);
+                    if update.filled > Decimal::ZERO && !swept {
+                        queue.push_back(Queued {
+                            order: bracket.exits(update.filled),
+                            announced: false,
+                        });
+                    }
+                }
+            }
+            events.push(Event::OrderUpdate(update));
+        }
+    }
+
+    /// Fires every stop the last trade price has crossed: its limit leg is
+    /// pulled from the book and the stop goes in behind it.
+    fn trigger(
+        &mut self,
+        book: &mut OrderBook,
+        price: Decimal,
+        events: &mut Vec<Event>,
+        queue: &mut VecDeque<Queued>,
+    ) {
+        let mut fired: Vec<OrderId> = self
+            .stops
+            .iter()
+            .filter(|(_, stop)| stop.triggered_by(price))
+            .map(|(id, _)| *id)
+            .collect();
+        // Deterministic order regardless of hash order.
+        fired.sort();
+        for id in fired {
+            let Some(stop) = self.stops.remove(&id) else {
+                continue;
+            };
+            self.oco.remove(&stop.limit_leg);
+            if let Some(mut linked) = book.cancel_order(stop.limit_leg) {
+                linked.reason = Some(OrderReason::LinkedOrder);
+                self.settle(vec![linked], events, queue);
+            }
+            queue.push_back(Queued {
+                order: stop.order,
+                announced: true,
+            });
+        }
+    }
+}
+
+/// Why the group attached to `new` is unusable, if it is.
+fn group_rejection(new: &NewOrder) -> Option<OrderReason> {
+    let positive = |price: Decimal| price > Decimal::ZERO;
+    let valid_stop = |stop: &StopLeg| {
+        positive(stop.trigger_price)
+            && !matches!(stop.limit_price, Some(price) if !positive(price))
+            && stop.order_id != new.order_id
+    };
+    let valid = match new.group.as_ref()? {
+        OrderGroup::Oco { stop } => new.order_type == OrderType::Limit && valid_stop(stop),
+        OrderGroup::Bracket {
+            take_profit,
+            stop_loss,
+        } => {
+            positive(take_profit.price)
+                && valid_stop(stop_loss)
+                && take_profit.order_id != new.order_id
+                && take_profit.order_id != stop_loss.order_id
+                && exits_straddle_entry(new, take_profit, stop_loss)
+        }
+    };
+    (!valid).then_some(OrderReason::InvalidGroup)
+}
+
+/// Whether the take-profit is on the profitable side of the entry and the
+/// stop-loss on the losing side. Market entries have no price to compare, so
+/// only the exits are checked against each other.
+fn exits_straddle_entry(new: &NewOrder, take_profit: &TakeProfitLeg, stop_loss: &StopLeg) -> bool {
+    let (above, below) = match new.side {
+        OrderSide::Buy => (take_profit.price, stop_loss.trigger_price),
+        OrderSide::Sell => (stop_loss.trigger_price, take_profit.price),
+    };
+    match new.order_type {
+        OrderType::Limit => below < new.price && new.price < above,
+        OrderType::Market => below < above,
+    }
+}
+
+fn cancelled(new: &NewOrder, reason: OrderReason) -> OrderUpdate {
+    let mut order = Order::from_new(new.clone());
+    order.status = OrderStatus::Cancelled;
+    order.reason = Some(reason);
+    order.update()
+}
+
+fn opposite(side: OrderSide) -> OrderSide {
+    match side {
+        OrderSide::Buy => OrderSide::Sell,
+        OrderSide::Sell => OrderSide::Buy,
+    }
+}
@@ -0,0 +1,46 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use hdrhistogram::Histogram;
+use redis::HandleLatency;
+
+/// Slowest handling time tracked precisely; anything slower counts as this.
+const MAX_TRACKED_US: u64 = 60_000_000;
+
+/// Handling time histograms per message kind, kept for the engine's lifetime.
+#[derive(Debug, Default)]
+pub struct LatencyRecorder {
+    histograms: BTreeMap<&'static str, Histogram<u64>>,
+}
+
+impl LatencyRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, kind: &'static str, elapsed: Duration) {
+        let histogram = self.histograms.entry(kind).or_insert_with(|| {
+            Histogram::new_with_bounds(1, MAX_TRACKED_US, 3)
+                .expect("static histogram bounds are valid")
+        });
+        let micros = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+        histogram.saturating_record(micros.clamp(1, MAX_TRACKED_US));
+    }
+
+    /// Percentiles per kind, in kind order.
+    pub fn summary(&self) -> Vec<HandleLatency> {
+        self.histograms
+            .iter()
+            .map(|(kind, histogram)| HandleLatency {
+                kind: kind.to_string(),
+                count: histogram.len(),
+                mean_us: histogram.mean(),
+                p50_us: histogram.value_at_quantile(0.5),
+                p90_us: histogram.value_at_quantile(0.9),
+                p99_us: histogram.value_at_quantile(0.99),
+                p999_us: histogram.value_at_quantile(0.999),
+                max_us: histogram.max(),
+            })
+            .collect()
+    }
+}
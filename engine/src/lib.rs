@@ -1,10 +1,17 @@
+pub mod breaker;
+pub mod closed;
+pub mod countdown;
+pub mod groups;
+pub mod latency;
 pub mod orderbook;
 pub mod processor;
+pub mod rules;
 
 pub use processor::Engine;
 
 /// Convenience entry point used by the binary.
-pub async fn run(redis_url: &str) -> Result<(), shared::CexError> {
+pub async fn run(redis_url: &str, rules: rules::MarketRulesConfig) -> Result<(), shared::CexError> {
     let mut engine = Engine::new(redis_url).await?;
+    engine.set_rules(rules);
     engine.run().await
 }
@@ -1,6 +1,10 @@
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let redis_url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string());
-    engine::run(&redis_url).await?;
+    let rules = match std::env::var("MARKET_RULES") {
+        Ok(json) => engine::rules::MarketRulesConfig::from_json(&json)?,
+        Err(_) => engine::rules::MarketRulesConfig::default(),
+    };
+    engine::run(&redis_url, rules).await?;
     Ok(())
 }
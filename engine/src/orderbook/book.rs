@@ -1,172 +1,361 @@
-use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use chrono::Utc;
-use rust_decimal::Decimal;
+use rust_decimal::{Decimal, RoundingStrategy};
 use shared::types::{
-    DepthLevel, DepthSnapshot, Order, OrderId, OrderSide, OrderStatus, OrderType, PartialFill,
-    Trade,
+    DepthLevel, DepthSnapshot, MarketState, Order, OrderId, OrderReason, OrderSide, OrderStatus,
+    OrderType, OrderUpdate, PartialFill, Trade, UserId,
 };
+use shared::CexError;
+
+use slab::Slab;
+
+use crate::orderbook::levels::{Level, LevelView, Node};
+use crate::orderbook::matching::{Fifo, MatchingStrategy};
+use crate::rules::{BandReference, MarketRules, PriceBand};
+
+/// How far past the best opposite price a market order may sweep unless it
+/// sets its own `max_slippage_bps`.
+pub const DEFAULT_MARKET_PROTECTION_BPS: u32 = 500;
+/// Decimal places of base quantity bought by quote-sized orders.
+pub const QUOTE_ORDER_QUANTITY_DP: u32 = 8;
+
+/// Everything that came out of submitting one order.
+#[derive(Debug, Default)]
+pub struct Execution {
+    pub trades: Vec<Trade>,
+    /// Lifecycle events in the order they happened: acceptance or rejection
+    /// of the incoming order, resting orders touched by each fill, then the
+    /// incoming order's state after matching.
+    pub updates: Vec<OrderUpdate>,
+    pub last_fill: Option<PartialFill>,
+}
 
 pub struct OrderBook {
     pair: String,
-    bids: BTreeMap<Decimal, VecDeque<Order>>, // highest price last when iterating ascending
-    asks: BTreeMap<Decimal, VecDeque<Order>>, // lowest price first
-    index: HashMap<OrderId, (OrderSide, Decimal)>,
+    /// Every resting order, linked into the queue of its level.
+    orders: Slab<Node>,
+    bids: BTreeMap<Decimal, Level>, // highest price last when iterating ascending
+    asks: BTreeMap<Decimal, Level>, // lowest price first
+    /// Slab key of each resting order.
+    index: HashMap<OrderId, usize>,
+    /// Resting orders per user, so mass cancels don't walk the whole book.
+    by_user: HashMap<UserId, HashSet<OrderId>>,
+    /// Default price band for market orders; `None` lets them sweep freely.
+    market_protection_bps: Option<u32>,
+    /// Band around the reference price that limit orders must fall within.
+    price_band: Option<PriceBand>,
+    last_price: Option<Decimal>,
+    state: MarketState,
+    matching: Box<dyn MatchingStrategy>,
+    /// Orders that set a new best price when they arrived, for strategies
+    /// that give them priority.
+    top_orders: HashSet<OrderId>,
 }
 
 impl OrderBook {
     pub fn new(pair: impl Into<String>) -> Self {
         Self {
             pair: pair.into(),
+            orders: Slab::new(),
             bids: BTreeMap::new(),
             asks: BTreeMap::new(),
             index: HashMap::new(),
+            by_user: HashMap::new(),
+            market_protection_bps: Some(DEFAULT_MARKET_PROTECTION_BPS),
+            price_band: None,
+            last_price: None,
+            state: MarketState::Continuous,
+            matching: Box::new(Fifo),
+            top_orders: HashSet::new(),
         }
     }
 
-    pub fn upsert(&mut self, mut order: Order) -> (Vec<Trade>, Option<PartialFill>) {
+    pub fn state(&self) -> MarketState {
+        self.state
+    }
+
+    /// Switches the trading state. Leaving an auction does not uncross the
+    /// book; call [`OrderBook::uncross`] first.
+    pub fn set_state(&mut self, state: MarketState) {
+        self.state = state;
+    }
+
+    pub fn with_rules(pair: impl Into<String>, rules: &MarketRules) -> Self {
+        let mut book = Self::new(pair);
+        book.apply_rules(rules);
+        book
+    }
+
+    pub fn apply_rules(&mut self, rules: &MarketRules) {
+        self.market_protection_bps = rules.market_protection_bps;
+        self.price_band = rules.price_band;
+        self.matching = rules.matching.strategy();
+    }
+
+    pub fn set_matching(&mut self, matching: Box<dyn MatchingStrategy>) {
+        self.matching = matching;
+    }
+
+    pub fn pair(&self) -> &str {
+        &self.pair
+    }
+
+    /// Price of the most recent trade.
+    pub fn last_price(&self) -> Option<Decimal> {
+        self.last_price
+    }
+
+    pub fn set_market_protection(&mut self, bps: Option<u32>) {
+        self.market_protection_bps = bps;
+    }
+
+    pub fn upsert(&mut self, order: Order) -> (Vec<Trade>, Option<PartialFill>) {
+        let execution = self.execute(order);
+        (execution.trades, execution.last_fill)
+    }
+
+    /// Validates, matches and rests `order`, reporting every lifecycle step.
+    pub fn execute(&mut self, mut order: Order) -> Execution {
+        let mut execution = Execution::default();
+        let rejection = self
+            .state_rejection(&order)
+            .or_else(|| rejection_reason(&order))
+            .or_else(|| self.band_violation(&order));
+        if let Some(reason) = rejection {
+            order.status = OrderStatus::Rejected;
+            order.reason = Some(reason);
+            execution.updates.push(order.update());
+            return execution;
+        }
+        if self.state.is_collecting() {
+            execution.updates.push(order.update());
+            self.enqueue(order);
+            return execution;
+        }
+        if let (Some(quote), Some(best)) =
+            (order.quote_quantity, self.best_opposite_price(order.side))
+        {
+            if affordable(quote, best) <= Decimal::ZERO {
+                order.status = OrderStatus::Rejected;
+                order.reason = Some(OrderReason::BudgetTooSmall);
+                execution.updates.push(order.update());
+                return execution;
+            }
+        }
+        // Market orders need something to trade against, and may only go as
+        // far as their band around the price they arrive at.
+        let band = match order.order_type {
+            OrderType::Limit => None,
+            OrderType::Market => match self.best_opposite_price(order.side) {
+                Some(best) => order
+                    .max_slippage_bps
+                    .or(self.market_protection_bps)
+                    .map(|bps| protection_limit(order.side, best, bps)),
+                None => {
+                    order.status = OrderStatus::Rejected;
+                    order.reason = Some(OrderReason::NoLiquidity);
+                    execution.updates.push(order.update());
+                    return execution;
+                }
+            },
+        };
+        execution.updates.push(order.update());
+
         let mut trades = Vec::new();
         let mut last_fill: Option<PartialFill> = None;
+        // Quote spent so far, for quote-sized orders.
+        let mut spent = Decimal::ZERO;
+        let mut outside_band = false;
 
         // Match against the opposite side first
-        loop {
-            let best_price = match self.best_opposite_price(order.side) {
-                Some(p) => p,
-                None => break,
-            };
-
+        while let Some(best_price) = self.best_opposite_price(order.side) {
             if !self.price_crosses(&order, best_price) {
                 break;
             }
-
-            let target_queue_opt = match order.side {
-                OrderSide::Buy => self.asks.get_mut(&best_price),
-                OrderSide::Sell => self.bids.get_mut(&best_price),
+            if band.is_some_and(|limit| beyond(order.side, best_price, limit)) {
+                outside_band = true;
+                break;
+            }
+            let incoming_remaining = match order.quote_quantity {
+                Some(quote) => affordable(quote - spent, best_price),
+                None => order.remaining(),
             };
+            if incoming_remaining <= Decimal::ZERO {
+                break;
+            }
 
-            let target_queue = match target_queue_opt {
-                Some(q) => q,
-                None => break,
+            let levels = match order.side {
+                OrderSide::Buy => &self.asks,
+                OrderSide::Sell => &self.bids,
             };
+            let Some(level) = levels.get(&best_price) else {
+                break;
+            };
+            let top_order = level
+                .front()
+                .is_some_and(|key| self.top_orders.contains(&self.orders[key].order.order_id));
+            let allocations = self.matching.allocate(
+                &LevelView::new(level, &self.orders),
+                top_order,
+                incoming_remaining,
+            );
+            if allocations.is_empty() {
+                break;
+            }
 
-            let maybe_resting = target_queue.pop_front();
-            let mut resting = match maybe_resting {
-                Some(r) => r,
-                None => {
+            for (resting_id, executed_qty) in allocations {
+                let Some(&key) = self.index.get(&resting_id) else {
                     continue;
-                }
-            };
+                };
+                let resting = self.fill_resting(key, best_price, executed_qty);
+                order.fill(best_price, executed_qty);
+                spent += best_price * executed_qty;
 
-            let resting_remaining = resting.remaining();
-            let incoming_remaining = order.remaining();
-            if resting_remaining <= Decimal::ZERO || incoming_remaining <= Decimal::ZERO {
-                continue;
-            }
+                // Quote-sized orders are settled once the sweep stops.
+                if order.quote_quantity.is_none() && order.remaining() == Decimal::ZERO {
+                    order.status = OrderStatus::Filled;
+                } else {
+                    order.status = OrderStatus::PartiallyFilled;
+                }
 
-            let executed_qty = if resting_remaining < incoming_remaining {
-                resting_remaining
-            } else {
-                incoming_remaining
-            };
+                let trade = match order.side {
+                    OrderSide::Buy => Trade::new(
+                        order.pair.clone(),
+                        best_price,
+                        executed_qty,
+                        order.order_id,
+                        resting.order_id,
+                    ),
+                    OrderSide::Sell => Trade::new(
+                        order.pair.clone(),
+                        best_price,
+                        executed_qty,
+                        resting.order_id,
+                        order.order_id,
+                    ),
+                };
+                trades.push(trade);
+                self.last_price = Some(best_price);
+                if resting.status == OrderStatus::Filled {
+                    self.remove(key);
+                }
+                execution.updates.push(resting);
 
-            resting.filled += executed_qty;
-            order.filled += executed_qty;
+                last_fill = Some(PartialFill {
+                    order_id: order.order_id,
+                    filled_qty: order.filled,
+                    price: best_price,
+                    remaining_qty: order.remaining(),
+                });
+            }
 
-            if resting.remaining() == Decimal::ZERO {
-                resting.status = OrderStatus::Filled;
-            } else {
-                resting.status = OrderStatus::PartiallyFilled;
+            if order.quote_quantity.is_none() && order.remaining() == Decimal::ZERO {
+                break;
             }
+        }
 
-            if order.remaining() == Decimal::ZERO {
-                order.status = OrderStatus::Filled;
-            } else {
-                order.status = OrderStatus::PartiallyFilled;
-            }
-
-            let trade_price = best_price;
-            let trade_qty = executed_qty;
-            let trade = match order.side {
-                OrderSide::Buy => Trade::new(
-                    order.pair.clone(),
-                    trade_price,
-                    trade_qty,
-                    order.order_id,
-                    resting.order_id,
-                ),
-                OrderSide::Sell => Trade::new(
-                    order.pair.clone(),
-                    trade_price,
-                    trade_qty,
-                    resting.order_id,
-                    order.order_id,
-                ),
+        let stop_reason = if outside_band {
+            OrderReason::PriceProtection
+        } else {
+            OrderReason::NoLiquidity
+        };
+        if let Some(quote) = order.quote_quantity {
+            // Done once the rest of the budget can't buy anything at the
+            // price the sweep stopped at.
+            order.quantity = order.filled;
+            let left = quote - spent;
+            let exhausted = match self.best_opposite_price(order.side) {
+                _ if left <= Decimal::ZERO => true,
+                Some(price) if !outside_band => affordable(left, price) <= Decimal::ZERO,
+                _ => false,
             };
-            trades.push(trade);
-
-            last_fill = Some(PartialFill {
-                order_id: order.order_id,
-                filled_qty: order.filled,
-                price: trade_price,
-                remaining_qty: order.remaining(),
-            });
-
-            if resting.remaining() > Decimal::ZERO {
-                target_queue.push_front(resting);
+            if exhausted && order.filled > Decimal::ZERO {
+                order.status = OrderStatus::Filled;
             } else {
-                self.index.remove(&resting.order_id);
+                order.status = OrderStatus::Expired;
+                order.reason = Some(if exhausted {
+                    OrderReason::BudgetTooSmall
+                } else {
+                    stop_reason
+                });
             }
-
-            if target_queue.is_empty() {
-                match order.side {
-                    OrderSide::Buy => {
-                        self.asks.remove(&best_price);
-                    }
-                    OrderSide::Sell => {
-                        self.bids.remove(&best_price);
-                    }
+        } else if order.remaining() > Decimal::ZERO {
+            // If still open and limit order, place into book; market orders
+            // never rest, so whatever did not execute expires.
+            match order.order_type {
+                OrderType::Limit => self.enqueue(order.clone()),
+                OrderType::Market => {
+                    order.status = OrderStatus::Expired;
+                    order.reason = Some(stop_reason);
                 }
             }
-
-            if order.remaining() == Decimal::ZERO {
-                break;
-            }
         }
-
-        // If still open and limit order, place into book
-        if order.remaining() > Decimal::ZERO && order.order_type == OrderType::Limit {
-            self.enqueue(order.clone());
+        if order.status != OrderStatus::New {
+            execution.updates.push(order.update());
         }
 
-        (trades, last_fill)
+        execution.trades = trades;
+        execution.last_fill = last_fill;
+        execution
     }
 
-    pub fn cancel(&mut self, order_id: OrderId) -> bool {
-        let (side, price) = match self.index.get(&order_id).copied() {
-            Some(v) => v,
-            None => return false,
-        };
+    /// A resting order, if it is in the book.
+    pub fn get(&self, order_id: OrderId) -> Option<&Order> {
+        let key = self.index.get(&order_id)?;
+        Some(&self.orders[*key].order)
+    }
+
+    /// Open quantity resting at `price` on `side`.
+    pub fn level_quantity(&self, side: OrderSide, price: Decimal) -> Decimal {
         let levels = match side {
-            OrderSide::Buy => &mut self.bids,
-            OrderSide::Sell => &mut self.asks,
+            OrderSide::Buy => &self.bids,
+            OrderSide::Sell => &self.asks,
         };
+        levels.get(&price).map(Level::total).unwrap_or_default()
+    }
 
-        if let Some(queue) = levels.get_mut(&price) {
-            let mut i = 0usize;
-            while i < queue.len() {
-                if queue[i].order_id == order_id {
-                    queue.remove(i);
-                    self.index.remove(&order_id);
-                    if queue.is_empty() {
-                        levels.remove(&price);
-                    }
-                    return true;
-                }
-                i += 1;
-            }
-        }
+    pub fn cancel(&mut self, order_id: OrderId) -> bool {
+        self.cancel_order(order_id).is_some()
+    }
 
-        false
+    /// Removes a resting order and returns its final state.
+    pub fn cancel_order(&mut self, order_id: OrderId) -> Option<OrderUpdate> {
+        let key = self.index.get(&order_id).copied()?;
+        let mut order = self.remove(key);
+        order.status = OrderStatus::Cancelled;
+        order.reason = Some(OrderReason::UserCancelled);
+        Some(order.update())
+    }
+
+    /// Cancels every resting order of `user_id`, optionally only on one side,
+    /// and returns their final states.
+    pub fn cancel_user_orders(
+        &mut self,
+        user_id: UserId,
+        side: Option<OrderSide>,
+        reason: OrderReason,
+    ) -> Vec<OrderUpdate> {
+        let Some(ids) = self.by_user.get(&user_id) else {
+            return Vec::new();
+        };
+        let mut ids: Vec<OrderId> = ids
+            .iter()
+            .copied()
+            .filter(|id| match side {
+                Some(side) => self.get(*id).is_some_and(|order| order.side == side),
+                None => true,
+            })
+            .collect();
+        // Deterministic output regardless of hash order.
+        ids.sort();
+        ids.into_iter()
+            .filter_map(|id| {
+                let mut update = self.cancel_order(id)?;
+                update.reason = Some(reason);
+                Some(update)
+            })
+            .collect()
     }
 
     pub fn depth(&self) -> DepthSnapshot {
@@ -174,22 +363,18 @@ impl OrderBook {
             .bids
             .iter()
             .rev()
-            .map(|(p, orders)| DepthLevel {
+            .map(|(p, level)| DepthLevel {
                 price: *p,
-                quantity: orders
-                    .iter()
-                    .fold(Decimal::ZERO, |acc, o| acc + o.remaining()),
+                quantity: level.total(),
             })
             .collect();
 
         let asks = self
             .asks
             .iter()
-            .map(|(p, orders)| DepthLevel {
+            .map(|(p, level)| DepthLevel {
                 price: *p,
-                quantity: orders
-                    .iter()
-                    .fold(Decimal::ZERO, |acc, o| acc + o.remaining()),
+                quantity: level.total(),
             })
             .collect();
 
@@ -201,15 +386,260 @@ impl OrderBook {
         }
     }
 
+    /// Checks the book's bookkeeping: every resting order is open, indexed
+    /// and queued at its own level, level totals match their queues, and the
+    /// book is not crossed outside a call phase. For tests and debugging; it
+    /// walks the whole book.
+    pub fn validate(&self) -> Result<(), CexError> {
+        let broken = |problem: String| Err(CexError::Internal(format!("{}: {problem}", self.pair)));
+        let mut resting = 0;
+        for (side, levels) in [(OrderSide::Buy, &self.bids), (OrderSide::Sell, &self.asks)] {
+            for (price, level) in levels {
+                if level.is_empty() {
+                    return broken(format!("empty {side:?} level at {price}"));
+                }
+                let keys = match level.keys(&self.orders) {
+                    Ok(keys) => keys,
+                    Err(problem) => return broken(format!("{side:?} level at {price}: {problem}")),
+                };
+                for key in keys {
+                    let order = &self.orders[key].order;
+                    let id = order.order_id;
+                    if order.side != side || order.price != *price {
+                        return broken(format!("{id} is queued at {side:?} {price}"));
+                    }
+                    if order.remaining() <= Decimal::ZERO {
+                        return broken(format!("{id} rests with nothing open"));
+                    }
+                    if self.index.get(&id) != Some(&key) {
+                        return broken(format!("{id} is not indexed at slot {key}"));
+                    }
+                    if !self
+                        .by_user
+                        .get(&order.user_id)
+                        .is_some_and(|ids| ids.contains(&id))
+                    {
+                        return broken(format!("{id} is missing from its user's orders"));
+                    }
+                    resting += 1;
+                }
+            }
+        }
+        let by_user: usize = self.by_user.values().map(HashSet::len).sum();
+        if self.orders.len() != resting || self.index.len() != resting || by_user != resting {
+            return broken(format!(
+                "{resting} queued orders but {} stored, {} indexed and {by_user} by user",
+                self.orders.len(),
+                self.index.len()
+            ));
+        }
+        if let Some(id) = self
+            .top_orders
+            .iter()
+            .find(|id| !self.index.contains_key(id))
+        {
+            return broken(format!("top order {id} is not resting"));
+        }
+        if !self.state.is_collecting() {
+            if let (Some(bid), Some(ask)) = (self.bids.keys().next_back(), self.asks.keys().next())
+            {
+                if bid >= ask {
+                    return broken(format!("crossed at {bid} / {ask}"));
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn enqueue(&mut self, order: Order) {
         let levels = match order.side {
             OrderSide::Buy => &mut self.bids,
             OrderSide::Sell => &mut self.asks,
         };
-        let queue = levels.entry(order.price).or_insert_with(VecDeque::new);
-        queue.push_back(order.clone());
-        self.index
-            .insert(order.order_id, (order.side.clone(), order.price));
+        let improves = match order.side {
+            OrderSide::Buy => {
+                !matches!(levels.keys().next_back(), Some(best) if order.price <= *best)
+            }
+            OrderSide::Sell => !matches!(levels.keys().next(), Some(best) if order.price >= *best),
+        };
+        if improves {
+            self.top_orders.insert(order.order_id);
+        }
+        let (order_id, user_id) = (order.order_id, order.user_id);
+        let key = levels
+            .entry(order.price)
+            .or_default()
+            .push_back(&mut self.orders, order);
+        self.index.insert(order_id, key);
+        self.by_user.entry(user_id).or_default().insert(order_id);
+    }
+
+    /// Takes a resting order out of its level and the book's indexes.
+    fn remove(&mut self, key: usize) -> Order {
+        let (side, price) = {
+            let order = &self.orders[key].order;
+            (order.side, order.price)
+        };
+        let levels = match side {
+            OrderSide::Buy => &mut self.bids,
+            OrderSide::Sell => &mut self.asks,
+        };
+        let level = levels
+            .get_mut(&price)
+            .expect("every resting order has a level");
+        let order = level.unlink(&mut self.orders, key);
+        if level.is_empty() {
+            levels.remove(&price);
+        }
+        self.index.remove(&order.order_id);
+        self.top_orders.remove(&order.order_id);
+        unlink_user(&mut self.by_user, order.user_id, order.order_id);
+        order
+    }
+
+    /// Executes `quantity` of a resting order at `price`, leaving it in place.
+    fn fill_resting(&mut self, key: usize, price: Decimal, quantity: Decimal) -> OrderUpdate {
+        let resting = &mut self.orders[key].order;
+        resting.fill(price, quantity);
+        resting.status = if resting.remaining() == Decimal::ZERO {
+            OrderStatus::Filled
+        } else {
+            OrderStatus::PartiallyFilled
+        };
+        let (side, level_price) = (resting.side, resting.price);
+        let update = resting.update();
+        let levels = match side {
+            OrderSide::Buy => &mut self.bids,
+            OrderSide::Sell => &mut self.asks,
+        };
+        if let Some(level) = levels.get_mut(&level_price) {
+            level.filled(quantity);
+        }
+        update
+    }
+
+    /// Where the collected orders would uncross: the price executing the most
+    /// volume, then leaving the least imbalance, then closest to the last
+    /// trade, then the lowest. `None` if the book does not cross.
+    pub fn indicative(&self) -> Option<(Decimal, Decimal)> {
+        let mut prices: Vec<Decimal> = self.bids.keys().chain(self.asks.keys()).copied().collect();
+        prices.sort();
+        prices.dedup();
+        // Asks at or below each price, filled in ascending.
+        let mut supply = Vec::with_capacity(prices.len());
+        let mut asks = self.asks.iter().peekable();
+        let mut total = Decimal::ZERO;
+        for &price in &prices {
+            while let Some((_, level)) = asks.next_if(|(p, _)| **p <= price) {
+                total += level.total();
+            }
+            supply.push(total);
+        }
+        // Bids at or above each price, filled in descending.
+        let mut demand = vec![Decimal::ZERO; prices.len()];
+        let mut bids = self.bids.iter().rev().peekable();
+        let mut total = Decimal::ZERO;
+        for (i, &price) in prices.iter().enumerate().rev() {
+            while let Some((_, level)) = bids.next_if(|(p, _)| **p >= price) {
+                total += level.total();
+            }
+            demand[i] = total;
+        }
+
+        let mut best: Option<(Decimal, Decimal, Decimal)> = None;
+        for (price, (demand, supply)) in prices.into_iter().zip(demand.into_iter().zip(supply)) {
+            let executed = demand.min(supply);
+            if executed <= Decimal::ZERO {
+                continue;
+            }
+            let imbalance = (demand - supply).abs();
+            let better = match best {
+                None => true,
+                Some((best_price, best_executed, best_imbalance)) => {
+                    let distance = |p: Decimal| self.last_price.map(|last| (p - last).abs());
+                    (executed, -imbalance) > (best_executed, -best_imbalance)
+                        || ((executed, imbalance) == (best_executed, best_imbalance)
+                            && distance(price) < distance(best_price))
+                }
+            };
+            if better {
+                best = Some((price, executed, imbalance));
+            }
+        }
+        best.map(|(price, executed, _)| (price, executed))
+    }
+
+    /// Matches the collected orders at the indicative price, in price-time
+    /// priority. The state is left unchanged.
+    pub fn uncross(&mut self) -> Execution {
+        let mut execution = Execution::default();
+        let Some((price, _)) = self.indicative() else {
+            return execution;
+        };
+        while let (Some((&bid_price, bids)), Some((&ask_price, asks))) =
+            (self.bids.last_key_value(), self.asks.first_key_value())
+        {
+            if bid_price < price || ask_price > price {
+                break;
+            }
+            let (Some(bid), Some(ask)) = (bids.front(), asks.front()) else {
+                break;
+            };
+            let quantity = self.orders[bid]
+                .order
+                .remaining()
+                .min(self.orders[ask].order.remaining());
+            let buy = self.fill_resting(bid, price, quantity);
+            let sell = self.fill_resting(ask, price, quantity);
+            execution.trades.push(Trade::new(
+                self.pair.clone(),
+                price,
+                quantity,
+                buy.order_id,
+                sell.order_id,
+            ));
+            self.last_price = Some(price);
+            for (key, update) in [(bid, buy), (ask, sell)] {
+                if update.status == OrderStatus::Filled {
+                    self.remove(key);
+                }
+                execution.updates.push(update);
+            }
+        }
+        execution
+    }
+
+    fn state_rejection(&self, order: &Order) -> Option<OrderReason> {
+        match self.state {
+            MarketState::Continuous => None,
+            MarketState::PreOpen | MarketState::Auction => {
+                (order.order_type == OrderType::Market).then_some(OrderReason::TradingState)
+            }
+            MarketState::Halted => Some(OrderReason::MarketHalted),
+            MarketState::Closed => Some(OrderReason::TradingState),
+        }
+    }
+
+    /// Limit orders priced outside the band around the reference price.
+    fn band_violation(&self, order: &Order) -> Option<OrderReason> {
+        let band = self.price_band?;
+        if order.order_type != OrderType::Limit {
+            return None;
+        }
+        let reference = match band.reference {
+            BandReference::LastTrade => self.last_price?,
+            BandReference::Mid => {
+                let bid = self.bids.keys().next_back()?;
+                let ask = self.asks.keys().next()?;
+                (bid + ask) / Decimal::TWO
+            }
+        };
+        let offset = reference * Decimal::from(band.bps) / Decimal::from(10_000);
+        let outside = match order.side {
+            OrderSide::Buy => order.price > reference + offset,
+            OrderSide::Sell => order.price < reference - offset,
+        };
+        outside.then_some(OrderReason::PriceBand)
     }
 
     fn best_opposite_price(&self, side: OrderSide) -> Option<Decimal> {
@@ -229,3 +659,59 @@ impl OrderBook {
         }
     }
 }
+
+fn rejection_reason(order: &Order) -> Option<OrderReason> {
+    let valid_quantity = match order.quote_quantity {
+        Some(quote) => {
+            quote > Decimal::ZERO
+                && order.quantity == Decimal::ZERO
+                && order.order_type == OrderType::Market
+                && order.side == OrderSide::Buy
+        }
+        None => order.quantity > Decimal::ZERO,
+    };
+    if !valid_quantity {
+        Some(OrderReason::InvalidQuantity)
+    } else if order.order_type == OrderType::Limit && order.price <= Decimal::ZERO {
+        Some(OrderReason::InvalidPrice)
+    } else {
+        None
+    }
+}
+
+/// Worst price a market order arriving at `best` may trade at.
+fn protection_limit(side: OrderSide, best: Decimal, bps: u32) -> Decimal {
+    let offset = best * Decimal::from(bps) / Decimal::from(10_000);
+    match side {
+        OrderSide::Buy => best + offset,
+        OrderSide::Sell => (best - offset).max(Decimal::ZERO),
+    }
+}
+
+fn beyond(side: OrderSide, price: Decimal, limit: Decimal) -> bool {
+    match side {
+        OrderSide::Buy => price > limit,
+        OrderSide::Sell => price < limit,
+    }
+}
+
+/// Base quantity `budget` buys at `price`, rounded down.
+fn affordable(budget: Decimal, price: Decimal) -> Decimal {
+    if budget <= Decimal::ZERO {
+        return Decimal::ZERO;
+    }
+    (budget / price).round_dp_with_strategy(QUOTE_ORDER_QUANTITY_DP, RoundingStrategy::ToZero)
+}
+
+fn unlink_user(
+    by_user: &mut HashMap<UserId, HashSet<OrderId>>,
+    user_id: UserId,
+    order_id: OrderId,
+) {
+    if let Some(ids) = by_user.get_mut(&user_id) {
+        ids.remove(&order_id);
+        if ids.is_empty() {
+            by_user.remove(&user_id);
+        }
+    }
+}
@@ -1,7 +1,8 @@
 use std::collections::BTreeMap;
 
 use rust_decimal::Decimal;
-use shared::types::DepthLevel;
+use shared::types::{DepthLevel, Order};
+use slab::Slab;
 
 pub fn aggregate_levels(levels: &BTreeMap<Decimal, Decimal>) -> Vec<DepthLevel> {
     levels
@@ -12,3 +13,154 @@ pub fn aggregate_levels(levels: &BTreeMap<Decimal, Decimal>) -> Vec<DepthLevel>
         })
         .collect()
 }
+
+/// A resting order and its neighbours in its level's queue.
+#[derive(Debug)]
+pub(crate) struct Node {
+    pub(crate) order: Order,
+    prev: Option<usize>,
+    next: Option<usize>,
+}
+
+/// Queue of one price level, threaded through the book's order slab so any
+/// order can be unlinked without a scan. Keeps its open quantity up to date.
+#[derive(Debug, Default)]
+pub struct Level {
+    head: Option<usize>,
+    tail: Option<usize>,
+    len: usize,
+    total: Decimal,
+}
+
+impl Level {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Open quantity of every order at this price.
+    pub fn total(&self) -> Decimal {
+        self.total
+    }
+
+    /// Slab key of the order first in line.
+    pub(crate) fn front(&self) -> Option<usize> {
+        self.head
+    }
+
+    pub(crate) fn push_back(&mut self, orders: &mut Slab<Node>, order: Order) -> usize {
+        self.total += order.remaining();
+        let key = orders.insert(Node {
+            order,
+            prev: self.tail,
+            next: None,
+        });
+        match self.tail {
+            Some(tail) => orders[tail].next = Some(key),
+            None => self.head = Some(key),
+        }
+        self.tail = Some(key);
+        self.len += 1;
+        key
+    }
+
+    /// Takes the order at `key`, which must be in this level, out of the
+    /// queue and the slab.
+    pub(crate) fn unlink(&mut self, orders: &mut Slab<Node>, key: usize) -> Order {
+        let node = orders.remove(key);
+        match node.prev {
+            Some(prev) => orders[prev].next = node.next,
+            None => self.head = node.next,
+        }
+        match node.next {
+            Some(next) => orders[next].prev = node.prev,
+            None => self.tail = node.prev,
+        }
+        self.len -= 1;
+        self.total -= node.order.remaining();
+        node.order
+    }
+
+    /// Accounts for `quantity` of one of its orders executing.
+    pub(crate) fn filled(&mut self, quantity: Decimal) {
+        self.total -= quantity;
+    }
+
+    /// Slab keys in queue order, once the links, length and total have been
+    /// checked against the queue.
+    pub(crate) fn keys(&self, orders: &Slab<Node>) -> Result<Vec<usize>, String> {
+        let mut keys = Vec::with_capacity(self.len);
+        let mut total = Decimal::ZERO;
+        let mut prev = None;
+        let mut cursor = self.head;
+        while let Some(key) = cursor {
+            if keys.len() >= orders.len() {
+                return Err("queue loops".to_string());
+            }
+            let node = orders
+                .get(key)
+                .ok_or_else(|| format!("queue links to free slot {key}"))?;
+            if node.prev != prev {
+                return Err(format!(
+                    "slot {key} links back to {:?} instead of {prev:?}",
+                    node.prev
+                ));
+            }
+            total += node.order.remaining();
+            keys.push(key);
+            prev = Some(key);
+            cursor = node.next;
+        }
+        if self.tail != prev {
+            return Err(format!(
+                "tail is {:?} but the queue ends at {prev:?}",
+                self.tail
+            ));
+        }
+        if keys.len() != self.len {
+            return Err(format!("len is {} for {} orders", self.len, keys.len()));
+        }
+        if total != self.total {
+            return Err(format!("total is {} for {total} open", self.total));
+        }
+        Ok(keys)
+    }
+}
+
+/// Read-only view of one level's orders, in time priority.
+#[derive(Clone, Copy)]
+pub struct LevelView<'a> {
+    level: &'a Level,
+    orders: &'a Slab<Node>,
+}
+
+impl<'a> LevelView<'a> {
+    pub(crate) fn new(level: &'a Level, orders: &'a Slab<Node>) -> Self {
+        Self { level, orders }
+    }
+
+    pub fn len(&self) -> usize {
+        self.level.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.level.is_empty()
+    }
+
+    pub fn total(&self) -> Decimal {
+        self.level.total()
+    }
+
+    pub fn front(&self) -> Option<&'a Order> {
+        self.level.head.map(|key| &self.orders[key].order)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &'a Order> + 'a {
+        let orders = self.orders;
+        std::iter::successors(self.level.head, move |key| orders[*key].next)
+            .map(move |key| &orders[key].order)
+    }
+}
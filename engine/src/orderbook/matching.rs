@@ -0,0 +1,178 @@
+use std::fmt;
+
+use rust_decimal::{Decimal, RoundingStrategy};
+use shared::types::{Order, OrderId};
+
+use crate::orderbook::levels::LevelView;
+
+/// Decimal places pro-rata shares are rounded down to without a lot size, so
+/// fills and level totals stay exact.
+pub const UNLOTTED_SHARE_DP: u32 = 8;
+
+/// Decides how an incoming order's quantity is shared among the orders
+/// resting at one price level.
+pub trait MatchingStrategy: fmt::Debug + Send + Sync {
+    /// Splits up to `quantity` across `level`, whose front order is the
+    /// level's top order when `top_order` is set. Returns `(order, quantity)`
+    /// pairs in the order the fills print; together they cover `quantity` or
+    /// the whole level, whichever is less.
+    fn allocate(
+        &self,
+        level: &LevelView<'_>,
+        top_order: bool,
+        quantity: Decimal,
+    ) -> Vec<(OrderId, Decimal)>;
+}
+
+/// Price-time priority: the queue fills front to back.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Fifo;
+
+impl MatchingStrategy for Fifo {
+    fn allocate(
+        &self,
+        level: &LevelView<'_>,
+        _top_order: bool,
+        quantity: Decimal,
+    ) -> Vec<(OrderId, Decimal)> {
+        let mut left = quantity;
+        let mut allocations = Vec::new();
+        for order in level.iter() {
+            if left <= Decimal::ZERO {
+                break;
+            }
+            let share = order.remaining().min(left);
+            allocations.push((order.order_id, share));
+            left -= share;
+        }
+        allocations
+    }
+}
+
+/// The level's top order fills first, up to `top_order_max`, then the rest
+/// of the queue front to back; whatever the top order still has open waits
+/// behind everyone else at the level. Without a top order this is [`Fifo`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FifoTopOrder {
+    pub top_order_max: Decimal,
+}
+
+impl MatchingStrategy for FifoTopOrder {
+    fn allocate(
+        &self,
+        level: &LevelView<'_>,
+        top_order: bool,
+        quantity: Decimal,
+    ) -> Vec<(OrderId, Decimal)> {
+        let orders: Vec<&Order> = level.iter().collect();
+        if !top_order || orders.is_empty() {
+            return Fifo.allocate(level, top_order, quantity);
+        }
+        let mut shares = vec![Decimal::ZERO; orders.len()];
+        let mut left = quantity;
+        shares[0] = orders[0]
+            .remaining()
+            .min(self.top_order_max.max(Decimal::ZERO))
+            .min(left);
+        left -= shares[0];
+        for i in (1..orders.len()).chain([0]) {
+            if left <= Decimal::ZERO {
+                break;
+            }
+            let extra = (orders[i].remaining() - shares[i]).min(left);
+            shares[i] += extra;
+            left -= extra;
+        }
+        orders
+            .iter()
+            .zip(shares)
+            .filter(|(_, share)| *share > Decimal::ZERO)
+            .map(|(order, share)| (order.order_id, share))
+            .collect()
+    }
+}
+
+/// Shares the quantity in proportion to resting size, each share rounded
+/// down to `lot_size`, or to [`UNLOTTED_SHARE_DP`] places without one.
+/// Shares below `min_allocation` are dropped, and what rounding leaves over
+/// goes out in time priority to orders that end up with at least
+/// `min_allocation`; only if none can take it does it go to the rest. With
+/// `top_order` set, the level's top order is filled in full before the rest
+/// is shared.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProRata {
+    pub lot_size: Option<Decimal>,
+    pub min_allocation: Decimal,
+    pub top_order: bool,
+}
+
+impl ProRata {
+    fn round(&self, quantity: Decimal) -> Decimal {
+        match self.lot_size {
+            Some(lot) if lot > Decimal::ZERO => (quantity / lot).floor() * lot,
+            _ => quantity.round_dp_with_strategy(UNLOTTED_SHARE_DP, RoundingStrategy::ToZero),
+        }
+    }
+}
+
+impl MatchingStrategy for ProRata {
+    fn allocate(
+        &self,
+        level: &LevelView<'_>,
+        top_order: bool,
+        quantity: Decimal,
+    ) -> Vec<(OrderId, Decimal)> {
+        let orders: Vec<&Order> = level.iter().collect();
+        let mut shares = vec![Decimal::ZERO; orders.len()];
+        let mut left = quantity;
+        let mut first = 0;
+        if self.top_order && top_order {
+            if let Some(top) = orders.first() {
+                shares[0] = top.remaining().min(left);
+                left -= shares[0];
+                first = 1;
+            }
+        }
+
+        let total = level.total()
+            - orders
+                .iter()
+                .take(first)
+                .map(|o| o.remaining())
+                .sum::<Decimal>();
+        if left > Decimal::ZERO && total > Decimal::ZERO {
+            let pool = left.min(total);
+            for (i, order) in orders.iter().enumerate().skip(first) {
+                let share = self
+                    .round(order.remaining() * pool / total)
+                    .min(order.remaining());
+                if share >= self.min_allocation {
+                    shares[i] = share;
+                    left -= share;
+                }
+            }
+        }
+
+        // Whatever rounding left over, front to back; sub-minimum fills only
+        // on the second pass, so the level still covers the quantity.
+        for strict in [true, false] {
+            for (order, share) in orders.iter().zip(shares.iter_mut()) {
+                if left <= Decimal::ZERO {
+                    break;
+                }
+                let extra = (order.remaining() - *share).min(left);
+                if extra <= Decimal::ZERO || (strict && *share + extra < self.min_allocation) {
+                    continue;
+                }
+                *share += extra;
+                left -= extra;
+            }
+        }
+        orders
+            .iter()
+            .zip(shares)
+            .filter(|(_, share)| *share > Decimal::ZERO)
+            .map(|(order, share)| (order.order_id, share))
+            .collect()
+    }
+}
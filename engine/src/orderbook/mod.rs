@@ -1,4 +1,6 @@
 pub mod book;
 pub mod levels;
+pub mod matching;
 
-pub use book::OrderBook;
+pub use book::{Execution, OrderBook};
+pub use matching::MatchingStrategy;
@@ -1,17 +1,58 @@
 use std::collections::HashMap;
+use std::future::Future;
+use std::time::{Duration, Instant};
 
-use redis::{queues::CHANNEL_EVENTS, RedisManager};
-use shared::types::{NewOrder, Order};
-use shared::{from_json, to_json, CexError, Envelope, Event};
-use tracing::{error, info};
+use chrono::Utc;
+use redis::queues::{
+    CHANNEL_EVENTS, QUEUE_ORDER_CANCEL, QUEUE_ORDER_NEW, STREAM_EVENTS, STREAM_EVENTS_MAX_LEN,
+};
+use redis::{DeadLetter, DeadLetterOrigin, RedisManager};
+use rust_decimal::Decimal;
+use shared::types::{
+    AuctionIndicative, CancelAll, CancelAllSummary, CancelOrder, CancelRejectReason,
+    CancelRejected, CountdownCancel, DepthSnapshot, HaltReason, MarketHalt, MarketResume,
+    MarketState, MarketStateChange, MarketStateRequest, NewOrder, OrderReason, OrderStatus,
+    OrderUpdate,
+};
+use shared::utils::Backoff;
+use shared::{to_json, CexError, Envelope, Event};
+use tracing::{error, warn};
+use uuid::Uuid;
 
+use crate::breaker::VolatilityMonitor;
+use crate::closed::ClosedOrders;
+use crate::countdown::Countdowns;
+use crate::groups::OrderGroups;
+use crate::latency::LatencyRecorder;
 use crate::orderbook::OrderBook;
+use crate::rules::MarketRulesConfig;
 
 const ENGINE_SOURCE: &str = "engine";
+/// How many filled, cancelled or expired orders are remembered for cancel replies.
+const CLOSED_ORDERS_CAPACITY: usize = 100_000;
+/// How often the handling latency summary is written out for `/metrics`.
+const LATENCY_PUBLISH_INTERVAL: Duration = Duration::from_secs(10);
+/// Tries per Redis write of engine output before it is logged and given up.
+const PUBLISH_ATTEMPTS: u32 = 3;
+const PUBLISH_RETRY_INITIAL: Duration = Duration::from_millis(20);
+const PUBLISH_RETRY_MAX: Duration = Duration::from_millis(200);
 
 pub struct Engine {
     redis: RedisManager,
     books: HashMap<String, OrderBook>,
+    /// OCO and bracket links, per market like the books they act on.
+    groups: HashMap<String, OrderGroups>,
+    /// Last sequence number published per market.
+    sequences: HashMap<String, u64>,
+    closed: ClosedOrders,
+    /// State changes due at a set time, such as the end of a circuit
+    /// breaker's cooldown or of a reopening auction.
+    scheduled: HashMap<String, (Instant, MarketState)>,
+    countdowns: Countdowns,
+    rules: MarketRulesConfig,
+    breakers: HashMap<String, VolatilityMonitor>,
+    latency: LatencyRecorder,
+    latency_published: Instant,
 }
 
 impl Engine {
@@ -20,95 +61,560 @@ impl Engine {
         Ok(Self {
             redis,
             books: HashMap::new(),
+            groups: HashMap::new(),
+            sequences: HashMap::new(),
+            closed: ClosedOrders::new(CLOSED_ORDERS_CAPACITY),
+            scheduled: HashMap::new(),
+            countdowns: Countdowns::new(),
+            rules: MarketRulesConfig::default(),
+            breakers: HashMap::new(),
+            latency: LatencyRecorder::new(),
+            latency_published: Instant::now(),
         })
     }
 
+    /// Replaces the trading rules, including for markets already open.
+    pub fn set_rules(&mut self, rules: MarketRulesConfig) {
+        for (pair, book) in &mut self.books {
+            book.apply_rules(rules.for_market(pair));
+        }
+        self.breakers.clear();
+        self.rules = rules;
+    }
+
+    /// Trading state of a market; markets without orders yet are continuous.
+    pub fn state(&self, market: &str) -> MarketState {
+        self.books
+            .get(market)
+            .map_or(MarketState::Continuous, OrderBook::state)
+    }
+
+    /// The market's book and group registry, opened under its rules on
+    /// first use.
+    fn market_mut(&mut self, pair: &str) -> (&mut OrderBook, &mut OrderGroups) {
+        let rules = self.rules.for_market(pair);
+        let book = self
+            .books
+            .entry(pair.to_string())
+            .or_insert_with(|| OrderBook::with_rules(pair, rules));
+        let groups = self.groups.entry(pair.to_string()).or_default();
+        (book, groups)
+    }
+
     pub async fn run(&mut self) -> Result<(), CexError> {
         loop {
+            // Queue pops time out every second, so switches fire and
+            // scheduled state changes happen at most about a second late.
+            self.fire_countdowns().await;
+            self.run_scheduled().await;
+            self.publish_latency().await;
             tokio::select! {
                 new_msg = self.redis.pop_new_order(1) => {
                     if let Ok(Some(payload)) = new_msg {
-                        if let Err(err) = self.handle_payload(payload).await {
-                            error!("new order handling error: {err}");
-                        }
+                        self.handle_or_dead_letter(QUEUE_ORDER_NEW, payload).await;
                     }
                 }
                 cancel_msg = self.redis.pop_cancel_order(1) => {
                     if let Ok(Some(payload)) = cancel_msg {
-                        if let Err(err) = self.handle_payload(payload).await {
-                            error!("cancel order handling error: {err}");
-                        }
+                        self.handle_or_dead_letter(QUEUE_ORDER_CANCEL, payload).await;
                     }
                 }
             }
         }
     }
 
-    async fn handle_payload(&mut self, payload: String) -> Result<(), CexError> {
-        let envelope: Envelope = from_json(&payload)?;
-        match envelope.event {
-            Event::OrderNew(new_order) => self.process_new_order(new_order).await?,
-            Event::OrderCancel { order_id } => self.process_cancel(order_id).await?,
+    /// Messages that cannot be decoded or are not valid requests are parked in
+    /// the dead letter store instead of being dropped. Anything else may have
+    /// failed after the book changed, where a re-drive would apply it twice,
+    /// so it is only logged.
+    async fn handle_or_dead_letter(&mut self, queue: &str, payload: Vec<u8>) {
+        let err = match self.handle_payload(&payload).await {
+            Ok(()) => return,
+            Err(err) if is_bad_message(&err) => err,
+            Err(err) => {
+                error!(queue, "failed to handle message: {err}");
+                return;
+            }
+        };
+        error!(queue, "dead-lettering message: {err}");
+        let letter = DeadLetter::new(
+            ENGINE_SOURCE,
+            DeadLetterOrigin::Queue(queue.to_string()),
+            payload,
+            err,
+        );
+        if let Err(err) = self.redis.push_dead_letter(&letter).await {
+            error!("failed to dead-letter message: {err}");
+        }
+    }
+
+    /// Writes the latency summary out if it is due. Failures are only logged
+    /// and retried at the next interval.
+    async fn publish_latency(&mut self) {
+        if self.latency_published.elapsed() < LATENCY_PUBLISH_INTERVAL {
+            return;
+        }
+        self.latency_published = Instant::now();
+        if let Err(err) = self
+            .redis
+            .store_engine_latency(&self.latency.summary())
+            .await
+        {
+            warn!("failed to publish latency summary: {err}");
+        }
+    }
+
+    /// Decodes and handles one queued message, recording how long it took
+    /// under its message kind.
+    async fn handle_payload(&mut self, payload: &[u8]) -> Result<(), CexError> {
+        let started = Instant::now();
+        let envelope = Envelope::decode(payload)?;
+        let kind = match &envelope.event {
+            Event::OrderNew(_) => "order_new",
+            Event::CancelRequest(_) | Event::OrderCancel { .. } => "cancel",
+            Event::CancelAll(_) => "cancel_all",
+            Event::CountdownCancel(_) => "countdown_cancel",
+            Event::SetMarketState(_) => "set_market_state",
+            _ => "unsupported",
+        };
+        let result = self.dispatch(envelope.event).await;
+        self.latency.record(kind, started.elapsed());
+        result
+    }
+
+    async fn dispatch(&mut self, event: Event) -> Result<(), CexError> {
+        match event {
+            Event::OrderNew(new_order) => self.process_new_order(new_order).await,
+            Event::CancelRequest(cancel) => self.process_cancel(cancel).await,
+            // Carries no user, so there is no owner to check it against.
+            Event::OrderCancel { order_id } => {
+                return Err(CexError::Validation(format!(
+                    "legacy cancel of {order_id} names no user; send a CancelRequest"
+                )))
+            }
+            Event::CancelAll(request) => self.process_cancel_all(request).await,
+            Event::CountdownCancel(countdown) => self.arm_countdown(countdown),
+            Event::SetMarketState(request) => self.apply_state_request(request).await,
             _ => {
-                info!("ignoring unsupported event from queue");
+                return Err(CexError::Validation(
+                    "unsupported event on order queue".to_string(),
+                ))
             }
         }
         Ok(())
     }
 
-    async fn process_new_order(&mut self, new_order: NewOrder) -> Result<(), CexError> {
+    async fn process_new_order(&mut self, new_order: NewOrder) {
         let pair = new_order.pair.clone();
-        let book = self
+        let (book, groups) = self.market_mut(&pair);
+        let events = groups.submit(book, new_order);
+        let depth = book.depth();
+        let tripped = self.check_volatility(&pair, &events);
+        self.publish_all(&pair, events).await;
+        self.publish_depth(&pair, depth).await;
+        self.publish_indicative(&pair).await;
+
+        if let Some(cooldown) = tripped {
+            self.halt_for_volatility(&pair, cooldown).await;
+        }
+    }
+
+    /// Feeds the market's new trades to its circuit breaker; returns the
+    /// cooldown if it tripped.
+    fn check_volatility(&mut self, pair: &str, events: &[Event]) -> Option<Duration> {
+        let config = self.rules.for_market(pair).volatility?;
+        let monitor = self
+            .breakers
+            .entry(pair.to_string())
+            .or_insert_with(|| VolatilityMonitor::new(config));
+        let now = Instant::now();
+        let mut tripped = false;
+        for event in events {
+            if let Event::TradeExecuted(trade) = event {
+                tripped |= monitor.record(now, trade.price);
+            }
+        }
+        tripped.then(|| monitor.cooldown())
+    }
+
+    /// Halts the market for the cooldown, after which it reopens through an
+    /// auction if its rules ask for one.
+    async fn halt_for_volatility(&mut self, pair: &str, cooldown: Duration) {
+        warn!(%pair, ?cooldown, "volatility circuit breaker tripped, halting market");
+        self.transition(pair, MarketState::Halted).await;
+        let reopen = match self.rules.for_market(pair).volatility {
+            Some(config) if config.reopen_auction_secs > 0 => MarketState::Auction,
+            _ => MarketState::Continuous,
+        };
+        self.scheduled
+            .insert(pair.to_string(), (Instant::now() + cooldown, reopen));
+        let resume_at = chrono::Duration::from_std(cooldown)
+            .ok()
+            .map(|cooldown| Utc::now() + cooldown);
+        let halt = MarketHalt {
+            pair: pair.to_string(),
+            reason: HaltReason::Volatility,
+            resume_at,
+            ts: Utc::now(),
+        };
+        self.publish_event(pair, Event::MarketHalted(halt)).await;
+    }
+
+    async fn run_scheduled(&mut self) {
+        let now = Instant::now();
+        let mut due: Vec<String> = self
+            .scheduled
+            .iter()
+            .filter(|(_, (at, _))| *at <= now)
+            .map(|(pair, _)| pair.clone())
+            .collect();
+        due.sort();
+        for pair in due {
+            let Some((_, state)) = self.scheduled.remove(&pair) else {
+                continue;
+            };
+            self.transition(&pair, state).await;
+            // A reopening auction ends on its own.
+            if state == MarketState::Auction {
+                if let Some(config) = self.rules.for_market(&pair).volatility {
+                    let ends = now + Duration::from_secs(config.reopen_auction_secs);
+                    self.scheduled.insert(pair, (ends, MarketState::Continuous));
+                }
+            }
+        }
+    }
+
+    /// Operator-requested state change; replaces anything scheduled.
+    async fn apply_state_request(&mut self, request: MarketStateRequest) {
+        self.scheduled.remove(&request.pair);
+        let from = self.transition(&request.pair, request.state).await;
+        if request.state == MarketState::Halted && from != MarketState::Halted {
+            let halt = MarketHalt {
+                pair: request.pair.clone(),
+                reason: HaltReason::Manual,
+                resume_at: None,
+                ts: Utc::now(),
+            };
+            self.publish_event(&request.pair, Event::MarketHalted(halt))
+                .await;
+        }
+    }
+
+    /// Moves a market to `to` and returns the state it left. Entering
+    /// continuous trading first uncrosses whatever was collected.
+    async fn transition(&mut self, pair: &str, to: MarketState) -> MarketState {
+        let (book, groups) = self.market_mut(pair);
+        let from = book.state();
+        if from == to {
+            return from;
+        }
+        let mut events = Vec::new();
+        if to == MarketState::Continuous {
+            let execution = book.uncross();
+            book.set_state(to);
+            events = groups.absorb(book, execution);
+        } else {
+            book.set_state(to);
+        }
+        let depth = book.depth();
+
+        let change = MarketStateChange {
+            pair: pair.to_string(),
+            from,
+            to,
+            ts: Utc::now(),
+        };
+        self.publish_event(pair, Event::MarketStateChanged(change))
+            .await;
+        if !events.is_empty() {
+            self.publish_all(pair, events).await;
+            self.publish_depth(pair, depth).await;
+        }
+        let resumed = matches!(to, MarketState::Continuous | MarketState::Auction);
+        if from == MarketState::Halted && resumed {
+            // Moves from before the halt should not count against the reopening.
+            self.breakers.remove(pair);
+            let resume = MarketResume {
+                pair: pair.to_string(),
+                ts: Utc::now(),
+            };
+            self.publish_event(pair, Event::MarketResumed(resume)).await;
+        }
+        self.publish_indicative(pair).await;
+        from
+    }
+
+    async fn process_cancel(&mut self, cancel: CancelOrder) {
+        let pair = cancel.pair.clone();
+        let reason = self.cancel_rejection(&cancel);
+        let cancelled = match reason {
+            Some(_) => None,
+            None => match (self.books.get_mut(&pair), self.groups.get_mut(&pair)) {
+                (Some(book), Some(groups)) => groups.cancel(book, cancel.order_id),
+                _ => None,
+            },
+        };
+        match cancelled {
+            Some(events) => {
+                self.publish_all(&pair, events).await;
+                self.publish_indicative(&pair).await;
+            }
+            None => {
+                let reason = reason.unwrap_or(CancelRejectReason::UnknownOrder);
+                let rejected = CancelRejected::new(&cancel, reason);
+                self.publish_event(&pair, Event::CancelRejected(rejected))
+                    .await;
+            }
+        }
+    }
+
+    fn arm_countdown(&mut self, countdown: CountdownCancel) {
+        self.countdowns.arm(
+            countdown.user_id,
+            Duration::from_millis(countdown.timeout_ms),
+            Instant::now(),
+        );
+    }
+
+    async fn fire_countdowns(&mut self) {
+        for user_id in self.countdowns.take_expired(Instant::now()) {
+            warn!(%user_id, "dead-man's switch expired, cancelling all orders");
+            let request = CancelAll {
+                request_id: Uuid::new_v4(),
+                user_id,
+                pair: None,
+                side: None,
+            };
+            self.process_cancel_all(request).await;
+        }
+    }
+
+    /// Cancels the user's matching orders market by market, then publishes a
+    /// summary. Halted markets are included, as halts are cancel-only.
+    async fn process_cancel_all(&mut self, request: CancelAll) {
+        let mut markets: Vec<String> = match &request.pair {
+            Some(pair) => vec![pair.clone()],
+            None => self.books.keys().cloned().collect(),
+        };
+        markets.sort();
+        let mut cancelled = Vec::new();
+        for market in markets {
+            let (Some(book), Some(groups)) =
+                (self.books.get_mut(&market), self.groups.get_mut(&market))
+            else {
+                continue;
+            };
+            let updates =
+                book.cancel_user_orders(request.user_id, request.side, OrderReason::MassCancel);
+            // Linked stop legs go down with their limit orders.
+            let events = groups.apply(book, updates);
+            cancelled.extend(events.iter().filter_map(|event| match event {
+                Event::OrderUpdate(update) if update.status == OrderStatus::Cancelled => {
+                    Some(update.order_id)
+                }
+                _ => None,
+            }));
+            self.publish_all(&market, events).await;
+            self.publish_indicative(&market).await;
+        }
+        let summary = CancelAllSummary {
+            request_id: request.request_id,
+            user_id: request.user_id,
+            pair: request.pair,
+            side: request.side,
+            cancelled,
+            ts: Utc::now(),
+        };
+        self.publish_unsequenced(Event::CancelAllDone(summary))
+            .await;
+    }
+
+    /// Why `cancel` cannot be carried out, if it can't. Orders are looked up
+    /// only in the market the request names. Halts are cancel-only, so the
+    /// market's state never stands in the way.
+    fn cancel_rejection(&self, cancel: &CancelOrder) -> Option<CancelRejectReason> {
+        let resting = self
             .books
-            .entry(pair.clone())
-            .or_insert_with(|| OrderBook::new(pair.clone()));
-        let (trades, depth) = {
-            let order = Order::from_new(new_order);
-            let (trades, _last_fill) = book.upsert(order);
-            let depth = book.depth();
-            (trades, depth)
+            .get(&cancel.pair)
+            .and_then(|book| book.get(cancel.order_id))
+            .map(|order| order.user_id);
+        let stop = self
+            .groups
+            .get(&cancel.pair)
+            .and_then(|groups| groups.stop(cancel.order_id))
+            .map(|order| order.user_id);
+        if let Some(owner) = resting.or(stop) {
+            return (owner != cancel.user_id).then_some(CancelRejectReason::NotOwner);
+        }
+        match self.closed.get(cancel.order_id) {
+            None => Some(CancelRejectReason::UnknownOrder),
+            Some((owner, _)) if owner != cancel.user_id => Some(CancelRejectReason::NotOwner),
+            Some((_, OrderStatus::Filled)) => Some(CancelRejectReason::AlreadyFilled),
+            Some(_) => Some(CancelRejectReason::AlreadyClosed),
+        }
+    }
+
+    async fn publish_update(&mut self, market: &str, update: OrderUpdate) {
+        if update.status.is_terminal() {
+            self.closed
+                .insert(update.order_id, update.user_id, update.status);
+        }
+        self.publish_event(market, Event::OrderUpdate(update)).await;
+    }
+
+    async fn publish_depth(&mut self, market: &str, depth: DepthSnapshot) {
+        self.publish_event(
+            market,
+            Event::DepthSnapshot {
+                pair: depth.pair.clone(),
+                bids: depth
+                    .bids
+                    .iter()
+                    .map(|lvl| (lvl.price, lvl.quantity))
+                    .collect(),
+                asks: depth
+                    .asks
+                    .iter()
+                    .map(|lvl| (lvl.price, lvl.quantity))
+                    .collect(),
+                ts: depth.timestamp,
+            },
+        )
+        .await;
+    }
+
+    /// Publishes where an auction would uncross; no-op outside auctions.
+    async fn publish_indicative(&mut self, market: &str) {
+        let Some(book) = self.books.get(market) else {
+            return;
+        };
+        if book.state() != MarketState::Auction {
+            return;
+        }
+        let indication = book.indicative();
+        let indicative = AuctionIndicative {
+            pair: market.to_string(),
+            price: indication.map(|(price, _)| price),
+            volume: indication.map_or(Decimal::ZERO, |(_, volume)| volume),
+            ts: Utc::now(),
         };
+        self.publish_event(market, Event::AuctionIndicative(indicative))
+            .await;
+    }
 
-        for trade in trades {
-            self.publish_event(Event::TradeExecuted(trade)).await?;
-        }
-
-        self.publish_event(Event::DepthSnapshot {
-            pair: depth.pair.clone(),
-            bids: depth
-                .bids
-                .iter()
-                .map(|lvl| (lvl.price, lvl.quantity))
-                .collect(),
-            asks: depth
-                .asks
-                .iter()
-                .map(|lvl| (lvl.price, lvl.quantity))
-                .collect(),
-            ts: depth.timestamp,
+    /// Publishes engine output in order, tracking orders it closes. One
+    /// event failing does not hold back the rest.
+    async fn publish_all(&mut self, market: &str, events: Vec<Event>) {
+        for event in events {
+            match event {
+                Event::OrderUpdate(update) => self.publish_update(market, update).await,
+                event => self.publish_event(market, event).await,
+            }
+        }
+    }
+
+    /// Stamps the event with the market's next sequence number and writes it
+    /// to the market's log before fanning it out. The book has already
+    /// changed by now, so failures are retried and then logged rather than
+    /// handed back to the caller.
+    async fn publish_event(&mut self, market: &str, event: Event) {
+        let sequence = match retry("read last sequence", || self.next_sequence(market)).await {
+            Ok(sequence) => sequence,
+            Err(err) => {
+                error!(%market, ?event, "dropping event, cannot number it: {err}");
+                return;
+            }
+        };
+        let envelope = Envelope::sequenced(ENGINE_SOURCE, market, sequence, event);
+        let payload = match to_json(&envelope) {
+            Ok(payload) => payload,
+            Err(err) => {
+                error!(%market, sequence, "failed to encode event: {err}");
+                return;
+            }
+        };
+        // The number is spent even if the log write fails, so a live consumer
+        // never sees it twice.
+        self.sequences.insert(market.to_string(), sequence);
+        // The market log lets consumers refetch ranges they missed, the global
+        // stream is the durable record for db_filler and pub/sub is live fan-out.
+        let logged = retry("append to market log", || {
+            self.redis.append_market_event(market, sequence, &payload)
         })
-        .await?;
+        .await;
+        if let Err(err) = logged {
+            error!(%market, sequence, "event missing from market log: {err}");
+        }
+        self.broadcast(&payload).await;
+    }
 
-        Ok(())
+    /// For events that span markets and so have no place in a market's log.
+    async fn publish_unsequenced(&self, event: Event) {
+        match to_json(&Envelope::new(ENGINE_SOURCE, event)) {
+            Ok(payload) => self.broadcast(&payload).await,
+            Err(err) => error!("failed to encode event: {err}"),
+        }
     }
 
-    async fn process_cancel(&mut self, order_id: uuid::Uuid) -> Result<(), CexError> {
-        let mut removed = false;
-        for book in self.books.values_mut() {
-            if book.cancel(order_id) {
-                removed = true;
-                break;
-            }
+    /// Appends to the event stream and publishes independently, so live
+    /// subscribers still get an event the stream could not take, and the
+    /// reverse.
+    async fn broadcast(&self, payload: &str) {
+        let appended = retry("append to event stream", || {
+            self.redis
+                .append_stream(STREAM_EVENTS, payload, STREAM_EVENTS_MAX_LEN)
+        })
+        .await;
+        if let Err(err) = appended {
+            error!("event missing from {STREAM_EVENTS}: {err}");
         }
-        if removed {
-            self.publish_event(Event::OrderCancel { order_id }).await?;
+        let published = retry("publish event", || {
+            self.redis.publish(CHANNEL_EVENTS, payload)
+        })
+        .await;
+        if let Err(err) = published {
+            error!("event not published on {CHANNEL_EVENTS}: {err}");
         }
-        Ok(())
     }
 
-    async fn publish_event(&self, event: Event) -> Result<(), CexError> {
-        let envelope = Envelope::new(ENGINE_SOURCE, event);
-        let payload = to_json(&envelope)?;
-        self.redis.publish(CHANNEL_EVENTS, &payload).await
+    /// Resumes from the market's log after a restart, so numbering never
+    /// repeats or goes backwards.
+    async fn next_sequence(&self, market: &str) -> Result<u64, CexError> {
+        let last = match self.sequences.get(market) {
+            Some(&last) => last,
+            None => self.redis.last_market_sequence(market).await?.unwrap_or(0),
+        };
+        Ok(last + 1)
+    }
+}
+
+/// Whether an error says the message itself is bad, as opposed to the engine
+/// failing while acting on it.
+fn is_bad_message(err: &CexError) -> bool {
+    matches!(
+        err,
+        CexError::Validation(_) | CexError::Serde(_) | CexError::Codec(_)
+    )
+}
+
+/// Runs a Redis operation up to [`PUBLISH_ATTEMPTS`] times with a short
+/// backoff, returning the last error if every try fails.
+async fn retry<T, F, Fut>(what: &str, mut op: F) -> Result<T, CexError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, CexError>>,
+{
+    let mut backoff = Backoff::new(PUBLISH_RETRY_INITIAL, PUBLISH_RETRY_MAX);
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Ok(value) => return Ok(value),
+            Err(err) if attempt < PUBLISH_ATTEMPTS => {
+                warn!(attempt, "{what} failed, retrying: {err}");
+                tokio::time::sleep(backoff.next_delay()).await;
+                attempt += 1;
+            }
+            Err(err) => return Err(err),
+        }
     }
 }
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use shared::CexError;
+
+use crate::orderbook::book::DEFAULT_MARKET_PROTECTION_BPS;
+use crate::orderbook::matching::{Fifo, FifoTopOrder, MatchingStrategy, ProRata};
+
+/// Default width of the limit price band.
+pub const DEFAULT_PRICE_BAND_BPS: u32 = 1_000;
+
+/// Price the band is centred on.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BandReference {
+    #[default]
+    LastTrade,
+    /// Midpoint of the best bid and ask.
+    Mid,
+}
+
+/// Limit buys more than `bps` above the reference price, and sells more
+/// than `bps` below it, are rejected. No reference price yet means no band.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+pub struct PriceBand {
+    pub bps: u32,
+    #[serde(default)]
+    pub reference: BandReference,
+}
+
+/// Default length of the call auction a market reopens with after a
+/// circuit breaker halt.
+pub const DEFAULT_REOPEN_AUCTION_SECS: u64 = 10;
+
+/// Halts the market for `cooldown_secs` once trades within `window_secs` of
+/// each other span more than `max_move_bps`, then reopens it through a call
+/// auction of `reopen_auction_secs`, or straight away when that is 0.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+pub struct VolatilityBreaker {
+    pub max_move_bps: u32,
+    pub window_secs: u64,
+    pub cooldown_secs: u64,
+    #[serde(default = "default_reopen_auction_secs")]
+    pub reopen_auction_secs: u64,
+}
+
+fn default_reopen_auction_secs() -> u64 {
+    DEFAULT_REOPEN_AUCTION_SECS
+}
+
+/// How a level's resting orders share an incoming order, see
+/// [`MatchingStrategy`].
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MatchingAlgorithm {
+    /// Price-time priority.
+    #[default]
+    Fifo,
+    ProRata {
+        #[serde(default)]
+        lot_size: Option<Decimal>,
+        #[serde(default)]
+        min_allocation: Decimal,
+    },
+    /// The order that set a new best price fills first, up to
+    /// `top_order_max`, then the rest of the level in time priority.
+    FifoTopOrder { top_order_max: Decimal },
+    /// The order that set a new best price fills first, then the level is
+    /// shared pro-rata and leftovers go in time priority.
+    ProRataTopOrder {
+        #[serde(default)]
+        lot_size: Option<Decimal>,
+        #[serde(default)]
+        min_allocation: Decimal,
+    },
+}
+
+impl MatchingAlgorithm {
+    pub fn strategy(&self) -> Box<dyn MatchingStrategy> {
+        match *self {
+            MatchingAlgorithm::Fifo => Box::new(Fifo),
+            MatchingAlgorithm::ProRata {
+                lot_size,
+                min_allocation,
+            } => Box::new(ProRata {
+                lot_size,
+                min_allocation,
+                top_order: false,
+            }),
+            MatchingAlgorithm::FifoTopOrder { top_order_max } => {
+                Box::new(FifoTopOrder { top_order_max })
+            }
+            MatchingAlgorithm::ProRataTopOrder {
+                lot_size,
+                min_allocation,
+            } => Box::new(ProRata {
+                lot_size,
+                min_allocation,
+                top_order: true,
+            }),
+        }
+    }
+}
+
+/// Trading limits of one market. Omitted fields keep their defaults and an
+/// explicit `null` turns the limit off.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct MarketRules {
+    pub price_band: Option<PriceBand>,
+    /// Default band for market orders, see `max_slippage_bps`.
+    pub market_protection_bps: Option<u32>,
+    pub volatility: Option<VolatilityBreaker>,
+    pub matching: MatchingAlgorithm,
+}
+
+impl Default for MarketRules {
+    fn default() -> Self {
+        Self {
+            price_band: Some(PriceBand {
+                bps: DEFAULT_PRICE_BAND_BPS,
+                reference: BandReference::LastTrade,
+            }),
+            market_protection_bps: Some(DEFAULT_MARKET_PROTECTION_BPS),
+            volatility: None,
+            matching: MatchingAlgorithm::Fifo,
+        }
+    }
+}
+
+/// Rules for every market, as read from `MARKET_RULES`:
+/// `{"default": {...}, "markets": {"SOLUSDC": {...}}}`.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct MarketRulesConfig {
+    pub default: MarketRules,
+    pub markets: HashMap<String, MarketRules>,
+}
+
+impl MarketRulesConfig {
+    pub fn from_json(json: &str) -> Result<Self, CexError> {
+        serde_json::from_str(json)
+            .map_err(|e| CexError::Validation(format!("invalid MARKET_RULES: {e}")))
+    }
+
+    pub fn for_market(&self, pair: &str) -> &MarketRules {
+        self.markets.get(pair).unwrap_or(&self.default)
+    }
+}
@@ -0,0 +1,139 @@
+use engine::groups::OrderGroups;
+use engine::orderbook::OrderBook;
+use rust_decimal::Decimal;
+use shared::types::{
+    new_order, MarketState, NewOrder, Order, OrderGroup, OrderReason, OrderSide, OrderStatus,
+    OrderType, StopLeg,
+};
+use shared::Event;
+use uuid::Uuid;
+
+fn dec(s: &str) -> Decimal {
+    Decimal::from_str_exact(s).unwrap()
+}
+
+fn new(side: OrderSide, order_type: OrderType, price: &str, qty: &str) -> NewOrder {
+    new_order(
+        Uuid::new_v4(),
+        "SOLUSDC".to_string(),
+        side,
+        order_type,
+        dec(price),
+        dec(qty),
+    )
+}
+
+fn limit(side: OrderSide, price: &str, qty: &str) -> Order {
+    Order::from_new(new(side, OrderType::Limit, price, qty))
+}
+
+fn auction_book() -> OrderBook {
+    let mut book = OrderBook::new("SOLUSDC");
+    book.set_state(MarketState::Auction);
+    book
+}
+
+#[test]
+fn auction_collects_crossing_orders_without_matching() {
+    let mut book = auction_book();
+    book.execute(limit(OrderSide::Sell, "99", "2"));
+    let execution = book.execute(limit(OrderSide::Buy, "101", "1"));
+    assert!(execution.trades.is_empty());
+    assert_eq!(execution.updates.len(), 1);
+    assert_eq!(execution.updates[0].status, OrderStatus::New);
+
+    let depth = book.depth();
+    assert_eq!(depth.bids[0].price, dec("101"));
+    assert_eq!(depth.asks[0].price, dec("99"));
+    assert_eq!(book.indicative(), Some((dec("99"), dec("1"))));
+}
+
+#[test]
+fn indicative_price_maximises_executed_volume() {
+    let mut book = auction_book();
+    assert_eq!(book.indicative(), None);
+    for (price, qty) in [("102", "3"), ("101", "2"), ("100", "5")] {
+        book.execute(limit(OrderSide::Buy, price, qty));
+    }
+    for (price, qty) in [("99", "2"), ("100", "2"), ("101", "4")] {
+        book.execute(limit(OrderSide::Sell, price, qty));
+    }
+    // At 100: demand 10, supply 4. At 101: demand 5, supply 8, so 5.
+    assert_eq!(book.indicative(), Some((dec("101"), dec("5"))));
+}
+
+#[test]
+fn uncross_trades_everything_at_one_price_in_priority_order() {
+    let mut book = auction_book();
+    let early = limit(OrderSide::Buy, "101", "2");
+    let late = limit(OrderSide::Buy, "101", "2");
+    let (early_id, late_id) = (early.order_id, late.order_id);
+    book.execute(early);
+    book.execute(late);
+    book.execute(limit(OrderSide::Sell, "99", "1"));
+    book.execute(limit(OrderSide::Sell, "100", "2"));
+
+    let execution = book.uncross();
+    assert_eq!(execution.trades.len(), 3);
+    assert!(execution.trades.iter().all(|t| t.price == dec("100")));
+    assert_eq!(
+        execution
+            .trades
+            .iter()
+            .fold(Decimal::ZERO, |acc, t| acc + t.quantity),
+        dec("3")
+    );
+    // 100 and 101 both clear 3; with no last trade the lower price wins.
+    // The earlier bid fills first.
+    assert_eq!(book.get(late_id).unwrap().filled, dec("1"));
+    assert!(book.get(early_id).is_none());
+    assert_eq!(book.last_price(), Some(dec("100")));
+    assert!(book.depth().asks.is_empty());
+    assert_eq!(book.indicative(), None);
+}
+
+#[test]
+fn states_gate_what_the_book_accepts() {
+    let mut book = auction_book();
+    let market = Order::from_new(new(OrderSide::Buy, OrderType::Market, "0", "1"));
+    let execution = book.execute(market);
+    assert_eq!(execution.updates[0].reason, Some(OrderReason::TradingState));
+
+    for (state, reason) in [
+        (MarketState::Closed, OrderReason::TradingState),
+        (MarketState::Halted, OrderReason::MarketHalted),
+    ] {
+        book.set_state(state);
+        let execution = book.execute(limit(OrderSide::Buy, "100", "1"));
+        assert_eq!(execution.updates[0].status, OrderStatus::Rejected);
+        assert_eq!(execution.updates[0].reason, Some(reason));
+    }
+}
+
+#[test]
+fn uncross_fills_set_off_order_groups() {
+    let mut book = OrderBook::new("SOLUSDC");
+    let mut groups = OrderGroups::new();
+    book.set_state(MarketState::PreOpen);
+
+    let stop = StopLeg {
+        order_id: Uuid::new_v4(),
+        trigger_price: dec("90"),
+        limit_price: None,
+    };
+    let mut oco = new(OrderSide::Sell, OrderType::Limit, "100", "1");
+    oco.group = Some(OrderGroup::Oco { stop: stop.clone() });
+    groups.submit(&mut book, oco);
+    groups.submit(&mut book, new(OrderSide::Buy, OrderType::Limit, "100", "1"));
+    assert_eq!(groups.pending_stops(), 1);
+
+    let execution = book.uncross();
+    book.set_state(MarketState::Continuous);
+    let events = groups.absorb(&mut book, execution);
+    assert_eq!(groups.pending_stops(), 0);
+    assert!(events.iter().any(|event| matches!(
+        event,
+        Event::OrderUpdate(update)
+            if update.order_id == stop.order_id && update.reason == Some(OrderReason::LinkedOrder)
+    )));
+}
@@ -0,0 +1,20 @@
+use engine::closed::ClosedOrders;
+use shared::types::OrderStatus;
+use uuid::Uuid;
+
+#[test]
+fn remembers_only_the_most_recent_orders() {
+    let mut closed = ClosedOrders::new(2);
+    let owner = Uuid::new_v4();
+    let ids: Vec<_> = (0..3).map(|_| Uuid::new_v4()).collect();
+    closed.insert(ids[0], owner, OrderStatus::Filled);
+    closed.insert(ids[1], owner, OrderStatus::Cancelled);
+    // Re-recording an order does not count twice against the capacity.
+    closed.insert(ids[1], owner, OrderStatus::Cancelled);
+    assert_eq!(closed.get(ids[0]), Some((owner, OrderStatus::Filled)));
+
+    closed.insert(ids[2], owner, OrderStatus::Expired);
+    assert_eq!(closed.get(ids[0]), None);
+    assert_eq!(closed.get(ids[1]), Some((owner, OrderStatus::Cancelled)));
+    assert_eq!(closed.get(ids[2]), Some((owner, OrderStatus::Expired)));
+}
@@ -0,0 +1,45 @@
+use std::time::{Duration, Instant};
+
+use engine::countdown::Countdowns;
+use uuid::Uuid;
+
+#[test]
+fn refreshing_pushes_the_deadline_back() {
+    let mut countdowns = Countdowns::new();
+    let user = Uuid::new_v4();
+    let start = Instant::now();
+    countdowns.arm(user, Duration::from_secs(10), start);
+    countdowns.arm(
+        user,
+        Duration::from_secs(10),
+        start + Duration::from_secs(8),
+    );
+
+    assert!(countdowns
+        .take_expired(start + Duration::from_secs(12))
+        .is_empty());
+    assert_eq!(
+        countdowns.take_expired(start + Duration::from_secs(18)),
+        vec![user]
+    );
+    // Fires once, then stays disarmed.
+    assert!(!countdowns.is_armed(user));
+    assert!(countdowns
+        .take_expired(start + Duration::from_secs(60))
+        .is_empty());
+}
+
+#[test]
+fn zero_timeout_disarms() {
+    let mut countdowns = Countdowns::new();
+    let (user, other) = (Uuid::new_v4(), Uuid::new_v4());
+    let start = Instant::now();
+    countdowns.arm(user, Duration::from_secs(1), start);
+    countdowns.arm(other, Duration::from_secs(1), start);
+    countdowns.arm(user, Duration::ZERO, start);
+
+    assert_eq!(
+        countdowns.take_expired(start + Duration::from_secs(2)),
+        vec![other]
+    );
+}
@@ -0,0 +1,41 @@
+use std::time::Duration;
+
+use engine::latency::LatencyRecorder;
+
+#[test]
+fn summarises_each_kind_separately() {
+    let mut recorder = LatencyRecorder::new();
+    assert!(recorder.summary().is_empty());
+
+    for micros in 1..=100 {
+        recorder.record("order_new", Duration::from_micros(micros));
+    }
+    recorder.record("cancel", Duration::from_millis(3));
+
+    let summary = recorder.summary();
+    let kinds: Vec<&str> = summary.iter().map(|l| l.kind.as_str()).collect();
+    assert_eq!(kinds, ["cancel", "order_new"]);
+
+    let cancel = &summary[0];
+    assert_eq!(cancel.count, 1);
+    // Three significant digits.
+    assert!(cancel.max_us.abs_diff(3_000) <= 3);
+
+    let new = &summary[1];
+    assert_eq!(new.count, 100);
+    assert_eq!(new.p50_us, 50);
+    assert_eq!(new.p99_us, 99);
+    assert_eq!(new.max_us, 100);
+    assert!((new.mean_us - 50.5).abs() < 0.5);
+}
+
+#[test]
+fn clamps_out_of_range_durations() {
+    let mut recorder = LatencyRecorder::new();
+    recorder.record("order_new", Duration::ZERO);
+    recorder.record("order_new", Duration::from_secs(3_600));
+    let summary = recorder.summary();
+    assert_eq!(summary[0].count, 2);
+    assert_eq!(summary[0].p50_us, 1);
+    assert!(summary[0].max_us >= 60_000_000);
+}
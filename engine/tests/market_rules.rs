@@ -0,0 +1,134 @@
+use std::time::{Duration, Instant};
+
+use engine::breaker::VolatilityMonitor;
+use engine::orderbook::OrderBook;
+use engine::rules::{
+    BandReference, MarketRules, MarketRulesConfig, PriceBand, VolatilityBreaker,
+    DEFAULT_PRICE_BAND_BPS, DEFAULT_REOPEN_AUCTION_SECS,
+};
+use rust_decimal::Decimal;
+use shared::types::{new_order, Order, OrderReason, OrderSide, OrderStatus, OrderType};
+use uuid::Uuid;
+
+fn dec(s: &str) -> Decimal {
+    Decimal::from_str_exact(s).unwrap()
+}
+
+fn limit(side: OrderSide, price: &str, qty: &str) -> Order {
+    Order::from_new(new_order(
+        Uuid::new_v4(),
+        "SOLUSDC".to_string(),
+        side,
+        OrderType::Limit,
+        dec(price),
+        dec(qty),
+    ))
+}
+
+fn banded(reference: BandReference) -> OrderBook {
+    let rules = MarketRules {
+        price_band: Some(PriceBand {
+            bps: 1_000,
+            reference,
+        }),
+        ..MarketRules::default()
+    };
+    OrderBook::with_rules("SOLUSDC", &rules)
+}
+
+fn status(book: &mut OrderBook, order: Order) -> (OrderStatus, Option<OrderReason>) {
+    let execution = book.execute(order);
+    let verdict = &execution.updates[0];
+    (verdict.status, verdict.reason)
+}
+
+#[test]
+fn limit_orders_outside_the_band_around_the_last_trade_are_rejected() {
+    let mut book = banded(BandReference::LastTrade);
+    // No trade yet, so no band.
+    book.execute(limit(OrderSide::Sell, "100", "1"));
+    book.execute(limit(OrderSide::Buy, "100", "1"));
+    assert_eq!(book.last_price(), Some(dec("100")));
+
+    assert_eq!(
+        status(&mut book, limit(OrderSide::Buy, "111", "1")),
+        (OrderStatus::Rejected, Some(OrderReason::PriceBand))
+    );
+    assert_eq!(
+        status(&mut book, limit(OrderSide::Sell, "89", "1")),
+        (OrderStatus::Rejected, Some(OrderReason::PriceBand))
+    );
+    // Passive prices far from the market only rest.
+    assert_eq!(
+        status(&mut book, limit(OrderSide::Buy, "50", "1")),
+        (OrderStatus::New, None)
+    );
+    assert_eq!(
+        status(&mut book, limit(OrderSide::Sell, "110", "1")),
+        (OrderStatus::New, None)
+    );
+}
+
+#[test]
+fn mid_reference_needs_both_sides() {
+    let mut book = banded(BandReference::Mid);
+    book.execute(limit(OrderSide::Buy, "98", "1"));
+    // Only bids, so there is no mid to band around.
+    assert_eq!(
+        status(&mut book, limit(OrderSide::Sell, "10", "1")),
+        (OrderStatus::New, None)
+    );
+
+    book.execute(limit(OrderSide::Buy, "98", "1"));
+    book.execute(limit(OrderSide::Sell, "102", "1"));
+    assert_eq!(
+        status(&mut book, limit(OrderSide::Buy, "111", "1")),
+        (OrderStatus::Rejected, Some(OrderReason::PriceBand))
+    );
+}
+
+#[test]
+fn rules_config_overrides_per_market_and_null_disables() {
+    let config = MarketRulesConfig::from_json(
+        r#"{
+            "default": {"volatility": {"max_move_bps": 500, "window_secs": 10, "cooldown_secs": 60}},
+            "markets": {"BTCUSDC": {"price_band": null, "market_protection_bps": 100}}
+        }"#,
+    )
+    .unwrap();
+
+    let default = config.for_market("SOLUSDC");
+    assert_eq!(default.price_band.unwrap().bps, DEFAULT_PRICE_BAND_BPS);
+    assert_eq!(default.volatility.unwrap().cooldown_secs, 60);
+    assert_eq!(
+        default.volatility.unwrap().reopen_auction_secs,
+        DEFAULT_REOPEN_AUCTION_SECS
+    );
+
+    let btc = config.for_market("BTCUSDC");
+    assert!(btc.price_band.is_none());
+    assert!(btc.volatility.is_none());
+    assert_eq!(btc.market_protection_bps, Some(100));
+
+    assert!(MarketRulesConfig::from_json("{\"default\": 1}").is_err());
+}
+
+#[test]
+fn breaker_trips_on_a_fast_move_only() {
+    let mut monitor = VolatilityMonitor::new(VolatilityBreaker {
+        max_move_bps: 500,
+        window_secs: 10,
+        cooldown_secs: 30,
+        reopen_auction_secs: 0,
+    });
+    let start = Instant::now();
+    assert!(!monitor.record(start, dec("100")));
+    assert!(!monitor.record(start + Duration::from_secs(5), dec("104")));
+    // 100 → 106 within 10s is a 6% move.
+    assert!(monitor.record(start + Duration::from_secs(9), dec("106")));
+
+    // The same move spread over longer than the window does not trip.
+    assert!(!monitor.record(start + Duration::from_secs(20), dec("100")));
+    assert!(!monitor.record(start + Duration::from_secs(31), dec("106")));
+    assert_eq!(monitor.cooldown(), Duration::from_secs(30));
+}
@@ -0,0 +1,168 @@
+use engine::orderbook::OrderBook;
+use engine::rules::{MarketRulesConfig, MatchingAlgorithm};
+use rust_decimal::Decimal;
+use shared::types::{new_order, Order, OrderId, OrderSide, OrderType};
+use uuid::Uuid;
+
+fn dec(s: &str) -> Decimal {
+    Decimal::from_str_exact(s).unwrap()
+}
+
+fn limit(side: OrderSide, price: &str, qty: &str) -> Order {
+    Order::from_new(new_order(
+        Uuid::new_v4(),
+        "SOLUSDC".to_string(),
+        side,
+        OrderType::Limit,
+        dec(price),
+        dec(qty),
+    ))
+}
+
+fn configured(matching: &str) -> OrderBook {
+    let json = format!(r#"{{"default": {{"matching": {matching}}}}}"#);
+    let config = MarketRulesConfig::from_json(&json).unwrap();
+    OrderBook::with_rules("SOLUSDC", config.for_market("SOLUSDC"))
+}
+
+/// Rests asks at 100 of the given sizes and returns their ids.
+fn asks(book: &mut OrderBook, sizes: &[&str]) -> Vec<OrderId> {
+    sizes
+        .iter()
+        .map(|qty| {
+            let order = limit(OrderSide::Sell, "100", qty);
+            let id = order.order_id;
+            book.execute(order);
+            id
+        })
+        .collect()
+}
+
+fn filled(book: &OrderBook, ids: &[OrderId], sizes: &[&str]) -> Vec<Decimal> {
+    ids.iter()
+        .zip(sizes)
+        .map(|(id, size)| match book.get(*id) {
+            Some(order) => order.filled,
+            None => dec(size),
+        })
+        .collect()
+}
+
+#[test]
+fn fifo_is_the_default() {
+    let config = MarketRulesConfig::from_json("{}").unwrap();
+    assert_eq!(config.default.matching, MatchingAlgorithm::Fifo);
+
+    let mut book = configured(r#"{"type": "fifo"}"#);
+    let sizes = ["3", "3"];
+    let ids = asks(&mut book, &sizes);
+    book.execute(limit(OrderSide::Buy, "100", "4"));
+    assert_eq!(filled(&book, &ids, &sizes), [dec("3"), dec("1")]);
+}
+
+#[test]
+fn pro_rata_shares_in_proportion_to_resting_size() {
+    let mut book = configured(r#"{"type": "pro_rata"}"#);
+    let sizes = ["2", "6"];
+    let ids = asks(&mut book, &sizes);
+    let execution = book.execute(limit(OrderSide::Buy, "100", "4"));
+    assert_eq!(execution.trades.len(), 2);
+    assert_eq!(filled(&book, &ids, &sizes), [dec("1"), dec("3")]);
+}
+
+#[test]
+fn pro_rata_rounds_to_lots_and_hands_leftovers_out_in_time_order() {
+    let mut book = configured(r#"{"type": "pro_rata", "lot_size": "1"}"#);
+    let sizes = ["5", "5", "5"];
+    let ids = asks(&mut book, &sizes);
+    book.execute(limit(OrderSide::Buy, "100", "4"));
+    // 4/3 each rounds down to 1; the lot left over goes to the oldest order.
+    assert_eq!(filled(&book, &ids, &sizes), [dec("2"), dec("1"), dec("1")]);
+}
+
+#[test]
+fn pro_rata_drops_shares_below_the_minimum() {
+    let mut book = configured(r#"{"type": "pro_rata", "lot_size": "1", "min_allocation": "1"}"#);
+    let sizes = ["1", "10"];
+    let ids = asks(&mut book, &sizes);
+    book.execute(limit(OrderSide::Buy, "100", "5"));
+    // The small order's share of 5/11 rounds to nothing, and the leftover
+    // goes to it first by time priority.
+    assert_eq!(filled(&book, &ids, &sizes), [dec("1"), dec("4")]);
+
+    let mut book = configured(r#"{"type": "pro_rata", "lot_size": "1", "min_allocation": "2"}"#);
+    let sizes = ["10", "3"];
+    let ids = asks(&mut book, &sizes);
+    book.execute(limit(OrderSide::Buy, "100", "6"));
+    // 6 * 3/13 rounds to 1, below the minimum of 2.
+    assert_eq!(filled(&book, &ids, &sizes), [dec("6"), dec("0")]);
+}
+
+#[test]
+fn pro_rata_leftovers_skip_orders_they_would_leave_below_the_minimum() {
+    let mut book = configured(r#"{"type": "pro_rata", "lot_size": "1", "min_allocation": "2"}"#);
+    let sizes = ["1", "10"];
+    let ids = asks(&mut book, &sizes);
+    book.execute(limit(OrderSide::Buy, "100", "5"));
+    // 5/11 of the small order rounds to nothing, and the leftover lot would
+    // give it 1, below the minimum, so it goes to the large order instead.
+    assert_eq!(filled(&book, &ids, &sizes), [dec("0"), dec("5")]);
+}
+
+#[test]
+fn fifo_top_order_caps_the_top_order_then_fills_front_to_back() {
+    let sizes = ["5", "2", "2"];
+    let mut fifo = configured(r#"{"type": "fifo"}"#);
+    let ids = asks(&mut fifo, &sizes);
+    fifo.execute(limit(OrderSide::Buy, "100", "6"));
+    assert_eq!(filled(&fifo, &ids, &sizes), [dec("5"), dec("1"), dec("0")]);
+
+    let mut book = configured(r#"{"type": "fifo_top_order", "top_order_max": "2"}"#);
+    let ids = asks(&mut book, &sizes);
+    book.execute(limit(OrderSide::Buy, "100", "6"));
+    assert_eq!(filled(&book, &ids, &sizes), [dec("2"), dec("2"), dec("2")]);
+
+    // Past the rest of the level, the top order's remainder fills last.
+    book.execute(limit(OrderSide::Buy, "100", "2"));
+    assert_eq!(book.get(ids[0]).unwrap().filled, dec("4"));
+}
+
+#[test]
+fn pro_rata_top_order_fills_first_then_the_level_is_shared() {
+    let mut book = configured(r#"{"type": "pro_rata_top_order", "lot_size": "1"}"#);
+    let sizes = ["2", "2", "6"];
+    let ids = asks(&mut book, &sizes);
+    book.execute(limit(OrderSide::Buy, "100", "6"));
+    assert_eq!(filled(&book, &ids, &sizes), [dec("2"), dec("1"), dec("3")]);
+}
+
+#[test]
+fn joining_a_level_behind_the_best_does_not_make_a_top_order() {
+    let mut book = configured(r#"{"type": "pro_rata_top_order"}"#);
+    asks(&mut book, &["1"]);
+    // Opens the 101 level, but 100 is still the best ask.
+    let behind = limit(OrderSide::Sell, "101", "2");
+    let behind_id = behind.order_id;
+    book.execute(behind);
+    let other = limit(OrderSide::Sell, "101", "6");
+    let other_id = other.order_id;
+    book.execute(other);
+
+    book.execute(limit(OrderSide::Buy, "101", "5"));
+    assert_eq!(book.get(behind_id).unwrap().filled, dec("1"));
+    assert_eq!(book.get(other_id).unwrap().filled, dec("3"));
+}
+
+#[test]
+fn pro_rata_without_lots_rounds_shares_so_totals_stay_exact() {
+    let mut book = configured(r#"{"type": "pro_rata"}"#);
+    let sizes = ["1", "1", "1"];
+    let ids = asks(&mut book, &sizes);
+    book.execute(limit(OrderSide::Buy, "100", "1"));
+    assert_eq!(
+        filled(&book, &ids, &sizes),
+        [dec("0.33333334"), dec("0.33333333"), dec("0.33333333")]
+    );
+    assert_eq!(book.level_quantity(OrderSide::Sell, dec("100")), dec("2"));
+    book.validate().unwrap();
+}
@@ -0,0 +1,363 @@
+use engine::groups::OrderGroups;
+use engine::orderbook::OrderBook;
+use rust_decimal::Decimal;
+use shared::types::{
+    new_order, NewOrder, OrderGroup, OrderId, OrderReason, OrderSide, OrderStatus, OrderType,
+    OrderUpdate, StopLeg, TakeProfitLeg,
+};
+use shared::Event;
+use uuid::Uuid;
+
+const PAIR: &str = "SOLUSDC";
+
+fn dec(s: &str) -> Decimal {
+    Decimal::from_str_exact(s).unwrap()
+}
+
+fn limit(user: Uuid, side: OrderSide, price: &str, qty: &str) -> NewOrder {
+    new_order(
+        user,
+        PAIR.to_string(),
+        side,
+        OrderType::Limit,
+        dec(price),
+        dec(qty),
+    )
+}
+
+fn market(user: Uuid, side: OrderSide, qty: &str) -> NewOrder {
+    new_order(
+        user,
+        PAIR.to_string(),
+        side,
+        OrderType::Market,
+        Decimal::ZERO,
+        dec(qty),
+    )
+}
+
+fn stop(trigger: &str) -> StopLeg {
+    StopLeg {
+        order_id: Uuid::new_v4(),
+        trigger_price: dec(trigger),
+        limit_price: None,
+    }
+}
+
+fn updates(events: &[Event]) -> Vec<&OrderUpdate> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            Event::OrderUpdate(update) => Some(update),
+            _ => None,
+        })
+        .collect()
+}
+
+fn last_status(events: &[Event], order_id: OrderId) -> Option<(OrderStatus, Option<OrderReason>)> {
+    updates(events)
+        .into_iter()
+        .rev()
+        .find(|update| update.order_id == order_id)
+        .map(|update| (update.status, update.reason))
+}
+
+fn trades(events: &[Event]) -> usize {
+    events
+        .iter()
+        .filter(|event| matches!(event, Event::TradeExecuted(_)))
+        .count()
+}
+
+#[test]
+fn oco_partial_fill_shrinks_the_stop_and_a_full_fill_cancels_it() {
+    let mut book = OrderBook::new(PAIR);
+    let mut groups = OrderGroups::new();
+    let trader = Uuid::new_v4();
+
+    let leg = stop("90");
+    let mut oco = limit(trader, OrderSide::Sell, "110", "2");
+    oco.group = Some(OrderGroup::Oco { stop: leg.clone() });
+    let events = groups.submit(&mut book, oco.clone());
+    // The stop leg is announced like any other order.
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, Event::OrderNew(new) if new.order_id == leg.order_id)));
+    assert_eq!(groups.stop(leg.order_id).unwrap().side, OrderSide::Sell);
+
+    let events = groups.submit(&mut book, limit(Uuid::new_v4(), OrderSide::Buy, "110", "1"));
+    assert_eq!(
+        last_status(&events, oco.order_id),
+        Some((OrderStatus::PartiallyFilled, None))
+    );
+    let resized = updates(&events)
+        .into_iter()
+        .find(|update| update.order_id == leg.order_id)
+        .unwrap();
+    assert_eq!(resized.status, OrderStatus::New);
+    assert_eq!(resized.quantity, dec("1"));
+    assert_eq!(groups.stop(leg.order_id).unwrap().quantity, dec("1"));
+
+    let events = groups.submit(&mut book, limit(Uuid::new_v4(), OrderSide::Buy, "110", "1"));
+    assert_eq!(
+        last_status(&events, oco.order_id),
+        Some((OrderStatus::Filled, None))
+    );
+    assert_eq!(
+        last_status(&events, leg.order_id),
+        Some((OrderStatus::Cancelled, Some(OrderReason::LinkedOrder)))
+    );
+    assert_eq!(groups.pending_stops(), 0);
+}
+
+#[test]
+fn oco_trigger_pulls_the_limit_leg_and_executes_the_stop() {
+    let mut book = OrderBook::new(PAIR);
+    let mut groups = OrderGroups::new();
+    let trader = Uuid::new_v4();
+    let other = Uuid::new_v4();
+
+    let leg = stop("90");
+    let mut oco = limit(trader, OrderSide::Sell, "110", "2");
+    oco.group = Some(OrderGroup::Oco { stop: leg.clone() });
+    groups.submit(&mut book, oco.clone());
+    // Liquidity for the stop to sell into once it triggers.
+    groups.submit(&mut book, limit(other, OrderSide::Buy, "89", "5"));
+
+    // Someone else sells through the trigger.
+    let events = groups.submit(&mut book, market(other, OrderSide::Sell, "1"));
+
+    assert_eq!(
+        last_status(&events, oco.order_id),
+        Some((OrderStatus::Cancelled, Some(OrderReason::LinkedOrder)))
+    );
+    assert_eq!(
+        last_status(&events, leg.order_id),
+        Some((OrderStatus::Filled, None))
+    );
+    assert_eq!(trades(&events), 2);
+    assert!(book.get(oco.order_id).is_none());
+    assert_eq!(groups.pending_stops(), 0);
+}
+
+#[test]
+fn cancelling_either_oco_leg_cancels_the_other() {
+    let mut book = OrderBook::new(PAIR);
+    let mut groups = OrderGroups::new();
+    let trader = Uuid::new_v4();
+
+    let leg = stop("90");
+    let mut oco = limit(trader, OrderSide::Sell, "110", "2");
+    oco.group = Some(OrderGroup::Oco { stop: leg.clone() });
+    groups.submit(&mut book, oco.clone());
+
+    let events = groups.cancel(&mut book, leg.order_id).unwrap();
+    assert_eq!(
+        last_status(&events, leg.order_id),
+        Some((OrderStatus::Cancelled, Some(OrderReason::UserCancelled)))
+    );
+    assert_eq!(
+        last_status(&events, oco.order_id),
+        Some((OrderStatus::Cancelled, Some(OrderReason::LinkedOrder)))
+    );
+
+    let leg = stop("90");
+    let mut oco = limit(trader, OrderSide::Sell, "110", "2");
+    oco.group = Some(OrderGroup::Oco { stop: leg.clone() });
+    groups.submit(&mut book, oco.clone());
+
+    let events = groups.cancel(&mut book, oco.order_id).unwrap();
+    assert_eq!(
+        last_status(&events, leg.order_id),
+        Some((OrderStatus::Cancelled, Some(OrderReason::LinkedOrder)))
+    );
+    assert!(groups.cancel(&mut book, leg.order_id).is_none());
+}
+
+#[test]
+fn bracket_exits_activate_once_the_entry_fills() {
+    let mut book = OrderBook::new(PAIR);
+    let mut groups = OrderGroups::new();
+    let trader = Uuid::new_v4();
+    let other = Uuid::new_v4();
+
+    let take_profit = TakeProfitLeg {
+        order_id: Uuid::new_v4(),
+        price: dec("120"),
+    };
+    let stop_loss = stop("90");
+    let mut entry = limit(trader, OrderSide::Buy, "100", "3");
+    entry.group = Some(OrderGroup::Bracket {
+        take_profit: take_profit.clone(),
+        stop_loss: stop_loss.clone(),
+    });
+    groups.submit(&mut book, entry.clone());
+    assert_eq!(groups.pending_brackets(), 1);
+
+    // A partial fill is not enough.
+    let events = groups.submit(&mut book, limit(other, OrderSide::Sell, "100", "1"));
+    assert!(last_status(&events, take_profit.order_id).is_none());
+
+    let events = groups.submit(&mut book, limit(other, OrderSide::Sell, "100", "2"));
+    assert_eq!(
+        last_status(&events, entry.order_id),
+        Some((OrderStatus::Filled, None))
+    );
+    assert_eq!(
+        last_status(&events, take_profit.order_id),
+        Some((OrderStatus::New, None))
+    );
+    let exit = book.get(take_profit.order_id).unwrap();
+    assert_eq!(exit.side, OrderSide::Sell);
+    assert_eq!(exit.quantity, dec("3"));
+    assert_eq!(groups.stop(stop_loss.order_id).unwrap().quantity, dec("3"));
+    assert_eq!(groups.pending_brackets(), 0);
+}
+
+#[test]
+fn bracket_entry_cancelled_unfilled_drops_its_exits() {
+    let mut book = OrderBook::new(PAIR);
+    let mut groups = OrderGroups::new();
+    let trader = Uuid::new_v4();
+
+    let mut entry = limit(trader, OrderSide::Buy, "100", "3");
+    entry.group = Some(OrderGroup::Bracket {
+        take_profit: TakeProfitLeg {
+            order_id: Uuid::new_v4(),
+            price: dec("120"),
+        },
+        stop_loss: stop("90"),
+    });
+    groups.submit(&mut book, entry.clone());
+
+    let events = groups.cancel(&mut book, entry.order_id).unwrap();
+    assert_eq!(updates(&events).len(), 1);
+    assert_eq!(groups.pending_brackets(), 0);
+    assert_eq!(groups.pending_stops(), 0);
+}
+
+#[test]
+fn mass_cancel_takes_stop_legs_down_too() {
+    let mut book = OrderBook::new(PAIR);
+    let mut groups = OrderGroups::new();
+    let trader = Uuid::new_v4();
+
+    let leg = stop("90");
+    let mut oco = limit(trader, OrderSide::Sell, "110", "2");
+    oco.group = Some(OrderGroup::Oco { stop: leg.clone() });
+    groups.submit(&mut book, oco.clone());
+
+    let swept = book.cancel_user_orders(trader, None, OrderReason::MassCancel);
+    let events = groups.apply(&mut book, swept);
+    assert_eq!(
+        last_status(&events, oco.order_id),
+        Some((OrderStatus::Cancelled, Some(OrderReason::MassCancel)))
+    );
+    assert_eq!(
+        last_status(&events, leg.order_id),
+        Some((OrderStatus::Cancelled, Some(OrderReason::LinkedOrder)))
+    );
+}
+
+#[test]
+fn rejects_malformed_groups() {
+    let mut book = OrderBook::new(PAIR);
+    let mut groups = OrderGroups::new();
+    let trader = Uuid::new_v4();
+
+    // OCO needs a limit order.
+    let mut oco = market(trader, OrderSide::Sell, "1");
+    oco.group = Some(OrderGroup::Oco { stop: stop("90") });
+    let events = groups.submit(&mut book, oco.clone());
+    assert_eq!(
+        last_status(&events, oco.order_id),
+        Some((OrderStatus::Rejected, Some(OrderReason::InvalidGroup)))
+    );
+
+    let mut oco = limit(trader, OrderSide::Sell, "110", "1");
+    oco.group = Some(OrderGroup::Oco { stop: stop("0") });
+    let events = groups.submit(&mut book, oco.clone());
+    assert_eq!(
+        last_status(&events, oco.order_id),
+        Some((OrderStatus::Rejected, Some(OrderReason::InvalidGroup)))
+    );
+    assert_eq!(groups.pending_stops(), 0);
+    assert!(book.get(oco.order_id).is_none());
+}
+
+#[test]
+fn rejects_brackets_with_exits_on_the_wrong_side() {
+    let mut book = OrderBook::new(PAIR);
+    let mut groups = OrderGroups::new();
+    let trader = Uuid::new_v4();
+    let bracket = |take_profit: &str, stop_loss: &str| OrderGroup::Bracket {
+        take_profit: TakeProfitLeg {
+            order_id: Uuid::new_v4(),
+            price: dec(take_profit),
+        },
+        stop_loss: stop(stop_loss),
+    };
+
+    // Buying at 100: a take-profit below the entry, a stop-loss above it.
+    for (side, take_profit, stop_loss) in [
+        (OrderSide::Buy, "95", "90"),
+        (OrderSide::Buy, "120", "105"),
+        (OrderSide::Sell, "105", "110"),
+        (OrderSide::Sell, "90", "95"),
+    ] {
+        let mut entry = limit(trader, side, "100", "1");
+        entry.group = Some(bracket(take_profit, stop_loss));
+        let events = groups.submit(&mut book, entry.clone());
+        assert_eq!(
+            last_status(&events, entry.order_id),
+            Some((OrderStatus::Rejected, Some(OrderReason::InvalidGroup))),
+            "{side:?} tp {take_profit} sl {stop_loss}"
+        );
+    }
+
+    // Market entries only need the exits the right way round.
+    let mut entry = market(trader, OrderSide::Buy, "1");
+    entry.group = Some(bracket("90", "120"));
+    let events = groups.submit(&mut book, entry.clone());
+    assert_eq!(
+        last_status(&events, entry.order_id),
+        Some((OrderStatus::Rejected, Some(OrderReason::InvalidGroup)))
+    );
+    assert_eq!(groups.pending_brackets(), 0);
+}
+
+#[test]
+fn mass_cancelled_part_filled_entry_leaves_no_orders_behind() {
+    let mut book = OrderBook::new(PAIR);
+    let mut groups = OrderGroups::new();
+    let trader = Uuid::new_v4();
+
+    let take_profit = TakeProfitLeg {
+        order_id: Uuid::new_v4(),
+        price: dec("120"),
+    };
+    let stop_loss = stop("90");
+    let mut entry = limit(trader, OrderSide::Buy, "100", "3");
+    entry.group = Some(OrderGroup::Bracket {
+        take_profit: take_profit.clone(),
+        stop_loss: stop_loss.clone(),
+    });
+    groups.submit(&mut book, entry.clone());
+    groups.submit(
+        &mut book,
+        limit(Uuid::new_v4(), OrderSide::Sell, "100", "1"),
+    );
+
+    let swept = book.cancel_user_orders(trader, None, OrderReason::MassCancel);
+    let events = groups.apply(&mut book, swept);
+    assert_eq!(
+        last_status(&events, entry.order_id),
+        Some((OrderStatus::Cancelled, Some(OrderReason::MassCancel)))
+    );
+    assert!(last_status(&events, take_profit.order_id).is_none());
+    assert!(book.get(take_profit.order_id).is_none());
+    assert!(groups.stop(stop_loss.order_id).is_none());
+    assert_eq!(groups.pending_brackets(), 0);
+    assert_eq!(groups.pending_stops(), 0);
+    assert!(book.depth().bids.is_empty() && book.depth().asks.is_empty());
+}
@@ -0,0 +1,363 @@
+use std::collections::HashMap;
+
+use engine::orderbook::{Execution, OrderBook};
+use engine::rules::MatchingAlgorithm;
+use proptest::prelude::*;
+use rust_decimal::Decimal;
+use shared::types::{new_order, Order, OrderId, OrderSide, OrderStatus, OrderType};
+use uuid::Uuid;
+
+#[derive(Debug, Clone)]
+enum Step {
+    Limit {
+        side: OrderSide,
+        tick: u32,
+        qty: u32,
+    },
+    Market {
+        side: OrderSide,
+        qty: u32,
+    },
+    /// Cancels one of the orders placed so far, resting or not.
+    Cancel {
+        pick: usize,
+    },
+}
+
+fn side() -> impl Strategy<Value = OrderSide> {
+    prop_oneof![Just(OrderSide::Buy), Just(OrderSide::Sell)]
+}
+
+fn step() -> impl Strategy<Value = Step> {
+    prop_oneof![
+        6 => (side(), 0u32..20, 1u32..=10).prop_map(|(side, tick, qty)| Step::Limit { side, tick, qty }),
+        1 => (side(), 1u32..=25).prop_map(|(side, qty)| Step::Market { side, qty }),
+        3 => any::<usize>().prop_map(|pick| Step::Cancel { pick }),
+    ]
+}
+
+fn steps() -> impl Strategy<Value = Vec<Step>> {
+    prop::collection::vec(step(), 1..200)
+}
+
+fn algorithm() -> impl Strategy<Value = MatchingAlgorithm> {
+    prop_oneof![
+        Just(MatchingAlgorithm::Fifo),
+        Just(MatchingAlgorithm::ProRata {
+            lot_size: None,
+            min_allocation: Decimal::ZERO,
+        }),
+        Just(MatchingAlgorithm::ProRata {
+            lot_size: Some(Decimal::ONE),
+            min_allocation: Decimal::TWO,
+        }),
+        Just(MatchingAlgorithm::FifoTopOrder {
+            top_order_max: Decimal::TWO,
+        }),
+        Just(MatchingAlgorithm::ProRataTopOrder {
+            lot_size: Some(Decimal::ONE),
+            min_allocation: Decimal::ZERO,
+        }),
+    ]
+}
+
+/// The order a step places, with ids numbered by arrival.
+fn order(seq: u128, step: &Step) -> Option<Order> {
+    let (side, order_type, price, qty) = match *step {
+        Step::Limit { side, tick, qty } => (side, OrderType::Limit, Decimal::from(100 + tick), qty),
+        Step::Market { side, qty } => (side, OrderType::Market, Decimal::ZERO, qty),
+        Step::Cancel { .. } => return None,
+    };
+    let mut new = new_order(
+        Uuid::from_u128(seq % 3 + 1),
+        "SOLUSDC".to_string(),
+        side,
+        order_type,
+        price,
+        Decimal::from(qty),
+    );
+    new.order_id = Uuid::from_u128(1_000 + seq);
+    Some(Order::from_new(new))
+}
+
+/// Best bids first, then best asks first.
+type Depth = (Vec<(Decimal, Decimal)>, Vec<(Decimal, Decimal)>);
+
+fn book_depth(book: &OrderBook) -> Depth {
+    let depth = book.depth();
+    let levels = |levels: Vec<shared::types::DepthLevel>| {
+        levels
+            .into_iter()
+            .map(|level| (level.price, level.quantity))
+            .collect()
+    };
+    (levels(depth.bids), levels(depth.asks))
+}
+
+fn trade_log(execution: &Execution) -> Vec<(OrderId, OrderId, Decimal, Decimal)> {
+    execution
+        .trades
+        .iter()
+        .map(|t| (t.buy_order_id, t.sell_order_id, t.price, t.quantity))
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+struct Resting {
+    side: OrderSide,
+    price: Decimal,
+    open: Decimal,
+    seq: u128,
+}
+
+/// What should be resting, rebuilt from nothing but the book's own output.
+#[derive(Default)]
+struct Model {
+    resting: HashMap<OrderId, Resting>,
+}
+
+impl Model {
+    fn depth(&self) -> Depth {
+        let mut bids: HashMap<Decimal, Decimal> = HashMap::new();
+        let mut asks: HashMap<Decimal, Decimal> = HashMap::new();
+        for order in self.resting.values() {
+            let levels = match order.side {
+                OrderSide::Buy => &mut bids,
+                OrderSide::Sell => &mut asks,
+            };
+            *levels.entry(order.price).or_default() += order.open;
+        }
+        let mut bids: Vec<_> = bids.into_iter().collect();
+        let mut asks: Vec<_> = asks.into_iter().collect();
+        bids.sort_by_key(|&(price, _)| std::cmp::Reverse(price));
+        asks.sort_by_key(|&(price, _)| price);
+        (bids, asks)
+    }
+
+    /// Applies one execution, checking each trade against what was resting.
+    fn apply(
+        &mut self,
+        incoming: &Order,
+        seq: u128,
+        execution: &Execution,
+        fifo: bool,
+    ) -> Result<(), TestCaseError> {
+        let mut filled = Decimal::ZERO;
+        for trade in &execution.trades {
+            let (mine, theirs) = match incoming.side {
+                OrderSide::Buy => (trade.buy_order_id, trade.sell_order_id),
+                OrderSide::Sell => (trade.sell_order_id, trade.buy_order_id),
+            };
+            prop_assert_eq!(mine, incoming.order_id);
+            prop_assert!(trade.quantity > Decimal::ZERO);
+            let resting = self.resting.get(&theirs).cloned();
+            let Some(resting) = resting else {
+                return Err(TestCaseError::fail(format!(
+                    "{theirs} traded but was not resting"
+                )));
+            };
+            prop_assert!(trade.quantity <= resting.open);
+            prop_assert_eq!(trade.price, resting.price);
+            if incoming.order_type == OrderType::Limit {
+                match incoming.side {
+                    OrderSide::Buy => prop_assert!(trade.price <= incoming.price),
+                    OrderSide::Sell => prop_assert!(trade.price >= incoming.price),
+                }
+            }
+
+            // Price priority always; time priority within the level for FIFO.
+            let opposite = self.resting.values().filter(|o| o.side == resting.side);
+            let best = match resting.side {
+                OrderSide::Buy => opposite.map(|o| o.price).max(),
+                OrderSide::Sell => opposite.map(|o| o.price).min(),
+            };
+            prop_assert_eq!(Some(resting.price), best);
+            if fifo {
+                let first = self
+                    .resting
+                    .values()
+                    .filter(|o| o.side == resting.side && o.price == resting.price)
+                    .map(|o| o.seq)
+                    .min();
+                prop_assert_eq!(Some(resting.seq), first, "filled out of time order");
+            }
+
+            filled += trade.quantity;
+            let left = resting.open - trade.quantity;
+            if left == Decimal::ZERO {
+                self.resting.remove(&theirs);
+            } else if let Some(order) = self.resting.get_mut(&theirs) {
+                order.open = left;
+            }
+        }
+
+        let Some(last) = execution
+            .updates
+            .iter()
+            .rev()
+            .find(|update| update.order_id == incoming.order_id)
+        else {
+            return Err(TestCaseError::fail("no update for the incoming order"));
+        };
+        prop_assert_eq!(last.filled, filled);
+        prop_assert!(filled <= incoming.quantity);
+        let rests = incoming.order_type == OrderType::Limit
+            && matches!(last.status, OrderStatus::New | OrderStatus::PartiallyFilled);
+        if rests {
+            self.resting.insert(
+                incoming.order_id,
+                Resting {
+                    side: incoming.side,
+                    price: incoming.price,
+                    open: incoming.quantity - filled,
+                    seq,
+                },
+            );
+        } else if last.status == OrderStatus::Filled {
+            prop_assert_eq!(filled, incoming.quantity);
+        }
+        Ok(())
+    }
+}
+
+/// Price-time matching over a list, written for obviousness not speed.
+#[derive(Default)]
+struct Reference {
+    /// In arrival order.
+    resting: Vec<(OrderId, OrderSide, Decimal, Decimal)>,
+}
+
+impl Reference {
+    fn submit(&mut self, order: &Order) -> Vec<(OrderId, OrderId, Decimal, Decimal)> {
+        let mut open = order.quantity;
+        let mut trades = Vec::new();
+        while open > Decimal::ZERO {
+            let best = self
+                .resting
+                .iter()
+                .enumerate()
+                .filter(|(_, resting)| resting.1 != order.side)
+                .min_by_key(|(i, resting)| match order.side {
+                    OrderSide::Buy => (resting.2, *i),
+                    OrderSide::Sell => (-resting.2, *i),
+                })
+                .map(|(i, _)| i);
+            let Some(i) = best else {
+                break;
+            };
+            let (id, _, price, resting_open) = self.resting[i];
+            let crosses = match (order.order_type, order.side) {
+                (OrderType::Market, _) => true,
+                (OrderType::Limit, OrderSide::Buy) => price <= order.price,
+                (OrderType::Limit, OrderSide::Sell) => price >= order.price,
+            };
+            if !crosses {
+                break;
+            }
+            let quantity = open.min(resting_open);
+            trades.push(match order.side {
+                OrderSide::Buy => (order.order_id, id, price, quantity),
+                OrderSide::Sell => (id, order.order_id, price, quantity),
+            });
+            open -= quantity;
+            if quantity == resting_open {
+                self.resting.remove(i);
+            } else {
+                self.resting[i].3 -= quantity;
+            }
+        }
+        if order.order_type == OrderType::Limit && open > Decimal::ZERO {
+            self.resting
+                .push((order.order_id, order.side, order.price, open));
+        }
+        trades
+    }
+
+    fn cancel(&mut self, order_id: OrderId) -> bool {
+        let before = self.resting.len();
+        self.resting.retain(|resting| resting.0 != order_id);
+        self.resting.len() < before
+    }
+
+    fn depth(&self) -> Depth {
+        let model = Model {
+            resting: self
+                .resting
+                .iter()
+                .enumerate()
+                .map(|(seq, &(id, side, price, open))| {
+                    let seq = seq as u128;
+                    (
+                        id,
+                        Resting {
+                            side,
+                            price,
+                            open,
+                            seq,
+                        },
+                    )
+                })
+                .collect(),
+        };
+        model.depth()
+    }
+}
+
+proptest! {
+    #[test]
+    fn invariants_hold_after_every_step(steps in steps(), algorithm in algorithm()) {
+        let mut book = OrderBook::new("SOLUSDC");
+        book.set_matching(algorithm.strategy());
+        let fifo = algorithm == MatchingAlgorithm::Fifo;
+        let mut model = Model::default();
+        let mut placed: Vec<OrderId> = Vec::new();
+
+        for (seq, step) in steps.iter().enumerate() {
+            let seq = seq as u128;
+            if let Step::Cancel { pick } = step {
+                let Some(&order_id) = placed.get(pick % placed.len().max(1)) else {
+                    continue;
+                };
+                let cancelled = book.cancel_order(order_id);
+                prop_assert_eq!(cancelled.is_some(), model.resting.remove(&order_id).is_some());
+            } else if let Some(order) = order(seq, step) {
+                placed.push(order.order_id);
+                let execution = book.execute(order.clone());
+                model.apply(&order, seq, &execution, fifo)?;
+            }
+
+            if let Err(err) = book.validate() {
+                return Err(TestCaseError::fail(err.to_string()));
+            }
+            prop_assert_eq!(book_depth(&book), model.depth());
+            for (order_id, resting) in &model.resting {
+                let open = book.get(*order_id).map(Order::remaining);
+                prop_assert_eq!(open, Some(resting.open));
+            }
+        }
+    }
+
+    #[test]
+    fn fifo_book_matches_the_reference(steps in steps()) {
+        let mut book = OrderBook::new("SOLUSDC");
+        // The reference has no notion of market order protection.
+        book.set_market_protection(None);
+        let mut reference = Reference::default();
+        let mut placed: Vec<OrderId> = Vec::new();
+
+        for (seq, step) in steps.iter().enumerate() {
+            if let Step::Cancel { pick } = step {
+                let Some(&order_id) = placed.get(pick % placed.len().max(1)) else {
+                    continue;
+                };
+                prop_assert_eq!(book.cancel(order_id), reference.cancel(order_id));
+            } else if let Some(order) = order(seq as u128, step) {
+                placed.push(order.order_id);
+                let expected = reference.submit(&order);
+                let execution = book.execute(order);
+                prop_assert_eq!(trade_log(&execution), expected);
+            }
+            prop_assert_eq!(book_depth(&book), reference.depth());
+        }
+    }
+}
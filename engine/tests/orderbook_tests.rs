@@ -1,6 +1,6 @@
 use engine::orderbook::OrderBook;
 use rust_decimal::Decimal;
-use shared::types::{new_order, Order, OrderSide, OrderType};
+use shared::types::{new_order, Order, OrderReason, OrderSide, OrderStatus, OrderType};
 use uuid::Uuid;
 
 fn mk_order(user: &str, side: OrderSide, price: &str, qty: &str) -> Order {
@@ -15,6 +15,10 @@ fn mk_order(user: &str, side: OrderSide, price: &str, qty: &str) -> Order {
     Order::from_new(o)
 }
 
+fn dec(s: &str) -> Decimal {
+    Decimal::from_str_exact(s).unwrap()
+}
+
 #[test]
 fn matches_limit_cross_and_clears_book() {
     let mut book = OrderBook::new("SOLUSDC");
@@ -98,3 +102,334 @@ fn cancel_removes_order() {
     let depth = book.depth();
     assert!(depth.asks.is_empty());
 }
+
+#[test]
+fn execution_reports_lifecycle_of_both_sides() {
+    let mut book = OrderBook::new("SOLUSDC");
+    let cheap = mk_order(
+        "aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa",
+        OrderSide::Sell,
+        "30.0",
+        "2",
+    );
+    let dear = mk_order(
+        "aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa",
+        OrderSide::Sell,
+        "31.0",
+        "4",
+    );
+    let dear_id = dear.order_id;
+    let accepted = book.execute(cheap).updates;
+    assert_eq!(accepted.len(), 1);
+    assert_eq!(accepted[0].status, OrderStatus::New);
+    book.execute(dear);
+
+    let buy = mk_order(
+        "bbbbbbbb-bbbb-bbbb-bbbb-bbbbbbbbbbbb",
+        OrderSide::Buy,
+        "31.0",
+        "4",
+    );
+    let buy_id = buy.order_id;
+    let execution = book.execute(buy);
+    assert_eq!(execution.trades.len(), 2);
+
+    let statuses: Vec<_> = execution
+        .updates
+        .iter()
+        .map(|u| (u.order_id == buy_id, u.status))
+        .collect();
+    assert_eq!(
+        statuses,
+        vec![
+            (true, OrderStatus::New),
+            (false, OrderStatus::Filled),
+            (false, OrderStatus::PartiallyFilled),
+            (true, OrderStatus::Filled),
+        ]
+    );
+    let last = execution.updates.last().unwrap();
+    assert_eq!(last.filled, dec("4"));
+    // (2 * 30 + 2 * 31) / 4
+    assert_eq!(last.avg_price, dec("30.5"));
+
+    assert_eq!(book.get(dear_id).map(|o| o.filled), Some(dec("2")));
+    assert!(book.get(buy_id).is_none());
+    let cancelled = book.cancel_order(dear_id).expect("resting order");
+    assert_eq!(cancelled.status, OrderStatus::Cancelled);
+    assert_eq!(cancelled.reason, Some(OrderReason::UserCancelled));
+    assert_eq!(cancelled.filled, dec("2"));
+    assert_eq!(cancelled.avg_price, dec("31.0"));
+    assert!(book.cancel_order(dear_id).is_none());
+}
+
+#[test]
+fn unfilled_market_remainder_expires() {
+    let mut book = OrderBook::new("SOLUSDC");
+    book.execute(mk_order(
+        "aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa",
+        OrderSide::Sell,
+        "30.0",
+        "1",
+    ));
+    let mut market = mk_order(
+        "bbbbbbbb-bbbb-bbbb-bbbb-bbbbbbbbbbbb",
+        OrderSide::Buy,
+        "0",
+        "3",
+    );
+    market.order_type = OrderType::Market;
+
+    let execution = book.execute(market);
+    let last = execution.updates.last().unwrap();
+    assert_eq!(last.status, OrderStatus::Expired);
+    assert_eq!(last.reason, Some(OrderReason::NoLiquidity));
+    assert_eq!(last.filled, dec("1"));
+    assert!(book.depth().bids.is_empty());
+}
+
+#[test]
+fn invalid_orders_are_rejected_without_touching_the_book() {
+    let mut book = OrderBook::new("SOLUSDC");
+    let zero_qty = mk_order(
+        "bbbbbbbb-bbbb-bbbb-bbbb-bbbbbbbbbbbb",
+        OrderSide::Buy,
+        "30.0",
+        "0",
+    );
+    let zero_price = mk_order(
+        "bbbbbbbb-bbbb-bbbb-bbbb-bbbbbbbbbbbb",
+        OrderSide::Buy,
+        "0",
+        "1",
+    );
+    for (order, reason) in [
+        (zero_qty, OrderReason::InvalidQuantity),
+        (zero_price, OrderReason::InvalidPrice),
+    ] {
+        let execution = book.execute(order);
+        assert!(execution.trades.is_empty());
+        assert_eq!(execution.updates.len(), 1);
+        assert_eq!(execution.updates[0].status, OrderStatus::Rejected);
+        assert_eq!(execution.updates[0].reason, Some(reason));
+    }
+    assert!(book.depth().bids.is_empty());
+}
+
+#[test]
+fn cancel_user_orders_filters_by_user_and_side() {
+    let alice = "aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa";
+    let bob = "bbbbbbbb-bbbb-bbbb-bbbb-bbbbbbbbbbbb";
+    let alice_id = Uuid::parse_str(alice).unwrap();
+    let mut book = OrderBook::new("SOLUSDC");
+    let bids: Vec<_> = ["29.0", "28.0"]
+        .iter()
+        .map(|p| mk_order(alice, OrderSide::Buy, p, "1"))
+        .collect();
+    let ask = mk_order(alice, OrderSide::Sell, "31.0", "2");
+    let ask_id = ask.order_id;
+    let bobs = mk_order(bob, OrderSide::Buy, "29.0", "1");
+    let bobs_id = bobs.order_id;
+    for order in bids.iter().cloned().chain([ask, bobs]) {
+        book.execute(order);
+    }
+
+    let cancelled =
+        book.cancel_user_orders(alice_id, Some(OrderSide::Buy), OrderReason::MassCancel);
+    assert_eq!(cancelled.len(), 2);
+    assert!(cancelled
+        .iter()
+        .all(|u| u.status == OrderStatus::Cancelled && u.reason == Some(OrderReason::MassCancel)));
+    assert!(book.get(ask_id).is_some());
+    assert!(book.get(bobs_id).is_some());
+
+    let cancelled = book.cancel_user_orders(alice_id, None, OrderReason::MassCancel);
+    assert_eq!(cancelled.len(), 1);
+    assert_eq!(cancelled[0].order_id, ask_id);
+    assert!(book
+        .cancel_user_orders(alice_id, None, OrderReason::MassCancel)
+        .is_empty());
+}
+
+#[test]
+fn filled_orders_leave_the_user_index() {
+    let alice = "aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa";
+    let mut book = OrderBook::new("SOLUSDC");
+    book.execute(mk_order(alice, OrderSide::Sell, "30.0", "1"));
+    book.execute(mk_order(
+        "bbbbbbbb-bbbb-bbbb-bbbb-bbbbbbbbbbbb",
+        OrderSide::Buy,
+        "30.0",
+        "1",
+    ));
+    let alice_id = Uuid::parse_str(alice).unwrap();
+    assert!(book
+        .cancel_user_orders(alice_id, None, OrderReason::MassCancel)
+        .is_empty());
+}
+
+fn mk_market(user: &str, side: OrderSide, qty: &str) -> Order {
+    let mut order = mk_order(user, side, "0", qty);
+    order.order_type = OrderType::Market;
+    order
+}
+
+fn asks(book: &mut OrderBook, levels: &[(&str, &str)]) {
+    for (price, qty) in levels {
+        book.execute(mk_order(
+            "aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa",
+            OrderSide::Sell,
+            price,
+            qty,
+        ));
+    }
+}
+
+#[test]
+fn market_order_into_an_empty_side_is_rejected() {
+    let mut book = OrderBook::new("SOLUSDC");
+    let execution = book.execute(mk_market(
+        "bbbbbbbb-bbbb-bbbb-bbbb-bbbbbbbbbbbb",
+        OrderSide::Buy,
+        "1",
+    ));
+    assert_eq!(execution.updates.len(), 1);
+    assert_eq!(execution.updates[0].status, OrderStatus::Rejected);
+    assert_eq!(execution.updates[0].reason, Some(OrderReason::NoLiquidity));
+}
+
+#[test]
+fn market_sweep_stops_at_the_protection_band() {
+    let mut book = OrderBook::new("SOLUSDC");
+    asks(&mut book, &[("100", "1"), ("104", "1"), ("106", "1")]);
+
+    // Default band is 5% above the best ask.
+    let execution = book.execute(mk_market(
+        "bbbbbbbb-bbbb-bbbb-bbbb-bbbbbbbbbbbb",
+        OrderSide::Buy,
+        "3",
+    ));
+    assert_eq!(execution.trades.len(), 2);
+    let last = execution.updates.last().unwrap();
+    assert_eq!(last.status, OrderStatus::Expired);
+    assert_eq!(last.reason, Some(OrderReason::PriceProtection));
+    assert_eq!(last.filled, dec("2"));
+    assert_eq!(book.depth().asks[0].price, dec("106"));
+
+    // A tighter per-order band stops sooner.
+    asks(&mut book, &[("100", "1"), ("101", "1")]);
+    let mut tight = mk_market("bbbbbbbb-bbbb-bbbb-bbbb-bbbbbbbbbbbb", OrderSide::Buy, "2");
+    tight.max_slippage_bps = Some(50);
+    let execution = book.execute(tight);
+    assert_eq!(execution.trades.len(), 1);
+    assert_eq!(execution.updates.last().unwrap().filled, dec("1"));
+}
+
+#[test]
+fn quote_sized_market_buy_spends_its_budget() {
+    let mut book = OrderBook::new("SOLUSDC");
+    asks(&mut book, &[("10", "2"), ("20", "5")]);
+
+    let mut buy = mk_market("bbbbbbbb-bbbb-bbbb-bbbb-bbbbbbbbbbbb", OrderSide::Buy, "0");
+    buy.quote_quantity = Some(dec("50"));
+    buy.max_slippage_bps = Some(10_000);
+    let execution = book.execute(buy);
+
+    // 2 at 10, then the remaining 30 buys 1.5 at 20.
+    assert_eq!(execution.trades.len(), 2);
+    assert_eq!(execution.trades[1].quantity, dec("1.5"));
+    let last = execution.updates.last().unwrap();
+    assert_eq!(last.status, OrderStatus::Filled);
+    assert_eq!(last.quantity, dec("3.5"));
+    assert_eq!(last.filled, dec("3.5"));
+    assert_eq!(book.depth().asks[0].quantity, dec("3.5"));
+}
+
+#[test]
+fn quote_budget_that_buys_nothing_is_rejected() {
+    let mut book = OrderBook::new("SOLUSDC");
+    asks(&mut book, &[("10", "2")]);
+
+    let mut order = mk_market("bbbbbbbb-bbbb-bbbb-bbbb-bbbbbbbbbbbb", OrderSide::Buy, "0");
+    order.quote_quantity = Some(dec("0.00000001"));
+    let execution = book.execute(order);
+    assert!(execution.trades.is_empty());
+    assert_eq!(execution.updates.len(), 1);
+    assert_eq!(execution.updates[0].status, OrderStatus::Rejected);
+    assert_eq!(
+        execution.updates[0].reason,
+        Some(OrderReason::BudgetTooSmall)
+    );
+    assert_eq!(book.depth().asks[0].quantity, dec("2"));
+}
+
+#[test]
+fn quote_sized_orders_must_be_market_buys() {
+    let mut book = OrderBook::new("SOLUSDC");
+    asks(&mut book, &[("10", "2")]);
+
+    let mut limit = mk_order(
+        "bbbbbbbb-bbbb-bbbb-bbbb-bbbbbbbbbbbb",
+        OrderSide::Buy,
+        "10",
+        "0",
+    );
+    limit.quote_quantity = Some(dec("10"));
+    let mut both = mk_market("bbbbbbbb-bbbb-bbbb-bbbb-bbbbbbbbbbbb", OrderSide::Buy, "1");
+    both.quote_quantity = Some(dec("10"));
+    for order in [limit, both] {
+        let execution = book.execute(order);
+        assert_eq!(execution.updates[0].status, OrderStatus::Rejected);
+        assert_eq!(
+            execution.updates[0].reason,
+            Some(OrderReason::InvalidQuantity)
+        );
+    }
+}
+
+#[test]
+fn cancelling_mid_queue_keeps_time_priority_and_level_totals() {
+    let mut book = OrderBook::new("SOLUSDC");
+    let asks: Vec<Order> = ["1", "2", "3"]
+        .iter()
+        .map(|qty| {
+            mk_order(
+                "aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa",
+                OrderSide::Sell,
+                "30",
+                qty,
+            )
+        })
+        .collect();
+    let ids: Vec<_> = asks.iter().map(|o| o.order_id).collect();
+    for ask in asks {
+        book.execute(ask);
+    }
+    assert_eq!(book.level_quantity(OrderSide::Sell, dec("30")), dec("6"));
+
+    assert!(book.cancel(ids[1]));
+    assert!(book.get(ids[1]).is_none());
+    assert_eq!(book.level_quantity(OrderSide::Sell, dec("30")), dec("4"));
+
+    // The first and last orders are still first and last in line.
+    let buy = mk_order(
+        "bbbbbbbb-bbbb-bbbb-bbbb-bbbbbbbbbbbb",
+        OrderSide::Buy,
+        "30",
+        "2",
+    );
+    let (trades, _) = book.upsert(buy);
+    assert_eq!(trades.len(), 2);
+    assert_eq!(trades[0].sell_order_id, ids[0]);
+    assert_eq!(trades[1].sell_order_id, ids[2]);
+    assert_eq!(book.get(ids[2]).unwrap().remaining(), dec("2"));
+    assert_eq!(book.depth().asks[0].quantity, dec("2"));
+
+    assert!(book.cancel(ids[2]));
+    assert!(book.depth().asks.is_empty());
+    assert_eq!(
+        book.level_quantity(OrderSide::Sell, dec("30")),
+        Decimal::ZERO
+    );
+    assert!(!book.cancel(ids[2]));
+}
@@ -0,0 +1,196 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use chrono::{DateTime, Utc};
+use redis_rs::{AsyncCommands, Script};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use shared::{from_json, to_json, CexError};
+use uuid::Uuid;
+
+use crate::manager::RedisManager;
+use crate::queues::{DEAD_LETTER_ENTRIES, DEAD_LETTER_INDEX, STREAM_EVENTS_MAX_LEN};
+use crate::streams::PAYLOAD_FIELD;
+
+/// Pushes a dead letter's payload back and removes the letter in one step,
+/// and only if the letter is still there, so concurrent re-drives of the
+/// same letter deliver it once.
+const REDRIVE_LUA: &str = r#"
+if redis.call('HEXISTS', KEYS[1], ARGV[1]) == 0 then
+    return 0
+end
+if ARGV[2] == 'queue' then
+    redis.call('RPUSH', KEYS[3], ARGV[3])
+else
+    redis.call('XADD', KEYS[3], 'MAXLEN', '~', ARGV[4], '*', ARGV[5], ARGV[3])
+end
+redis.call('HDEL', KEYS[1], ARGV[1])
+redis.call('ZREM', KEYS[2], ARGV[1])
+return 1
+"#;
+
+/// Where a dead-lettered message was read from, and where a re-drive puts it back.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "name", rename_all = "snake_case")]
+pub enum DeadLetterOrigin {
+    Queue(String),
+    Stream(String),
+}
+
+/// A message a service could not decode or process, kept with enough context
+/// to inspect it and push it back once the cause is fixed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub id: Uuid,
+    /// Service that gave up on the message.
+    pub source: String,
+    pub origin: DeadLetterOrigin,
+    /// Raw bytes as received; base64 in JSON since payloads may be MessagePack.
+    #[serde(with = "base64_bytes")]
+    pub payload: Vec<u8>,
+    pub error: String,
+    pub failed_at: DateTime<Utc>,
+}
+
+impl DeadLetter {
+    pub fn new(
+        source: impl Into<String>,
+        origin: DeadLetterOrigin,
+        payload: impl Into<Vec<u8>>,
+        error: impl ToString,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            source: source.into(),
+            origin,
+            payload: payload.into(),
+            error: error.to_string(),
+            failed_at: Utc::now(),
+        }
+    }
+}
+
+impl RedisManager {
+    /// Stores a dead letter; entries are kept until re-driven or discarded.
+    pub async fn push_dead_letter(&self, letter: &DeadLetter) -> Result<(), CexError> {
+        let mut conn = self.connection().await?;
+        redis_rs::pipe()
+            .atomic()
+            .hset(DEAD_LETTER_ENTRIES, letter.id.to_string(), to_json(letter)?)
+            .ignore()
+            .zadd(
+                DEAD_LETTER_INDEX,
+                letter.id.to_string(),
+                letter.failed_at.timestamp_millis(),
+            )
+            .ignore()
+            .query_async::<()>(&mut conn)
+            .await
+            .map_err(|e| CexError::Redis(format!("dead letter push failed: {e}")))
+    }
+
+    /// Dead letters oldest first, skipping the first `offset`.
+    pub async fn list_dead_letters(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<DeadLetter>, CexError> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+        let mut conn = self.connection().await?;
+        let ids: Vec<String> = conn
+            .zrange(
+                DEAD_LETTER_INDEX,
+                offset as isize,
+                (offset + limit - 1) as isize,
+            )
+            .await
+            .map_err(|e| CexError::Redis(format!("dead letter list failed: {e}")))?;
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let raw: Vec<Option<String>> = redis_rs::cmd("HMGET")
+            .arg(DEAD_LETTER_ENTRIES)
+            .arg(&ids)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| CexError::Redis(format!("dead letter list failed: {e}")))?;
+        raw.into_iter().flatten().map(|s| from_json(&s)).collect()
+    }
+
+    pub async fn get_dead_letter(&self, id: Uuid) -> Result<Option<DeadLetter>, CexError> {
+        let mut conn = self.connection().await?;
+        let raw: Option<String> = conn
+            .hget(DEAD_LETTER_ENTRIES, id.to_string())
+            .await
+            .map_err(|e| CexError::Redis(format!("dead letter get failed: {e}")))?;
+        raw.map(|s| from_json(&s)).transpose()
+    }
+
+    /// Number of dead letters waiting for attention.
+    pub async fn dead_letter_depth(&self) -> Result<u64, CexError> {
+        let mut conn = self.connection().await?;
+        conn.zcard(DEAD_LETTER_INDEX)
+            .await
+            .map_err(|e| CexError::Redis(format!("dead letter depth failed: {e}")))
+    }
+
+    /// Removes a dead letter; returns whether it existed.
+    pub async fn discard_dead_letter(&self, id: Uuid) -> Result<bool, CexError> {
+        let mut conn = self.connection().await?;
+        let (removed, _): (u64, u64) = redis_rs::pipe()
+            .atomic()
+            .hdel(DEAD_LETTER_ENTRIES, id.to_string())
+            .zrem(DEAD_LETTER_INDEX, id.to_string())
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| CexError::Redis(format!("dead letter discard failed: {e}")))?;
+        Ok(removed > 0)
+    }
+
+    /// Puts the original payload back where it came from and drops the dead
+    /// letter, atomically. Returns `None` if there is no dead letter with that
+    /// id, including when a concurrent re-drive got to it first.
+    pub async fn redrive_dead_letter(&self, id: Uuid) -> Result<Option<DeadLetter>, CexError> {
+        let Some(letter) = self.get_dead_letter(id).await? else {
+            return Ok(None);
+        };
+        let (kind, target) = match &letter.origin {
+            DeadLetterOrigin::Queue(queue) => ("queue", queue),
+            DeadLetterOrigin::Stream(stream) => {
+                if std::str::from_utf8(&letter.payload).is_err() {
+                    return Err(CexError::Validation(
+                        "stream payload is not valid utf-8".to_string(),
+                    ));
+                }
+                ("stream", stream)
+            }
+        };
+        let mut conn = self.connection().await?;
+        let redriven: i64 = Script::new(REDRIVE_LUA)
+            .key(DEAD_LETTER_ENTRIES)
+            .key(DEAD_LETTER_INDEX)
+            .key(target)
+            .arg(id.to_string())
+            .arg(kind)
+            .arg(&letter.payload)
+            .arg(STREAM_EVENTS_MAX_LEN)
+            .arg(PAYLOAD_FIELD)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| CexError::Redis(format!("dead letter redrive failed: {e}")))?;
+        Ok((redriven == 1).then_some(letter))
+    }
+}
+
+mod base64_bytes {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        STANDARD.decode(encoded).map_err(serde::de::Error::custom)
+    }
+}
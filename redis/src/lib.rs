@@ -1,9 +1,17 @@
+pub mod dead_letter;
 pub mod manager;
+pub mod metrics;
 pub mod publisher;
 pub mod queues;
+pub mod rate_limit;
+pub mod streams;
 pub mod subscriber;
 
+pub use dead_letter::{DeadLetter, DeadLetterOrigin};
 pub use manager::RedisManager;
+pub use metrics::HandleLatency;
 pub use publisher::RedisPublisher;
-pub use queues::{CHANNEL_EVENTS, QUEUE_ORDER_CANCEL, QUEUE_ORDER_NEW};
+pub use queues::{CHANNEL_EVENTS, QUEUE_ORDER_CANCEL, QUEUE_ORDER_NEW, STREAM_EVENTS};
+pub use rate_limit::RateLimitDecision;
+pub use streams::StreamEntry;
 pub use subscriber::RedisSubscriber;
@@ -16,13 +16,23 @@ impl RedisManager {
         Ok(Self { client })
     }
 
-    async fn connection(&self) -> Result<MultiplexedConnection, CexError> {
+    pub(crate) async fn connection(&self) -> Result<MultiplexedConnection, CexError> {
         self.client
             .get_multiplexed_async_connection()
             .await
             .map_err(|e| CexError::Redis(format!("connection error: {e}")))
     }
 
+    /// Round-trips a `PING`, useful to probe availability before doing real work.
+    pub async fn ping(&self) -> Result<(), CexError> {
+        let mut conn = self.connection().await?;
+        redis_rs::cmd("PING")
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| CexError::Redis(format!("ping failed: {e}")))
+            .map(|_: String| ())
+    }
+
     pub async fn push(&self, queue: &str, payload: &str) -> Result<(), CexError> {
         let mut conn = self.connection().await?;
         conn.rpush(queue, payload)
@@ -31,6 +41,29 @@ impl RedisManager {
             .map(|_: i64| ())
     }
 
+    pub async fn push_bytes(&self, queue: &str, payload: &[u8]) -> Result<(), CexError> {
+        let mut conn = self.connection().await?;
+        conn.rpush(queue, payload)
+            .await
+            .map_err(|e| CexError::Redis(format!("rpush failed: {e}")))
+            .map(|_: i64| ())
+    }
+
+    /// `SET key 1 NX PX ttl_ms`; returns true when the key did not exist yet.
+    pub async fn set_nx(&self, key: &str, ttl_ms: u64) -> Result<bool, CexError> {
+        let mut conn = self.connection().await?;
+        let reply: Option<String> = redis_rs::cmd("SET")
+            .arg(key)
+            .arg(1)
+            .arg("NX")
+            .arg("PX")
+            .arg(ttl_ms)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| CexError::Redis(format!("set nx failed: {e}")))?;
+        Ok(reply.is_some())
+    }
+
     pub async fn pop(&self, queue: &str, timeout_secs: u64) -> Result<Option<String>, CexError> {
         let mut conn = self.connection().await?;
         let result: Option<(String, String)> = conn
@@ -40,6 +73,19 @@ impl RedisManager {
         Ok(result.map(|(_, payload)| payload))
     }
 
+    pub async fn pop_bytes(
+        &self,
+        queue: &str,
+        timeout_secs: u64,
+    ) -> Result<Option<Vec<u8>>, CexError> {
+        let mut conn = self.connection().await?;
+        let result: Option<(String, Vec<u8>)> = conn
+            .brpop(queue, timeout_secs as f64)
+            .await
+            .map_err(|e| CexError::Redis(format!("brpop failed: {e}")))?;
+        Ok(result.map(|(_, payload)| payload))
+    }
+
     pub async fn publish(&self, channel: &str, payload: &str) -> Result<(), CexError> {
         let mut conn = self.connection().await?;
         conn.publish(channel, payload)
@@ -68,20 +114,20 @@ impl RedisManager {
         self.subscribe(CHANNEL_EVENTS).await
     }
 
-    pub async fn push_new_order(&self, payload: &str) -> Result<(), CexError> {
-        self.push(QUEUE_ORDER_NEW, payload).await
+    pub async fn push_new_order(&self, payload: &[u8]) -> Result<(), CexError> {
+        self.push_bytes(QUEUE_ORDER_NEW, payload).await
     }
 
-    pub async fn push_cancel_order(&self, payload: &str) -> Result<(), CexError> {
-        self.push(QUEUE_ORDER_CANCEL, payload).await
+    pub async fn push_cancel_order(&self, payload: &[u8]) -> Result<(), CexError> {
+        self.push_bytes(QUEUE_ORDER_CANCEL, payload).await
     }
 
-    pub async fn pop_new_order(&self, timeout_secs: u64) -> Result<Option<String>, CexError> {
-        self.pop(QUEUE_ORDER_NEW, timeout_secs).await
+    pub async fn pop_new_order(&self, timeout_secs: u64) -> Result<Option<Vec<u8>>, CexError> {
+        self.pop_bytes(QUEUE_ORDER_NEW, timeout_secs).await
     }
 
-    pub async fn pop_cancel_order(&self, timeout_secs: u64) -> Result<Option<String>, CexError> {
-        self.pop(QUEUE_ORDER_CANCEL, timeout_secs).await
+    pub async fn pop_cancel_order(&self, timeout_secs: u64) -> Result<Option<Vec<u8>>, CexError> {
+        self.pop_bytes(QUEUE_ORDER_CANCEL, timeout_secs).await
     }
 }
 
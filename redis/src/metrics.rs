@@ -0,0 +1,41 @@
+use redis_rs::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use shared::{from_json, to_json, CexError};
+
+use crate::manager::RedisManager;
+use crate::queues::ENGINE_LATENCY;
+
+/// How long the engine took to handle one kind of message since it started,
+/// in microseconds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HandleLatency {
+    /// Message type, such as `order_new` or `cancel`.
+    pub kind: String,
+    pub count: u64,
+    pub mean_us: f64,
+    pub p50_us: u64,
+    pub p90_us: u64,
+    pub p99_us: u64,
+    pub p999_us: u64,
+    pub max_us: u64,
+}
+
+impl RedisManager {
+    /// Replaces the engine's latency summary.
+    pub async fn store_engine_latency(&self, latency: &[HandleLatency]) -> Result<(), CexError> {
+        let mut conn = self.connection().await?;
+        conn.set(ENGINE_LATENCY, to_json(&latency)?)
+            .await
+            .map_err(|e| CexError::Redis(format!("latency store failed: {e}")))
+    }
+
+    /// The engine's latest latency summary; empty until it has published one.
+    pub async fn engine_latency(&self) -> Result<Vec<HandleLatency>, CexError> {
+        let mut conn = self.connection().await?;
+        let raw: Option<String> = conn
+            .get(ENGINE_LATENCY)
+            .await
+            .map_err(|e| CexError::Redis(format!("latency load failed: {e}")))?;
+        Ok(raw.map(|s| from_json(&s)).transpose()?.unwrap_or_default())
+    }
+}
@@ -16,11 +16,11 @@ impl<'a> RedisPublisher<'a> {
         self.manager.publish(CHANNEL_EVENTS, payload).await
     }
 
-    pub async fn enqueue_new_order(&self, payload: &str) -> Result<(), CexError> {
-        self.manager.push(QUEUE_ORDER_NEW, payload).await
+    pub async fn enqueue_new_order(&self, payload: &[u8]) -> Result<(), CexError> {
+        self.manager.push_bytes(QUEUE_ORDER_NEW, payload).await
     }
 
-    pub async fn enqueue_cancel_order(&self, payload: &str) -> Result<(), CexError> {
-        self.manager.push(QUEUE_ORDER_CANCEL, payload).await
+    pub async fn enqueue_cancel_order(&self, payload: &[u8]) -> Result<(), CexError> {
+        self.manager.push_bytes(QUEUE_ORDER_CANCEL, payload).await
     }
 }
@@ -2,3 +2,24 @@ pub const QUEUE_ORDER_NEW: &str = "orders.new";
 pub const QUEUE_ORDER_CANCEL: &str = "orders.cancel";
 
 pub const CHANNEL_EVENTS: &str = "events";
+
+/// Durable copy of everything published on `CHANNEL_EVENTS`, for consumers
+/// that must not miss events across restarts.
+pub const STREAM_EVENTS: &str = "events.stream";
+/// Approximate cap on `STREAM_EVENTS` entries retained by Redis.
+pub const STREAM_EVENTS_MAX_LEN: usize = 1_000_000;
+
+pub const MARKET_STREAM_PREFIX: &str = "events.market.";
+
+/// Per-market event log whose entry ids are `{sequence}-0`, so a consumer that
+/// spots a gap can fetch exactly the missing sequence numbers.
+pub fn market_stream(market: &str) -> String {
+    format!("{MARKET_STREAM_PREFIX}{market}")
+}
+
+/// Hash of dead letters by id, plus a sorted set of ids by failure time.
+pub const DEAD_LETTER_ENTRIES: &str = "dlq:entries";
+pub const DEAD_LETTER_INDEX: &str = "dlq:index";
+
+/// Latest engine latency summary, see [`crate::HandleLatency`].
+pub const ENGINE_LATENCY: &str = "metrics:engine:latency";
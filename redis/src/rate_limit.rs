@@ -0,0 +1,70 @@
+use redis_rs::Script;
+use shared::CexError;
+
+use crate::manager::RedisManager;
+
+/// Atomically refills and debits a token bucket stored as a hash. Uses the
+/// Redis clock so every API instance sharing the bucket agrees on time.
+const TOKEN_BUCKET_LUA: &str = r#"
+local capacity = tonumber(ARGV[1])
+local refill_per_ms = tonumber(ARGV[2])
+local cost = tonumber(ARGV[3])
+local t = redis.call('TIME')
+local now = tonumber(t[1]) * 1000 + math.floor(tonumber(t[2]) / 1000)
+local state = redis.call('HMGET', KEYS[1], 'tokens', 'ts')
+local tokens = tonumber(state[1]) or capacity
+local ts = tonumber(state[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - ts) * refill_per_ms)
+local allowed = 0
+local retry_after = 0
+if tokens >= cost then
+    tokens = tokens - cost
+    allowed = 1
+else
+    retry_after = math.ceil((cost - tokens) / refill_per_ms)
+end
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'ts', now)
+redis.call('PEXPIRE', KEYS[1], math.ceil(capacity / refill_per_ms) + 1000)
+return {allowed, math.floor(tokens), retry_after}
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    pub retry_after_ms: u64,
+}
+
+impl RedisManager {
+    /// Takes `cost` tokens from the bucket at `key`, which holds at most
+    /// `capacity` tokens and regains `refill_per_sec` tokens every second.
+    pub async fn take_tokens(
+        &self,
+        key: &str,
+        capacity: u32,
+        refill_per_sec: f64,
+        cost: u32,
+    ) -> Result<RateLimitDecision, CexError> {
+        if refill_per_sec <= 0.0 {
+            return Err(CexError::Validation(
+                "refill rate must be positive".to_string(),
+            ));
+        }
+        let mut conn = self.connection().await?;
+        let (allowed, remaining, retry_after_ms): (i64, i64, i64) = Script::new(TOKEN_BUCKET_LUA)
+            .key(key)
+            .arg(capacity)
+            .arg(refill_per_sec / 1000.0)
+            .arg(cost)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| CexError::Redis(format!("rate limit script failed: {e}")))?;
+        Ok(RateLimitDecision {
+            allowed: allowed == 1,
+            limit: capacity,
+            remaining: remaining.max(0) as u32,
+            retry_after_ms: retry_after_ms.max(0) as u64,
+        })
+    }
+}
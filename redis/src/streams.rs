@@ -0,0 +1,156 @@
+use redis_rs::streams::{
+    StreamId, StreamMaxlen, StreamRangeReply, StreamReadOptions, StreamReadReply,
+};
+use redis_rs::AsyncCommands;
+use shared::CexError;
+
+use crate::manager::RedisManager;
+use crate::queues::{market_stream, MARKET_STREAM_PREFIX, STREAM_EVENTS_MAX_LEN};
+
+pub(crate) const PAYLOAD_FIELD: &str = "payload";
+
+/// Stream id that reads a stream from its first entry.
+pub const STREAM_START: &str = "0-0";
+
+/// One entry read from a stream.
+#[derive(Debug, Clone)]
+pub struct StreamEntry {
+    pub id: String,
+    pub payload: String,
+}
+
+impl RedisManager {
+    /// `XADD stream MAXLEN ~ max_len * payload <payload>`; returns the entry id.
+    pub async fn append_stream(
+        &self,
+        stream: &str,
+        payload: &str,
+        max_len: usize,
+    ) -> Result<String, CexError> {
+        let mut conn = self.connection().await?;
+        conn.xadd_maxlen(
+            stream,
+            StreamMaxlen::Approx(max_len),
+            "*",
+            &[(PAYLOAD_FIELD, payload)],
+        )
+        .await
+        .map_err(|e| CexError::Redis(format!("xadd failed: {e}")))
+    }
+
+    /// Reads up to `count` entries after `after`, waiting at most `block_ms`
+    /// for new ones. An empty result means the wait timed out.
+    pub async fn read_stream(
+        &self,
+        stream: &str,
+        after: &str,
+        count: usize,
+        block_ms: usize,
+    ) -> Result<Vec<StreamEntry>, CexError> {
+        let mut conn = self.connection().await?;
+        let opts = StreamReadOptions::default().count(count).block(block_ms);
+        let reply: Option<StreamReadReply> =
+            conn.xread_options(&[stream], &[after], &opts)
+                .await
+                .map_err(|e| CexError::Redis(format!("xread failed: {e}")))?;
+        let entries = reply
+            .into_iter()
+            .flat_map(|r| r.keys)
+            .flat_map(|k| k.ids)
+            .map(StreamEntry::from)
+            .collect();
+        Ok(entries)
+    }
+
+    /// Appends an event to its market's log under id `{sequence}-0`. Redis
+    /// rejects a sequence that is not greater than the last one stored.
+    pub async fn append_market_event(
+        &self,
+        market: &str,
+        sequence: u64,
+        payload: &str,
+    ) -> Result<(), CexError> {
+        let mut conn = self.connection().await?;
+        conn.xadd_maxlen::<_, _, _, _, String>(
+            market_stream(market),
+            StreamMaxlen::Approx(STREAM_EVENTS_MAX_LEN),
+            format!("{sequence}-0"),
+            &[(PAYLOAD_FIELD, payload)],
+        )
+        .await
+        .map_err(|e| CexError::Redis(format!("xadd failed: {e}")))?;
+        Ok(())
+    }
+
+    /// Events of `market` with sequence numbers in `from..=to`, in order.
+    pub async fn read_market_range(
+        &self,
+        market: &str,
+        from: u64,
+        to: u64,
+    ) -> Result<Vec<StreamEntry>, CexError> {
+        let mut conn = self.connection().await?;
+        let reply: StreamRangeReply = conn
+            .xrange(
+                market_stream(market),
+                format!("{from}-0"),
+                format!("{to}-0"),
+            )
+            .await
+            .map_err(|e| CexError::Redis(format!("xrange failed: {e}")))?;
+        Ok(reply.ids.into_iter().map(StreamEntry::from).collect())
+    }
+
+    /// The newest `count` events of `market`, newest first.
+    pub async fn read_market_tail(
+        &self,
+        market: &str,
+        count: usize,
+    ) -> Result<Vec<StreamEntry>, CexError> {
+        let mut conn = self.connection().await?;
+        let reply: StreamRangeReply = conn
+            .xrevrange_count(market_stream(market), "+", "-", count)
+            .await
+            .map_err(|e| CexError::Redis(format!("xrevrange failed: {e}")))?;
+        Ok(reply.ids.into_iter().map(StreamEntry::from).collect())
+    }
+
+    /// Every market that has an event log, in no particular order.
+    pub async fn markets_with_logs(&self) -> Result<Vec<String>, CexError> {
+        let mut conn = self.connection().await?;
+        let mut keys = conn
+            .scan_match::<_, String>(format!("{MARKET_STREAM_PREFIX}*"))
+            .await
+            .map_err(|e| CexError::Redis(format!("scan failed: {e}")))?;
+        let mut markets = Vec::new();
+        while let Some(key) = keys.next_item().await {
+            if let Some(market) = key.strip_prefix(MARKET_STREAM_PREFIX) {
+                markets.push(market.to_string());
+            }
+        }
+        Ok(markets)
+    }
+
+    /// Highest sequence number stored for `market`, if any.
+    pub async fn last_market_sequence(&self, market: &str) -> Result<Option<u64>, CexError> {
+        let mut conn = self.connection().await?;
+        let reply: StreamRangeReply = conn
+            .xrevrange_count(market_stream(market), "+", "-", 1)
+            .await
+            .map_err(|e| CexError::Redis(format!("xrevrange failed: {e}")))?;
+        Ok(reply
+            .ids
+            .first()
+            .and_then(|id| id.id.split('-').next())
+            .and_then(|seq| seq.parse().ok()))
+    }
+}
+
+impl From<StreamId> for StreamEntry {
+    fn from(id: StreamId) -> Self {
+        Self {
+            payload: id.get(PAYLOAD_FIELD).unwrap_or_default(),
+            id: id.id,
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use chrono::{Duration, Utc};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+
+use crate::error::CexError;
+use crate::types::UserId;
+
+pub const DEFAULT_ACCESS_TTL_SECS: i64 = 15 * 60;
+pub const DEFAULT_REFRESH_TTL_SECS: i64 = 7 * 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenKind {
+    Access,
+    Refresh,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: UserId,
+    pub kind: TokenKind,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+}
+
+/// HS256 signer/verifier shared by every service that accepts user tokens.
+pub struct JwtKeys {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    access_ttl: Duration,
+    refresh_ttl: Duration,
+}
+
+impl JwtKeys {
+    pub fn new(secret: &[u8]) -> Self {
+        Self::with_ttls(secret, DEFAULT_ACCESS_TTL_SECS, DEFAULT_REFRESH_TTL_SECS)
+    }
+
+    pub fn with_ttls(secret: &[u8], access_ttl_secs: i64, refresh_ttl_secs: i64) -> Self {
+        Self {
+            encoding: EncodingKey::from_secret(secret),
+            decoding: DecodingKey::from_secret(secret),
+            access_ttl: Duration::seconds(access_ttl_secs),
+            refresh_ttl: Duration::seconds(refresh_ttl_secs),
+        }
+    }
+
+    pub fn issue(&self, user_id: UserId, kind: TokenKind) -> Result<String, CexError> {
+        let now = Utc::now();
+        let ttl = match kind {
+            TokenKind::Access => self.access_ttl,
+            TokenKind::Refresh => self.refresh_ttl,
+        };
+        let claims = Claims {
+            sub: user_id,
+            kind,
+            iat: now.timestamp(),
+            exp: (now + ttl).timestamp(),
+        };
+        encode(&Header::new(Algorithm::HS256), &claims, &self.encoding)
+            .map_err(|e| CexError::Internal(format!("token encoding failed: {e}")))
+    }
+
+    pub fn issue_pair(&self, user_id: UserId) -> Result<TokenPair, CexError> {
+        Ok(TokenPair {
+            access_token: self.issue(user_id, TokenKind::Access)?,
+            refresh_token: self.issue(user_id, TokenKind::Refresh)?,
+            token_type: "Bearer".to_string(),
+            expires_in: self.access_ttl.num_seconds(),
+        })
+    }
+
+    /// Verifies signature and expiry, and that the token is of the expected kind
+    /// so refresh tokens can't be used to call the API and vice versa.
+    pub fn verify(&self, token: &str, expected: TokenKind) -> Result<Claims, CexError> {
+        let data = decode::<Claims>(token, &self.decoding, &Validation::new(Algorithm::HS256))
+            .map_err(|e| CexError::Unauthorized(format!("invalid token: {e}")))?;
+        if data.claims.kind != expected {
+            return Err(CexError::Unauthorized("wrong token kind".to_string()));
+        }
+        Ok(data.claims)
+    }
+}
@@ -8,9 +8,18 @@ pub enum CexError {
     #[error("serialization error: {0}")]
     Serde(#[from] serde_json::Error),
 
+    #[error("codec error: {0}")]
+    Codec(String),
+
     #[error("redis error: {0}")]
     Redis(String),
 
+    #[error("unauthorized: {0}")]
+    Unauthorized(String),
+
+    #[error("forbidden: {0}")]
+    Forbidden(String),
+
     #[error("not found: {0}")]
     NotFound(String),
 
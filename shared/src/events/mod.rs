@@ -0,0 +1,165 @@
+pub mod sequence;
+pub mod upcast;
+
+use crate::error::CexError;
+use crate::types::{
+    AuctionIndicative, CancelAll, CancelAllSummary, CancelOrder, CancelRejected, Candle,
+    CountdownCancel, MarketHalt, MarketResume, MarketStateChange, MarketStateRequest, NewOrder,
+    OrderUpdate, Trade, UserId,
+};
+use crate::utils::codec::Codec;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub use sequence::{SequenceCheck, SequenceTracker};
+pub use upcast::ENVELOPE_VERSION;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data")]
+pub enum Event {
+    OrderNew(NewOrder),
+    /// Cancel announced by engines that predate [`Event::OrderUpdate`]. The
+    /// engine dead-letters it on its queues, as it names no user.
+    OrderCancel {
+        order_id: Uuid,
+    },
+    /// A user asks the engine to cancel one of their orders.
+    CancelRequest(CancelOrder),
+    CancelRejected(CancelRejected),
+    /// Kill switch: cancel all of a user's orders matching the filters.
+    CancelAll(CancelAll),
+    CancelAllDone(CancelAllSummary),
+    CountdownCancel(CountdownCancel),
+    TradeExecuted(Trade),
+    /// An order was accepted, filled, rejected, cancelled or expired.
+    OrderUpdate(OrderUpdate),
+    DepthSnapshot {
+        pair: String,
+        bids: Vec<(Decimal, Decimal)>,
+        asks: Vec<(Decimal, Decimal)>,
+        ts: DateTime<Utc>,
+    },
+    /// Live update of an OHLCV bar, published by the candle aggregator.
+    Candle(Candle),
+    MarketHalted(MarketHalt),
+    MarketResumed(MarketResume),
+    /// Operator request to change a market's trading state.
+    SetMarketState(MarketStateRequest),
+    MarketStateChanged(MarketStateChange),
+    AuctionIndicative(AuctionIndicative),
+}
+
+/// Who may see an event on a client-facing feed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Audience {
+    /// Market data.
+    Public,
+    /// Only the user whose orders it is about.
+    User(UserId),
+    /// Requests between services; never forwarded to clients.
+    Internal,
+}
+
+impl Event {
+    pub fn audience(&self) -> Audience {
+        match self {
+            Event::OrderNew(order) => Audience::User(order.user_id),
+            Event::OrderUpdate(update) => Audience::User(update.user_id),
+            Event::CancelRejected(rejected) => Audience::User(rejected.user_id),
+            Event::CancelAllDone(summary) => Audience::User(summary.user_id),
+            Event::OrderCancel { .. }
+            | Event::CancelRequest(_)
+            | Event::CancelAll(_)
+            | Event::CountdownCancel(_)
+            | Event::SetMarketState(_) => Audience::Internal,
+            Event::TradeExecuted(_)
+            | Event::DepthSnapshot { .. }
+            | Event::Candle(_)
+            | Event::MarketHalted(_)
+            | Event::MarketResumed(_)
+            | Event::MarketStateChanged(_)
+            | Event::AuctionIndicative(_) => Audience::Public,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope {
+    /// Schema version, see [`upcast`]. Always [`ENVELOPE_VERSION`] once decoded.
+    pub version: u8,
+    /// Unique per event; consumers use it to discard redeliveries.
+    pub event_id: Uuid,
+    pub source: String,
+    /// Market whose event log this belongs to; `None` for events that are
+    /// not sequenced, such as candle updates.
+    pub market: Option<String>,
+    /// Position in the market's log, starting at 1 with no gaps.
+    pub sequence: Option<u64>,
+    pub event: Event,
+    pub emitted_at: DateTime<Utc>,
+}
+
+impl Envelope {
+    pub fn new(source: impl Into<String>, event: Event) -> Self {
+        Self {
+            version: ENVELOPE_VERSION,
+            event_id: Uuid::new_v4(),
+            source: source.into(),
+            market: None,
+            sequence: None,
+            event,
+            emitted_at: Utc::now(),
+        }
+    }
+
+    /// An envelope at position `sequence` of `market`'s event log.
+    pub fn sequenced(
+        source: impl Into<String>,
+        market: impl Into<String>,
+        sequence: u64,
+        event: Event,
+    ) -> Self {
+        Self {
+            market: Some(market.into()),
+            sequence: Some(sequence),
+            ..Self::new(source, event)
+        }
+    }
+
+    /// Market and sequence number, if this event is part of a market's log.
+    pub fn position(&self) -> Option<(&str, u64)> {
+        Some((self.market.as_deref()?, self.sequence?))
+    }
+
+    pub fn encode(&self, codec: Codec) -> Result<Vec<u8>, CexError> {
+        codec.encode(self)
+    }
+
+    /// Decodes an envelope in whichever codec it was written with, upcasting
+    /// older schema versions and rejecting ones this build does not know.
+    pub fn decode(bytes: &[u8]) -> Result<Self, CexError> {
+        let codec = Codec::detect(bytes);
+        let VersionProbe { version } = codec.decode(bytes)?;
+        upcast::check_version(version)?;
+        if version == ENVELOPE_VERSION {
+            return codec.decode(bytes);
+        }
+        // Upcasters work on the JSON form. MessagePack is only used on live
+        // queues, so drain those before rolling out a new version.
+        if codec != Codec::Json {
+            return Err(CexError::Validation(format!(
+                "{codec} envelopes must be version {ENVELOPE_VERSION}, got {version}"
+            )));
+        }
+        let value = serde_json::from_slice(bytes)?;
+        let value = upcast::upcast(value, version, bytes)?;
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+#[derive(Deserialize)]
+struct VersionProbe {
+    version: u8,
+}
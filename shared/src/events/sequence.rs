@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+/// How a sequence number relates to what a consumer has already seen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SequenceCheck {
+    /// First event seen for the market; nothing to compare against.
+    First,
+    /// Exactly the next expected number.
+    Next,
+    /// Already seen; drop it.
+    Duplicate,
+    /// Numbers in `missing` were skipped and should be fetched before this one.
+    Gap { missing: RangeInclusive<u64> },
+    /// Numbering started over at 1 after `previous`, as when the engine's
+    /// logs were lost; the tracker follows the new numbering.
+    Reset { previous: u64 },
+}
+
+/// Last sequence number seen per market, for consumers of the event log.
+#[derive(Debug, Default, Clone)]
+pub struct SequenceTracker {
+    last: HashMap<String, u64>,
+}
+
+impl SequenceTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Classifies `sequence` and, unless it is a duplicate, records it as the
+    /// latest seen for `market`.
+    pub fn check(&mut self, market: &str, sequence: u64) -> SequenceCheck {
+        let Some(last) = self.last.get_mut(market) else {
+            self.last.insert(market.to_string(), sequence);
+            return SequenceCheck::First;
+        };
+        if sequence == 1 && *last > 1 {
+            let previous = *last;
+            *last = sequence;
+            return SequenceCheck::Reset { previous };
+        }
+        if sequence <= *last {
+            return SequenceCheck::Duplicate;
+        }
+        let expected = *last + 1;
+        *last = sequence;
+        if sequence == expected {
+            SequenceCheck::Next
+        } else {
+            SequenceCheck::Gap {
+                missing: expected..=sequence - 1,
+            }
+        }
+    }
+
+    pub fn last(&self, market: &str) -> Option<u64> {
+        self.last.get(market).copied()
+    }
+
+    /// Records `sequence` as the latest seen for `market` without checking
+    /// it, e.g. when starting from what the market's log already holds.
+    pub fn set(&mut self, market: &str, sequence: u64) {
+        self.last.insert(market.to_string(), sequence);
+    }
+}
@@ -0,0 +1,74 @@
+//! Upgrades envelopes written by older releases to the current schema.
+//!
+//! Each upcaster rewrites the JSON form of an envelope from version `n` to
+//! `n + 1`; decoding an old payload runs every step from its version up to
+//! [`ENVELOPE_VERSION`]. When a change to [`Event`](super::Event) or the types
+//! it carries alters the wire format, bump the version, add a step here and
+//! check in golden files for the new version under `shared/tests/golden`.
+
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::error::CexError;
+
+/// Schema version written by this build.
+pub const ENVELOPE_VERSION: u8 = 3;
+
+/// Oldest version this build can still read.
+pub const MIN_ENVELOPE_VERSION: u8 = 1;
+
+/// Namespace for ids derived from version 1 payloads, which had no `event_id`.
+const V1_EVENT_ID_NAMESPACE: Uuid = Uuid::from_u128(0x6a1c_4f0e_93d2_4b57_8e1f_2c0a_5d7b_9e31);
+
+type Upcaster = fn(&mut Value, &[u8]) -> Result<(), CexError>;
+
+/// `UPCASTERS[i]` upgrades version `MIN_ENVELOPE_VERSION + i` by one step.
+const UPCASTERS: [Upcaster; (ENVELOPE_VERSION - MIN_ENVELOPE_VERSION) as usize] =
+    [v1_to_v2, v2_to_v3];
+
+/// Rejects versions outside what this build understands.
+pub fn check_version(version: u8) -> Result<(), CexError> {
+    if (MIN_ENVELOPE_VERSION..=ENVELOPE_VERSION).contains(&version) {
+        Ok(())
+    } else {
+        Err(CexError::Validation(format!(
+            "unsupported envelope version {version}, expected {MIN_ENVELOPE_VERSION}..={ENVELOPE_VERSION}"
+        )))
+    }
+}
+
+/// Runs the upcasters needed to bring `value` from `version` to the current
+/// schema. `raw` is the payload as received, for steps that derive data from it.
+pub fn upcast(mut value: Value, version: u8, raw: &[u8]) -> Result<Value, CexError> {
+    check_version(version)?;
+    let first = (version - MIN_ENVELOPE_VERSION) as usize;
+    for step in &UPCASTERS[first..] {
+        step(&mut value, raw)?;
+    }
+    Ok(value)
+}
+
+/// v2 added `event_id`. Old events get an id derived from their bytes so the
+/// same payload maps to the same id however often it is read.
+fn v1_to_v2(value: &mut Value, raw: &[u8]) -> Result<(), CexError> {
+    let obj = as_object(value)?;
+    obj.entry("event_id")
+        .or_insert_with(|| Value::String(Uuid::new_v5(&V1_EVENT_ID_NAMESPACE, raw).to_string()));
+    obj.insert("version".to_string(), Value::from(2));
+    Ok(())
+}
+
+/// v3 added per-market sequencing. Earlier events predate it and have none.
+fn v2_to_v3(value: &mut Value, _raw: &[u8]) -> Result<(), CexError> {
+    let obj = as_object(value)?;
+    obj.entry("market").or_insert(Value::Null);
+    obj.entry("sequence").or_insert(Value::Null);
+    obj.insert("version".to_string(), Value::from(3));
+    Ok(())
+}
+
+fn as_object(value: &mut Value) -> Result<&mut serde_json::Map<String, Value>, CexError> {
+    value
+        .as_object_mut()
+        .ok_or_else(|| CexError::Validation("envelope is not an object".to_string()))
+}
@@ -1,3 +1,4 @@
+pub mod auth;
 pub mod constants;
 pub mod error;
 pub mod events;
@@ -5,6 +6,7 @@ pub mod types;
 pub mod utils;
 
 pub use error::CexError;
-pub use events::{Envelope, Event};
+pub use events::{Audience, Envelope, Event, SequenceCheck, SequenceTracker, ENVELOPE_VERSION};
 pub use types::*;
+pub use utils::codec::Codec;
 pub use utils::json::{from_json, to_json};
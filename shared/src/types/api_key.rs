@@ -0,0 +1,57 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::CexError;
+use crate::types::UserId;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiScope {
+    Read,
+    Trade,
+    Withdraw,
+}
+
+impl ApiScope {
+    pub const ALL: [ApiScope; 3] = [ApiScope::Read, ApiScope::Trade, ApiScope::Withdraw];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiScope::Read => "read",
+            ApiScope::Trade => "trade",
+            ApiScope::Withdraw => "withdraw",
+        }
+    }
+}
+
+impl FromStr for ApiScope {
+    type Err = CexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(ApiScope::Read),
+            "trade" => Ok(ApiScope::Trade),
+            "withdraw" => Ok(ApiScope::Withdraw),
+            other => Err(CexError::Validation(format!("unknown scope: {other}"))),
+        }
+    }
+}
+
+/// A programmatic credential. Only a hash of the public key is persisted; the
+/// signing secret is derived from it server-side and shown once at creation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub key_id: Uuid,
+    pub user_id: UserId,
+    pub label: String,
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    pub scopes: Vec<ApiScope>,
+    /// When set, requests are only accepted from these addresses.
+    pub allowed_ips: Option<Vec<String>>,
+    pub created_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
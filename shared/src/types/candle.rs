@@ -0,0 +1,93 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, TimeZone, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::error::CexError;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum CandleInterval {
+    #[serde(rename = "1m")]
+    OneMinute,
+    #[serde(rename = "5m")]
+    FiveMinutes,
+    #[serde(rename = "15m")]
+    FifteenMinutes,
+    #[serde(rename = "1h")]
+    OneHour,
+    #[serde(rename = "1d")]
+    OneDay,
+}
+
+impl CandleInterval {
+    pub const ALL: [CandleInterval; 5] = [
+        CandleInterval::OneMinute,
+        CandleInterval::FiveMinutes,
+        CandleInterval::FifteenMinutes,
+        CandleInterval::OneHour,
+        CandleInterval::OneDay,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CandleInterval::OneMinute => "1m",
+            CandleInterval::FiveMinutes => "5m",
+            CandleInterval::FifteenMinutes => "15m",
+            CandleInterval::OneHour => "1h",
+            CandleInterval::OneDay => "1d",
+        }
+    }
+
+    pub fn seconds(&self) -> i64 {
+        match self {
+            CandleInterval::OneMinute => 60,
+            CandleInterval::FiveMinutes => 300,
+            CandleInterval::FifteenMinutes => 900,
+            CandleInterval::OneHour => 3_600,
+            CandleInterval::OneDay => 86_400,
+        }
+    }
+
+    /// Start of the bucket containing `ts`, aligned to the Unix epoch (UTC).
+    pub fn open_time(&self, ts: DateTime<Utc>) -> DateTime<Utc> {
+        let secs = ts.timestamp();
+        let start = secs - secs.rem_euclid(self.seconds());
+        Utc.timestamp_opt(start, 0).single().unwrap_or(ts)
+    }
+}
+
+impl FromStr for CandleInterval {
+    type Err = CexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CandleInterval::ALL
+            .into_iter()
+            .find(|i| i.as_str() == s)
+            .ok_or_else(|| CexError::Validation(format!("unknown interval: {s}")))
+    }
+}
+
+/// OHLCV bar for one pair and interval. `first_trade_at`/`last_trade_at`
+/// decide which trade sets `open`/`close` when trades arrive out of order.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Candle {
+    pub pair: String,
+    pub interval: CandleInterval,
+    pub open_time: DateTime<Utc>,
+    #[serde(with = "crate::utils::decimal")]
+    pub open: Decimal,
+    #[serde(with = "crate::utils::decimal")]
+    pub high: Decimal,
+    #[serde(with = "crate::utils::decimal")]
+    pub low: Decimal,
+    #[serde(with = "crate::utils::decimal")]
+    pub close: Decimal,
+    #[serde(with = "crate::utils::decimal")]
+    pub volume: Decimal,
+    #[serde(with = "crate::utils::decimal")]
+    pub quote_volume: Decimal,
+    pub trade_count: i64,
+    pub first_trade_at: DateTime<Utc>,
+    pub last_trade_at: DateTime<Utc>,
+}
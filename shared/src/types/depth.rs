@@ -15,3 +15,12 @@ pub struct DepthSnapshot {
     pub asks: Vec<DepthLevel>,
     pub timestamp: DateTime<Utc>,
 }
+
+impl DepthSnapshot {
+    /// Keeps only the best `limit` levels on each side.
+    pub fn top(mut self, limit: usize) -> Self {
+        self.bids.truncate(limit);
+        self.asks.truncate(limit);
+        self
+    }
+}
@@ -1,10 +1,83 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Market {
+/// Trading phase of a market.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MarketState {
+    /// Orders are collected without matching and without an indicative price.
+    PreOpen,
+    /// Call auction: orders are collected without matching and uncross at a
+    /// single price when continuous trading starts.
+    Auction,
+    #[default]
+    Continuous,
+    /// No orders or cancels are accepted.
+    Halted,
+    /// Cancels only.
+    Closed,
+}
+
+impl MarketState {
+    /// Whether orders rest without matching.
+    pub fn is_collecting(&self) -> bool {
+        matches!(self, MarketState::PreOpen | MarketState::Auction)
+    }
+}
+
+/// Asks the engine to move a market to another state.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MarketStateRequest {
+    pub pair: String,
+    pub state: MarketState,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MarketStateChange {
+    pub pair: String,
+    pub from: MarketState,
+    pub to: MarketState,
+    pub ts: DateTime<Utc>,
+}
+
+/// Where the auction would uncross if it ended now; `price` is `None` while
+/// the book does not cross.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AuctionIndicative {
+    pub pair: String,
+    #[serde(
+        default,
+        with = "crate::utils::decimal::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub price: Option<Decimal>,
+    #[serde(with = "crate::utils::decimal")]
+    pub volume: Decimal,
+    pub ts: DateTime<Utc>,
+}
+
+/// Why trading in a market was halted.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HaltReason {
+    /// The price moved further than the market's circuit breaker allows.
+    Volatility,
+    Manual,
+}
+
+/// Published when a market stops accepting orders and cancels.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MarketHalt {
+    pub pair: String,
+    pub reason: HaltReason,
+    /// When trading resumes on its own; `None` until resumed by hand.
+    pub resume_at: Option<DateTime<Utc>>,
+    pub ts: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MarketResume {
     pub pair: String,
-    pub base_asset: String,
-    pub quote_asset: String,
-    pub tick_size: String,
-    pub lot_size: String,
+    pub ts: DateTime<Utc>,
 }
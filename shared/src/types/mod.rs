@@ -1,9 +1,15 @@
+pub mod api_key;
+pub mod candle;
 pub mod depth;
+pub mod market;
 pub mod order;
 pub mod trade;
 pub mod user;
 
+pub use api_key::*;
+pub use candle::*;
 pub use depth::*;
+pub use market::*;
 pub use order::*;
 pub use trade::*;
 pub use user::*;
@@ -1,8 +1,12 @@
+use std::str::FromStr;
+
 use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::error::CexError;
+
 pub type OrderId = Uuid;
 pub type UserId = Uuid;
 
@@ -20,6 +24,19 @@ pub enum OrderType {
     Market,
 }
 
+impl OrderType {
+    /// Rejects a limit order without a positive price; shared by every
+    /// entry point that accepts orders.
+    pub fn check_price(self, price: Decimal) -> Result<(), CexError> {
+        match self {
+            OrderType::Limit if price <= Decimal::ZERO => Err(CexError::Validation(
+                "limit orders need a positive price".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum OrderStatus {
@@ -28,8 +45,116 @@ pub enum OrderStatus {
     Filled,
     Cancelled,
     Rejected,
+    /// Unfilled remainder dropped by the engine rather than the user.
+    Expired,
+}
+
+impl OrderStatus {
+    /// Whether the order can no longer trade.
+    pub fn is_terminal(&self) -> bool {
+        !matches!(self, OrderStatus::New | OrderStatus::PartiallyFilled)
+    }
+}
+
+/// Why an order left the book other than by filling.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderReason {
+    UserCancelled,
+    /// A market order ran out of resting liquidity.
+    NoLiquidity,
+    InvalidQuantity,
+    InvalidPrice,
+    /// Trading in the market is halted.
+    MarketHalted,
+    /// Swept up by a cancel-all request.
+    MassCancel,
+    /// A market order reached the edge of its price protection band.
+    PriceProtection,
+    /// A limit price too far from the market's reference price.
+    PriceBand,
+    /// The market's trading state does not take the order, such as a market
+    /// order during an auction or any order while closed.
+    TradingState,
+    /// Another order in its OCO or bracket group filled, triggered or was
+    /// cancelled.
+    LinkedOrder,
+    /// The order's group was malformed.
+    InvalidGroup,
+    /// A quote-sized order's budget buys nothing at the best price.
+    BudgetTooSmall,
+}
+
+/// Why the engine refused a cancel request.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CancelRejectReason {
+    /// No such order in the requested market.
+    UnknownOrder,
+    AlreadyFilled,
+    /// The order was already cancelled, rejected or expired.
+    AlreadyClosed,
+    NotOwner,
 }
 
+/// String forms match the serde representation, for storage in text columns.
+macro_rules! string_enum {
+    ($ty:ident { $($variant:ident => $name:literal),+ $(,)? }) => {
+        impl $ty {
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $($ty::$variant => $name),+
+                }
+            }
+        }
+
+        impl FromStr for $ty {
+            type Err = CexError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match s {
+                    $($name => Ok($ty::$variant),)+
+                    other => Err(CexError::Validation(format!(
+                        concat!("unknown ", stringify!($ty), ": {}"),
+                        other
+                    ))),
+                }
+            }
+        }
+    };
+}
+
+string_enum!(OrderSide { Buy => "buy", Sell => "sell" });
+string_enum!(OrderType { Limit => "limit", Market => "market" });
+string_enum!(OrderStatus {
+    New => "new",
+    PartiallyFilled => "partially_filled",
+    Filled => "filled",
+    Cancelled => "cancelled",
+    Rejected => "rejected",
+    Expired => "expired",
+});
+string_enum!(OrderReason {
+    UserCancelled => "user_cancelled",
+    NoLiquidity => "no_liquidity",
+    InvalidQuantity => "invalid_quantity",
+    InvalidPrice => "invalid_price",
+    MarketHalted => "market_halted",
+    MassCancel => "mass_cancel",
+    PriceProtection => "price_protection",
+    PriceBand => "price_band",
+    TradingState => "trading_state",
+    LinkedOrder => "linked_order",
+    InvalidGroup => "invalid_group",
+    BudgetTooSmall => "budget_too_small",
+});
+string_enum!(CancelRejectReason {
+    UnknownOrder => "unknown_order",
+    AlreadyFilled => "already_filled",
+    AlreadyClosed => "already_closed",
+    NotOwner => "not_owner",
+});
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NewOrder {
     pub order_id: OrderId,
@@ -37,9 +162,66 @@ pub struct NewOrder {
     pub pair: String,
     pub side: OrderSide,
     pub order_type: OrderType,
+    #[serde(with = "crate::utils::decimal")]
     pub price: Decimal,
+    #[serde(with = "crate::utils::decimal")]
     pub quantity: Decimal,
+    /// Market buys only: amount of quote to spend instead of a base
+    /// `quantity`, which must then be zero.
+    #[serde(
+        default,
+        with = "crate::utils::decimal::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub quote_quantity: Option<Decimal>,
+    /// Market orders only: how far past the best opposite price at arrival
+    /// the order may sweep, overriding the book's default band.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_slippage_bps: Option<u32>,
     pub created_at: DateTime<Utc>,
+    /// Orders linked to this one; legs carry their own ids so they can be
+    /// cancelled and tracked like any other order.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<OrderGroup>,
+}
+
+/// Linked orders placed together with a [`NewOrder`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OrderGroup {
+    /// One-cancels-other: the order is a limit order and `stop` waits for its
+    /// trigger on the same side. Any fill or cancel of one cancels the other.
+    Oco { stop: StopLeg },
+    /// The order is an entry; once it fills the exits are placed on the
+    /// opposite side for the filled quantity, as an OCO of each other.
+    Bracket {
+        take_profit: TakeProfitLeg,
+        stop_loss: StopLeg,
+    },
+}
+
+/// An order held back until the market trades through `trigger_price`: at or
+/// below it for a sell, at or above it for a buy. It then goes in as a market
+/// order, or as a limit order when `limit_price` is set.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StopLeg {
+    pub order_id: OrderId,
+    #[serde(with = "crate::utils::decimal")]
+    pub trigger_price: Decimal,
+    #[serde(
+        default,
+        with = "crate::utils::decimal::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub limit_price: Option<Decimal>,
+}
+
+/// Limit exit of a bracket.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TakeProfitLeg {
+    pub order_id: OrderId,
+    #[serde(with = "crate::utils::decimal")]
+    pub price: Decimal,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,6 +231,59 @@ pub struct CancelOrder {
     pub pair: String,
 }
 
+/// Cancels every resting order of a user, optionally narrowed to one market
+/// and/or side.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CancelAll {
+    pub request_id: Uuid,
+    pub user_id: UserId,
+    pub pair: Option<String>,
+    pub side: Option<OrderSide>,
+}
+
+/// Published once a [`CancelAll`] has been carried out, after the cancel
+/// events of the individual orders.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CancelAllSummary {
+    pub request_id: Uuid,
+    pub user_id: UserId,
+    pub pair: Option<String>,
+    pub side: Option<OrderSide>,
+    pub cancelled: Vec<OrderId>,
+    pub ts: DateTime<Utc>,
+}
+
+/// Arms, refreshes or (with a zero timeout) disarms a user's dead-man's
+/// switch, which cancels all their orders once it runs out.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CountdownCancel {
+    pub user_id: UserId,
+    pub timeout_ms: u64,
+}
+
+/// Published by the engine when a [`CancelOrder`] could not be carried out.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CancelRejected {
+    pub order_id: OrderId,
+    /// The user who asked for the cancel, not necessarily the order's owner.
+    pub user_id: UserId,
+    pub pair: String,
+    pub reason: CancelRejectReason,
+    pub ts: DateTime<Utc>,
+}
+
+impl CancelRejected {
+    pub fn new(cancel: &CancelOrder, reason: CancelRejectReason) -> Self {
+        Self {
+            order_id: cancel.order_id,
+            user_id: cancel.user_id,
+            pair: cancel.pair.clone(),
+            reason,
+            ts: Utc::now(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PartialFill {
     pub order_id: OrderId,
@@ -57,6 +292,26 @@ pub struct PartialFill {
     pub remaining_qty: Decimal,
 }
 
+/// State of an order after a lifecycle step, as published by the engine.
+/// `status` is `New` when the order is accepted.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OrderUpdate {
+    pub order_id: OrderId,
+    pub user_id: UserId,
+    pub pair: String,
+    pub status: OrderStatus,
+    #[serde(with = "crate::utils::decimal")]
+    pub quantity: Decimal,
+    /// Cumulative executed quantity.
+    #[serde(with = "crate::utils::decimal")]
+    pub filled: Decimal,
+    /// Volume-weighted fill price; zero until the first fill.
+    #[serde(with = "crate::utils::decimal")]
+    pub avg_price: Decimal,
+    pub reason: Option<OrderReason>,
+    pub ts: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Order {
     pub order_id: OrderId,
@@ -65,9 +320,19 @@ pub struct Order {
     pub side: OrderSide,
     pub order_type: OrderType,
     pub price: Decimal,
+    /// For quote-sized orders, zero until the order is done and then the
+    /// base quantity it bought.
     pub quantity: Decimal,
+    #[serde(default)]
+    pub quote_quantity: Option<Decimal>,
+    #[serde(default)]
+    pub max_slippage_bps: Option<u32>,
     pub filled: Decimal,
+    /// Volume-weighted fill price; zero until the first fill.
+    pub avg_price: Decimal,
     pub status: OrderStatus,
+    /// Set when the order was cancelled, rejected or expired.
+    pub reason: Option<OrderReason>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -81,7 +346,29 @@ impl Order {
     }
 
     pub fn is_open(&self) -> bool {
-        matches!(self.status, OrderStatus::New | OrderStatus::PartiallyFilled)
+        !self.status.is_terminal()
+    }
+
+    /// Records an execution of `quantity` at `price`.
+    pub fn fill(&mut self, price: Decimal, quantity: Decimal) {
+        let filled = self.filled + quantity;
+        self.avg_price = (self.avg_price * self.filled + price * quantity) / filled;
+        self.filled = filled;
+    }
+
+    /// Lifecycle event for the order's current state.
+    pub fn update(&self) -> OrderUpdate {
+        OrderUpdate {
+            order_id: self.order_id,
+            user_id: self.user_id,
+            pair: self.pair.clone(),
+            status: self.status,
+            quantity: self.quantity,
+            filled: self.filled,
+            avg_price: self.avg_price,
+            reason: self.reason,
+            ts: Utc::now(),
+        }
     }
 
     pub fn from_new(new: NewOrder) -> Self {
@@ -93,8 +380,12 @@ impl Order {
             order_type: new.order_type,
             price: new.price,
             quantity: new.quantity,
+            quote_quantity: new.quote_quantity,
+            max_slippage_bps: new.max_slippage_bps,
             filled: Decimal::ZERO,
+            avg_price: Decimal::ZERO,
             status: OrderStatus::New,
+            reason: None,
             created_at: new.created_at,
         }
     }
@@ -116,6 +407,9 @@ pub fn new_order(
         order_type,
         price,
         quantity,
+        quote_quantity: None,
+        max_slippage_bps: None,
         created_at: Utc::now(),
+        group: None,
     }
 }
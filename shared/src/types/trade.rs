@@ -7,7 +7,9 @@ use uuid::Uuid;
 pub struct Trade {
     pub trade_id: Uuid,
     pub pair: String,
+    #[serde(with = "crate::utils::decimal")]
     pub price: Decimal,
+    #[serde(with = "crate::utils::decimal")]
     pub quantity: Decimal,
     pub buy_order_id: Uuid,
     pub sell_order_id: Uuid,
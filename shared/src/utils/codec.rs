@@ -0,0 +1,83 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::error::CexError;
+
+/// Wire encodings supported for envelopes, both on WebSocket connections and
+/// on the internal Redis queues.
+///
+/// Payloads are self-describing: JSON envelopes always start with `{` while
+/// MessagePack envelopes start with a map marker, so consumers can decode
+/// either with [`Codec::decode_any`] without out-of-band negotiation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Codec {
+    #[default]
+    Json,
+    MsgPack,
+}
+
+impl Codec {
+    pub fn encode<T: Serialize>(&self, v: &T) -> Result<Vec<u8>, CexError> {
+        match self {
+            Codec::Json => serde_json::to_vec(v).map_err(CexError::from),
+            Codec::MsgPack => {
+                rmp_serde::to_vec_named(v).map_err(|e| CexError::Codec(e.to_string()))
+            }
+        }
+    }
+
+    pub fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, CexError> {
+        match self {
+            Codec::Json => serde_json::from_slice(bytes).map_err(CexError::from),
+            Codec::MsgPack => {
+                rmp_serde::from_slice(bytes).map_err(|e| CexError::Codec(e.to_string()))
+            }
+        }
+    }
+
+    /// Guesses the codec from the first non-whitespace byte of a payload.
+    pub fn detect(bytes: &[u8]) -> Codec {
+        match bytes.iter().find(|b| !b.is_ascii_whitespace()) {
+            Some(b'{') | Some(b'[') | Some(b'"') => Codec::Json,
+            Some(_) => Codec::MsgPack,
+            None => Codec::Json,
+        }
+    }
+
+    pub fn decode_any<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, CexError> {
+        Codec::detect(bytes).decode(bytes)
+    }
+
+    pub fn is_binary(&self) -> bool {
+        matches!(self, Codec::MsgPack)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Codec::Json => "json",
+            Codec::MsgPack => "msgpack",
+        }
+    }
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Codec {
+    type Err = CexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(Codec::Json),
+            "msgpack" | "messagepack" => Ok(Codec::MsgPack),
+            other => Err(CexError::Validation(format!("unknown codec: {other}"))),
+        }
+    }
+}
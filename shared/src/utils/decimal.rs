@@ -0,0 +1,128 @@
+//! Serde helpers for `Decimal` that keep the string form for human-readable
+//! formats (JSON) and use the 16-byte binary form for compact ones
+//! (MessagePack), avoiding string parsing on the engine's hot path.
+
+use std::fmt;
+
+use rust_decimal::Decimal;
+use serde::de::{self, SeqAccess, Visitor};
+use serde::{Deserializer, Serialize, Serializer};
+
+pub fn serialize<S: Serializer>(value: &Decimal, serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        Serialize::serialize(value, serializer)
+    } else {
+        serializer.serialize_bytes(&value.serialize())
+    }
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Decimal, D::Error> {
+    if deserializer.is_human_readable() {
+        // Internally tagged enums buffer their content and then claim to be
+        // human-readable whatever the format, so bytes can show up here too.
+        deserializer.deserialize_any(DecimalBytesVisitor)
+    } else {
+        deserializer.deserialize_bytes(DecimalBytesVisitor)
+    }
+}
+
+struct DecimalBytesVisitor;
+
+impl<'de> Visitor<'de> for DecimalBytesVisitor {
+    type Value = Decimal;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a decimal string, number or 16 bytes of binary decimal")
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Decimal, E> {
+        let bytes: [u8; 16] = v
+            .try_into()
+            .map_err(|_| E::invalid_length(v.len(), &self))?;
+        from_bytes(bytes)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Decimal, E> {
+        v.parse()
+            .or_else(|_| Decimal::from_scientific(v))
+            .map_err(E::custom)
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Decimal, E> {
+        Ok(Decimal::from(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Decimal, E> {
+        Ok(Decimal::from(v))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Decimal, E> {
+        Decimal::try_from(v).map_err(E::custom)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Decimal, A::Error> {
+        let mut bytes = [0u8; 16];
+        for (i, b) in bytes.iter_mut().enumerate() {
+            *b = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+        }
+        from_bytes(bytes)
+    }
+}
+
+/// Sign bit and scale byte; everything else in the flags word is reserved.
+const FLAGS_MASK: u32 = 0x80FF_0000;
+
+/// `Decimal::deserialize` quietly masks reserved flag bits and rescales
+/// anything past the maximum scale, changing the value. Bytes come from
+/// clients, so reject those forms instead.
+fn from_bytes<E: de::Error>(bytes: [u8; 16]) -> Result<Decimal, E> {
+    let flags = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    let scale = (flags >> 16) & 0xFF;
+    if flags & !FLAGS_MASK != 0 {
+        return Err(E::custom(format!(
+            "binary decimal has reserved flag bits set: {flags:#010x}"
+        )));
+    }
+    if scale > Decimal::MAX_SCALE {
+        return Err(E::custom(format!(
+            "binary decimal scale {scale} exceeds {}",
+            Decimal::MAX_SCALE
+        )));
+    }
+    Ok(Decimal::deserialize(bytes))
+}
+
+/// The same encoding for `Option<Decimal>`.
+pub mod option {
+    use rust_decimal::Decimal;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    struct Wrapped<'a>(&'a Decimal);
+
+    impl Serialize for Wrapped<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            super::serialize(self.0, serializer)
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct Owned(#[serde(with = "super")] Decimal);
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<Decimal>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => serializer.serialize_some(&Wrapped(value)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Decimal>, D::Error> {
+        Ok(Option::<Owned>::deserialize(deserializer)?.map(|owned| owned.0))
+    }
+}
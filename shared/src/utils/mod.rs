@@ -1,3 +1,8 @@
+pub mod backoff;
+pub mod codec;
+pub mod decimal;
 pub mod json;
 
+pub use backoff::Backoff;
+pub use codec::Codec;
 pub use json::{from_json, to_json};
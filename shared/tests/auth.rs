@@ -0,0 +1,35 @@
+use shared::auth::{JwtKeys, TokenKind};
+use shared::CexError;
+use uuid::Uuid;
+
+#[test]
+fn issued_tokens_verify_for_their_kind_only() {
+    let keys = JwtKeys::new(b"secret");
+    let user = Uuid::new_v4();
+    let pair = keys.issue_pair(user).unwrap();
+
+    let claims = keys.verify(&pair.access_token, TokenKind::Access).unwrap();
+    assert_eq!(claims.sub, user);
+    assert!(keys.verify(&pair.refresh_token, TokenKind::Refresh).is_ok());
+    assert!(matches!(
+        keys.verify(&pair.refresh_token, TokenKind::Access),
+        Err(CexError::Unauthorized(_))
+    ));
+}
+
+#[test]
+fn rejects_tokens_signed_with_another_secret() {
+    let token = JwtKeys::new(b"one")
+        .issue(Uuid::new_v4(), TokenKind::Access)
+        .unwrap();
+    assert!(JwtKeys::new(b"two")
+        .verify(&token, TokenKind::Access)
+        .is_err());
+}
+
+#[test]
+fn rejects_expired_tokens() {
+    let keys = JwtKeys::with_ttls(b"secret", -120, -120);
+    let token = keys.issue(Uuid::new_v4(), TokenKind::Access).unwrap();
+    assert!(keys.verify(&token, TokenKind::Access).is_err());
+}
@@ -0,0 +1,12 @@
+use std::time::Duration;
+
+use shared::utils::Backoff;
+
+#[test]
+fn delays_double_up_to_the_cap_and_reset() {
+    let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_millis(500));
+    let delays: Vec<_> = (0..5).map(|_| backoff.next_delay().as_millis()).collect();
+    assert_eq!(delays, vec![100, 200, 400, 500, 500]);
+    backoff.reset();
+    assert_eq!(backoff.next_delay(), Duration::from_millis(100));
+}
@@ -0,0 +1,31 @@
+use chrono::{TimeZone, Utc};
+use shared::types::CandleInterval;
+
+#[test]
+fn intervals_parse_from_their_wire_names() {
+    for interval in CandleInterval::ALL {
+        assert_eq!(
+            interval.as_str().parse::<CandleInterval>().unwrap(),
+            interval
+        );
+        let json = serde_json::to_string(&interval).unwrap();
+        assert_eq!(json, format!("\"{}\"", interval.as_str()));
+    }
+    assert!("2m".parse::<CandleInterval>().is_err());
+}
+
+#[test]
+fn open_time_aligns_to_bucket_start() {
+    let ts = Utc.with_ymd_and_hms(2024, 5, 2, 13, 47, 31).unwrap();
+    let cases = [
+        (CandleInterval::OneMinute, (13, 47)),
+        (CandleInterval::FiveMinutes, (13, 45)),
+        (CandleInterval::FifteenMinutes, (13, 45)),
+        (CandleInterval::OneHour, (13, 0)),
+        (CandleInterval::OneDay, (0, 0)),
+    ];
+    for (interval, (h, m)) in cases {
+        let expected = Utc.with_ymd_and_hms(2024, 5, 2, h, m, 0).unwrap();
+        assert_eq!(interval.open_time(ts), expected, "{}", interval.as_str());
+    }
+}
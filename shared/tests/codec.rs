@@ -0,0 +1,120 @@
+use std::str::FromStr;
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use shared::types::{new_order, OrderGroup, OrderSide, OrderType, StopLeg};
+use shared::{Codec, Envelope, Event};
+use uuid::Uuid;
+
+fn sample_envelope() -> Envelope {
+    let order = new_order(
+        Uuid::new_v4(),
+        "SOLUSDC".to_string(),
+        OrderSide::Buy,
+        OrderType::Limit,
+        Decimal::from_str("31.1250").unwrap(),
+        Decimal::from_str("0.001").unwrap(),
+    );
+    Envelope::new("test", Event::OrderNew(order))
+}
+
+fn assert_same_order(a: &Envelope, b: &Envelope) {
+    match (&a.event, &b.event) {
+        (Event::OrderNew(x), Event::OrderNew(y)) => {
+            assert_eq!(x.order_id, y.order_id);
+            assert_eq!(x.price, y.price);
+            assert_eq!(x.price.scale(), y.price.scale());
+            assert_eq!(x.quantity, y.quantity);
+            assert_eq!(x.created_at, y.created_at);
+        }
+        other => panic!("unexpected events {other:?}"),
+    }
+}
+
+#[test]
+fn roundtrips_envelope_in_every_codec() {
+    let envelope = sample_envelope();
+    for codec in [Codec::Json, Codec::MsgPack] {
+        let bytes = envelope.encode(codec).unwrap();
+        assert_eq!(Codec::detect(&bytes), codec);
+        let decoded = Envelope::decode(&bytes).unwrap();
+        assert_eq!(decoded.event_id, envelope.event_id);
+        assert_same_order(&envelope, &decoded);
+    }
+}
+
+#[test]
+fn version_1_envelopes_without_event_id_still_decode() {
+    let bytes = sample_envelope().encode(Codec::Json).unwrap();
+    let mut value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    value.as_object_mut().unwrap().remove("event_id");
+    value["version"] = 1.into();
+    let legacy = serde_json::to_vec(&value).unwrap();
+    let decoded = Envelope::decode(&legacy).unwrap();
+    assert!(!decoded.event_id.is_nil());
+}
+
+#[test]
+fn order_groups_roundtrip_in_every_codec() {
+    for limit_price in [None, Some(Decimal::from_str("29.40").unwrap())] {
+        let mut envelope = sample_envelope();
+        let group = OrderGroup::Oco {
+            stop: StopLeg {
+                order_id: Uuid::new_v4(),
+                trigger_price: Decimal::from_str("29.50").unwrap(),
+                limit_price,
+            },
+        };
+        if let Event::OrderNew(order) = &mut envelope.event {
+            order.group = Some(group.clone());
+        }
+        for codec in [Codec::Json, Codec::MsgPack] {
+            let bytes = envelope.encode(codec).unwrap();
+            let Event::OrderNew(decoded) = Envelope::decode(&bytes).unwrap().event else {
+                panic!("expected an order");
+            };
+            assert_eq!(decoded.group.as_ref(), Some(&group));
+        }
+    }
+}
+
+#[test]
+fn json_keeps_decimals_as_strings() {
+    let bytes = sample_envelope().encode(Codec::Json).unwrap();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(value["event"]["data"]["price"], "31.1250");
+}
+
+#[test]
+fn msgpack_is_smaller_than_json() {
+    let envelope = sample_envelope();
+    let json = envelope.encode(Codec::Json).unwrap();
+    let msgpack = envelope.encode(Codec::MsgPack).unwrap();
+    assert!(msgpack.len() < json.len());
+}
+
+#[test]
+fn parses_codec_names() {
+    assert_eq!(Codec::from_str("msgpack").unwrap(), Codec::MsgPack);
+    assert_eq!(Codec::from_str("JSON").unwrap(), Codec::Json);
+    assert!(Codec::from_str("xml").is_err());
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Price(#[serde(with = "shared::utils::decimal")] Decimal);
+
+#[test]
+fn binary_decimals_with_a_bad_scale_or_flags_are_rejected() {
+    let bytes = Codec::MsgPack.encode(&Price(Decimal::new(1, 2))).unwrap();
+    // bin8 marker and length, then the flags word.
+    assert_eq!(&bytes[..2], &[0xc4, 16]);
+    let decoded: Price = Codec::MsgPack.decode(&bytes).unwrap();
+    assert_eq!(decoded.0, Decimal::new(1, 2));
+
+    let mut scaled = bytes.clone();
+    scaled[4] = 40;
+    assert!(Codec::MsgPack.decode::<Price>(&scaled).is_err());
+    let mut reserved = bytes;
+    reserved[2] = 1;
+    assert!(Codec::MsgPack.decode::<Price>(&reserved).is_err());
+}
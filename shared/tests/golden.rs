@@ -0,0 +1,117 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+use shared::events::upcast::MIN_ENVELOPE_VERSION;
+use shared::{Codec, Envelope, ENVELOPE_VERSION};
+
+fn golden_dir(version: u8) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("v{version}"))
+}
+
+fn golden_files(version: u8) -> Vec<PathBuf> {
+    let mut files: Vec<_> = fs::read_dir(golden_dir(version))
+        .unwrap_or_else(|e| panic!("missing golden files for v{version}: {e}"))
+        .map(|entry| entry.unwrap().path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+    assert!(!files.is_empty(), "no golden files for v{version}");
+    files
+}
+
+#[test]
+fn every_historical_version_decodes_to_the_current_schema() {
+    for version in MIN_ENVELOPE_VERSION..=ENVELOPE_VERSION {
+        for path in golden_files(version) {
+            let bytes = fs::read(&path).unwrap();
+            let envelope = Envelope::decode(&bytes)
+                .unwrap_or_else(|e| panic!("{} failed to decode: {e}", path.display()));
+            assert_eq!(envelope.version, ENVELOPE_VERSION, "{}", path.display());
+        }
+    }
+}
+
+#[test]
+fn current_version_reencodes_to_the_same_json() {
+    for path in golden_files(ENVELOPE_VERSION) {
+        let bytes = fs::read(&path).unwrap();
+        let envelope = Envelope::decode(&bytes).unwrap();
+        let reencoded: Value =
+            serde_json::from_slice(&envelope.encode(Codec::Json).unwrap()).unwrap();
+        let golden: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(reencoded, golden, "{} changed shape", path.display());
+    }
+}
+
+#[test]
+fn upcasting_preserves_event_content() {
+    for version in MIN_ENVELOPE_VERSION..ENVELOPE_VERSION {
+        for old_path in golden_files(version) {
+            let current_path = golden_dir(ENVELOPE_VERSION).join(old_path.file_name().unwrap());
+            let old = Envelope::decode(&fs::read(&old_path).unwrap()).unwrap();
+            let current = Envelope::decode(&fs::read(&current_path).unwrap()).unwrap();
+            assert_eq!(
+                serde_json::to_value(&old.event).unwrap(),
+                serde_json::to_value(&current.event).unwrap(),
+                "{}",
+                old_path.display()
+            );
+            assert_eq!(old.emitted_at, current.emitted_at);
+        }
+    }
+}
+
+#[test]
+fn v1_event_ids_are_stable_across_reads() {
+    for path in golden_files(1) {
+        let bytes = fs::read(&path).unwrap();
+        let first = Envelope::decode(&bytes).unwrap();
+        let second = Envelope::decode(&bytes).unwrap();
+        assert_eq!(first.event_id, second.event_id, "{}", path.display());
+    }
+}
+
+#[test]
+fn rejects_unknown_versions_and_incomplete_current_envelopes() {
+    let path = golden_dir(ENVELOPE_VERSION).join("order_cancel.json");
+    let golden: Value = serde_json::from_slice(&fs::read(path).unwrap()).unwrap();
+
+    for version in [0, ENVELOPE_VERSION + 1] {
+        let mut value = golden.clone();
+        value["version"] = Value::from(version);
+        let err = Envelope::decode(&serde_json::to_vec(&value).unwrap()).unwrap_err();
+        assert!(
+            err.to_string().contains("unsupported envelope version"),
+            "{err}"
+        );
+    }
+
+    let mut value = golden;
+    value.as_object_mut().unwrap().remove("event_id");
+    assert!(Envelope::decode(&serde_json::to_vec(&value).unwrap()).is_err());
+}
+
+#[test]
+fn msgpack_envelopes_must_be_current() {
+    let bytes = fs::read(golden_dir(ENVELOPE_VERSION).join("order_new.json")).unwrap();
+    let mut envelope = Envelope::decode(&bytes).unwrap();
+    let current = envelope.encode(Codec::MsgPack).unwrap();
+    assert!(Envelope::decode(&current).is_ok());
+
+    envelope.version = MIN_ENVELOPE_VERSION;
+    let old = envelope.encode(Codec::MsgPack).unwrap();
+    assert!(Envelope::decode(&old).is_err());
+}
+
+#[test]
+fn events_from_before_sequencing_have_no_position() {
+    for version in MIN_ENVELOPE_VERSION..3 {
+        for path in golden_files(version) {
+            let envelope = Envelope::decode(&fs::read(&path).unwrap()).unwrap();
+            assert_eq!(envelope.position(), None, "{}", path.display());
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use shared::{SequenceCheck, SequenceTracker};
+
+#[test]
+fn first_event_per_market_sets_the_baseline() {
+    let mut tracker = SequenceTracker::new();
+    assert_eq!(tracker.check("SOLUSDC", 7), SequenceCheck::First);
+    assert_eq!(tracker.check("BTCUSDC", 1), SequenceCheck::First);
+    assert_eq!(tracker.check("SOLUSDC", 8), SequenceCheck::Next);
+    assert_eq!(tracker.last("SOLUSDC"), Some(8));
+    assert_eq!(tracker.last("ETHUSDC"), None);
+}
+
+#[test]
+fn repeated_or_older_sequences_are_duplicates() {
+    let mut tracker = SequenceTracker::new();
+    tracker.check("SOLUSDC", 5);
+    assert_eq!(tracker.check("SOLUSDC", 5), SequenceCheck::Duplicate);
+    assert_eq!(tracker.check("SOLUSDC", 2), SequenceCheck::Duplicate);
+    assert_eq!(tracker.last("SOLUSDC"), Some(5));
+}
+
+#[test]
+fn skipped_sequences_are_reported_as_a_gap() {
+    let mut tracker = SequenceTracker::new();
+    tracker.check("SOLUSDC", 1);
+    assert_eq!(
+        tracker.check("SOLUSDC", 5),
+        SequenceCheck::Gap { missing: 2..=4 }
+    );
+    assert_eq!(tracker.check("SOLUSDC", 6), SequenceCheck::Next);
+    // Gap fills arriving late are behind the tracker and get dropped.
+    assert_eq!(tracker.check("SOLUSDC", 3), SequenceCheck::Duplicate);
+}
+
+#[test]
+fn numbering_that_restarts_at_one_is_a_reset() {
+    let mut tracker = SequenceTracker::new();
+    tracker.check("SOLUSDC", 40);
+    assert_eq!(
+        tracker.check("SOLUSDC", 1),
+        SequenceCheck::Reset { previous: 40 }
+    );
+    assert_eq!(tracker.check("SOLUSDC", 2), SequenceCheck::Next);
+    assert_eq!(tracker.check("SOLUSDC", 2), SequenceCheck::Duplicate);
+}
+
+#[test]
+fn a_set_sequence_is_the_baseline_for_the_next_check() {
+    let mut tracker = SequenceTracker::new();
+    tracker.set("SOLUSDC", 9);
+    assert_eq!(tracker.check("SOLUSDC", 10), SequenceCheck::Next);
+}
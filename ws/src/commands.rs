@@ -0,0 +1,253 @@
+use redis::RedisManager;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use shared::types::{
+    new_order as build_new_order, CancelAll, CancelOrder, OrderId, OrderSide, OrderType, UserId,
+};
+use shared::{CexError, Codec, Envelope, Event};
+use uuid::Uuid;
+
+const WS_SOURCE: &str = "ws";
+
+/// Commands a client may send over the socket. `req_id` is echoed back on the
+/// acknowledgement so clients can correlate replies with requests.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum ClientCommand {
+    /// Authenticates the session with an access token issued by `/auth/login`.
+    Auth {
+        req_id: Option<String>,
+        token: String,
+        /// Cancel all of the user's orders when this connection goes away.
+        #[serde(default)]
+        cancel_on_disconnect: bool,
+    },
+    Place {
+        req_id: Option<String>,
+        pair: String,
+        side: OrderSide,
+        order_type: OrderType,
+        /// Required for limit orders; market orders may omit it.
+        #[serde(default)]
+        price: Decimal,
+        quantity: Decimal,
+    },
+    Cancel {
+        req_id: Option<String>,
+        order_id: OrderId,
+        pair: String,
+    },
+    /// Cancel-replace: the existing order is cancelled and, once the engine
+    /// confirms the cancel, a new one with a fresh `order_id` is submitted in
+    /// its place. Handled by the session, which sees the confirmation.
+    Amend {
+        req_id: Option<String>,
+        order_id: OrderId,
+        pair: String,
+        side: OrderSide,
+        order_type: OrderType,
+        /// Required for limit orders; market orders may omit it.
+        #[serde(default)]
+        price: Decimal,
+        quantity: Decimal,
+    },
+}
+
+impl ClientCommand {
+    pub fn req_id(&self) -> Option<String> {
+        match self {
+            ClientCommand::Auth { req_id, .. }
+            | ClientCommand::Place { req_id, .. }
+            | ClientCommand::Cancel { req_id, .. }
+            | ClientCommand::Amend { req_id, .. } => req_id.clone(),
+        }
+    }
+
+    pub fn op(&self) -> &'static str {
+        match self {
+            ClientCommand::Auth { .. } => "auth",
+            ClientCommand::Place { .. } => "place",
+            ClientCommand::Cancel { .. } => "cancel",
+            ClientCommand::Amend { .. } => "amend",
+        }
+    }
+}
+
+/// The order an amend submits in place of the one it cancels.
+#[derive(Debug, Clone)]
+pub struct Replacement {
+    pub pair: String,
+    pub side: OrderSide,
+    pub order_type: OrderType,
+    pub price: Decimal,
+    pub quantity: Decimal,
+}
+
+impl Replacement {
+    /// Checked before the cancel goes out, so a bad replacement never costs
+    /// the client the order it meant to amend.
+    pub fn validate(&self) -> Result<(), CexError> {
+        check_order(self.order_type, self.price, self.quantity)
+    }
+
+    pub async fn submit(
+        self,
+        redis: &RedisManager,
+        codec: Codec,
+        user_id: UserId,
+    ) -> Result<OrderId, CexError> {
+        push_new_order(
+            redis,
+            codec,
+            user_id,
+            self.pair,
+            self.side,
+            self.order_type,
+            self.price,
+            self.quantity,
+        )
+        .await
+    }
+}
+
+/// Replies sent back on the socket, interleaved with the event stream.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CommandReply {
+    Ack {
+        req_id: Option<String>,
+        op: &'static str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        order_id: Option<OrderId>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        replaced_order_id: Option<OrderId>,
+    },
+    Error {
+        req_id: Option<String>,
+        message: String,
+    },
+}
+
+impl CommandReply {
+    pub fn ack(req_id: Option<String>, op: &'static str, order_id: Option<OrderId>) -> Self {
+        CommandReply::Ack {
+            req_id,
+            op,
+            order_id,
+            replaced_order_id: None,
+        }
+    }
+
+    /// Acknowledges an amend once its replacement is queued.
+    pub fn amended(req_id: Option<String>, order_id: OrderId, replaced: OrderId) -> Self {
+        CommandReply::Ack {
+            req_id,
+            op: "amend",
+            order_id: Some(order_id),
+            replaced_order_id: Some(replaced),
+        }
+    }
+
+    pub fn error(req_id: Option<String>, err: impl ToString) -> Self {
+        CommandReply::Error {
+            req_id,
+            message: err.to_string(),
+        }
+    }
+}
+
+/// Translates a place or cancel command into the same queue messages the
+/// HTTP API produces and pushes them to the engine.
+pub async fn submit(
+    redis: &RedisManager,
+    codec: Codec,
+    user_id: UserId,
+    cmd: ClientCommand,
+) -> Result<CommandReply, CexError> {
+    let req_id = cmd.req_id();
+    let op = cmd.op();
+    match cmd {
+        ClientCommand::Auth { .. } | ClientCommand::Amend { .. } => Err(CexError::Validation(
+            format!("{op} is handled by the session"),
+        )),
+        ClientCommand::Place {
+            pair,
+            side,
+            order_type,
+            price,
+            quantity,
+            ..
+        } => {
+            let order_id = push_new_order(
+                redis, codec, user_id, pair, side, order_type, price, quantity,
+            )
+            .await?;
+            Ok(CommandReply::ack(req_id, op, Some(order_id)))
+        }
+        ClientCommand::Cancel { order_id, pair, .. } => {
+            push_cancel(redis, codec, user_id, order_id, pair).await?;
+            Ok(CommandReply::ack(req_id, op, Some(order_id)))
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn push_new_order(
+    redis: &RedisManager,
+    codec: Codec,
+    user_id: UserId,
+    pair: String,
+    side: OrderSide,
+    order_type: OrderType,
+    price: Decimal,
+    quantity: Decimal,
+) -> Result<OrderId, CexError> {
+    check_order(order_type, price, quantity)?;
+    let order = build_new_order(user_id, pair, side, order_type, price, quantity);
+    let order_id = order.order_id;
+    let envelope = Envelope::new(WS_SOURCE, Event::OrderNew(order));
+    redis.push_new_order(&envelope.encode(codec)?).await?;
+    Ok(order_id)
+}
+
+fn check_order(order_type: OrderType, price: Decimal, quantity: Decimal) -> Result<(), CexError> {
+    order_type.check_price(price)?;
+    if quantity <= Decimal::ZERO {
+        return Err(CexError::Validation(
+            "quantity must be positive".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+pub async fn push_cancel(
+    redis: &RedisManager,
+    codec: Codec,
+    user_id: UserId,
+    order_id: OrderId,
+    pair: String,
+) -> Result<(), CexError> {
+    let cancel = CancelOrder {
+        order_id,
+        user_id,
+        pair,
+    };
+    let envelope = Envelope::new(WS_SOURCE, Event::CancelRequest(cancel));
+    redis.push_cancel_order(&envelope.encode(codec)?).await
+}
+
+/// Queues a cancel of every order `user_id` has, for cancel-on-disconnect.
+pub async fn push_cancel_all(
+    redis: &RedisManager,
+    codec: Codec,
+    user_id: UserId,
+) -> Result<(), CexError> {
+    let request = CancelAll {
+        request_id: Uuid::new_v4(),
+        user_id,
+        pair: None,
+        side: None,
+    };
+    let envelope = Envelope::new(WS_SOURCE, Event::CancelAll(request));
+    redis.push_cancel_order(&envelope.encode(codec)?).await
+}
@@ -0,0 +1,26 @@
+use shared::{CexError, Codec};
+
+/// Runtime settings for the WebSocket server, read from the environment by the binary.
+#[derive(Debug, Clone)]
+pub struct WsConfig {
+    pub bind_addr: String,
+    pub redis_url: String,
+    pub jwt_secret: String,
+    pub queue_codec: Codec,
+}
+
+impl WsConfig {
+    pub fn from_env() -> Result<Self, CexError> {
+        Ok(Self {
+            bind_addr: env_or("WS_BIND", "0.0.0.0:9000"),
+            redis_url: env_or("REDIS_URL", "redis://127.0.0.1/"),
+            jwt_secret: std::env::var("JWT_SECRET")
+                .map_err(|_| CexError::Validation("JWT_SECRET must be set".to_string()))?,
+            queue_codec: env_or("QUEUE_CODEC", "json").parse()?,
+        })
+    }
+}
+
+fn env_or(key: &str, default: &str) -> String {
+    std::env::var(key).unwrap_or_else(|_| default.to_string())
+}
@@ -0,0 +1,55 @@
+use std::sync::OnceLock;
+
+use shared::types::UserId;
+use shared::{Audience, Codec, Envelope};
+
+/// An event received from Redis, shared by every session. The MessagePack
+/// form is encoded on first use, once, so JSON-only deployments never pay
+/// for it.
+#[derive(Debug)]
+pub struct EventFrame {
+    pub json: String,
+    /// The decoded event, for sessions that act on what the engine did.
+    pub envelope: Option<Envelope>,
+    msgpack: OnceLock<Option<Vec<u8>>>,
+}
+
+impl EventFrame {
+    pub fn new(json: String) -> Self {
+        let envelope = Envelope::decode(json.as_bytes())
+            .map_err(|err| tracing::warn!("failed to decode event: {err}"))
+            .ok();
+        Self {
+            json,
+            envelope,
+            msgpack: OnceLock::new(),
+        }
+    }
+
+    /// Market data goes to every session, order events only to a session
+    /// authenticated as their user, and undecodable or internal events to
+    /// none.
+    pub fn visible_to(&self, user_id: Option<UserId>) -> bool {
+        match self
+            .envelope
+            .as_ref()
+            .map(|envelope| envelope.event.audience())
+        {
+            Some(Audience::Public) => true,
+            Some(Audience::User(owner)) => user_id == Some(owner),
+            Some(Audience::Internal) | None => false,
+        }
+    }
+
+    pub fn msgpack(&self) -> Option<&[u8]> {
+        self.msgpack
+            .get_or_init(|| {
+                let envelope = self.envelope.as_ref()?;
+                envelope
+                    .encode(Codec::MsgPack)
+                    .map_err(|err| tracing::warn!("failed to re-encode event as msgpack: {err}"))
+                    .ok()
+            })
+            .as_deref()
+    }
+}
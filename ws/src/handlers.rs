@@ -1,16 +1,60 @@
+use actix_web::http::header::SEC_WEBSOCKET_PROTOCOL;
 use actix_web::{get, web, Error, HttpRequest, HttpResponse};
 use actix_web_actors::ws;
-use tokio::sync::broadcast;
+use serde::Deserialize;
+use shared::Codec;
 
 use crate::server::WsState;
 use crate::session::WsSession;
 
+const PROTOCOL_JSON: &str = "cex.json";
+const PROTOCOL_MSGPACK: &str = "cex.msgpack";
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ConnectParams {
+    pub encoding: Option<Codec>,
+}
+
 #[get("/ws")]
 pub async fn ws_upgrade(
     req: HttpRequest,
     stream: web::Payload,
     state: web::Data<WsState>,
 ) -> Result<HttpResponse, Error> {
-    let rx: broadcast::Receiver<String> = state.broadcaster.subscribe();
-    ws::start(WsSession::new(rx), &req, stream)
+    let params = web::Query::<ConnectParams>::from_query(req.query_string())
+        .map(|q| q.into_inner())
+        .map_err(actix_web::error::ErrorBadRequest)?;
+    let codec = negotiate_codec(&req, &params);
+    let session = WsSession::new(
+        state.broadcaster.subscribe(),
+        state.redis.clone(),
+        state.queue_codec,
+        state.jwt.clone(),
+        codec,
+    );
+    ws::WsResponseBuilder::new(session, &req, stream)
+        .protocols(&[PROTOCOL_MSGPACK, PROTOCOL_JSON])
+        .start()
+}
+
+/// The `encoding` query parameter wins; otherwise the `cex.msgpack` /
+/// `cex.json` subprotocol is honoured, defaulting to JSON.
+pub fn negotiate_codec(req: &HttpRequest, params: &ConnectParams) -> Codec {
+    if let Some(codec) = params.encoding {
+        return codec;
+    }
+    let requested = req
+        .headers()
+        .get(SEC_WEBSOCKET_PROTOCOL)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let wants_msgpack = requested
+        .split(',')
+        .map(str::trim)
+        .any(|p| p == PROTOCOL_MSGPACK);
+    if wants_msgpack {
+        Codec::MsgPack
+    } else {
+        Codec::Json
+    }
 }
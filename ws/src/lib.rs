@@ -1,5 +1,9 @@
+pub mod commands;
+pub mod config;
+pub mod frames;
 pub mod handlers;
 pub mod server;
 pub mod session;
 
+pub use config::WsConfig;
 pub use server::run;
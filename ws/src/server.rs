@@ -1,27 +1,45 @@
 use actix_web::{middleware::Logger, web, App, HttpServer};
 use redis::RedisManager;
-use shared::CexError;
+use shared::auth::JwtKeys;
+use shared::utils::Backoff;
+use shared::{CexError, Codec, SequenceCheck, SequenceTracker};
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::broadcast;
 use tokio_stream::StreamExt;
 use tracing::info;
 
+use crate::config::WsConfig;
+use crate::frames::EventFrame;
 use crate::handlers;
 
 #[derive(Clone)]
 pub struct WsState {
-    pub broadcaster: broadcast::Sender<String>,
+    pub broadcaster: broadcast::Sender<Arc<EventFrame>>,
+    pub redis: Arc<RedisManager>,
+    /// Encoding used for order commands pushed onto the engine queues.
+    pub queue_codec: Codec,
+    pub jwt: Arc<JwtKeys>,
 }
 
-pub async fn run(bind_addr: &str, redis_url: &str) -> Result<(), CexError> {
+pub async fn run(config: WsConfig) -> Result<(), CexError> {
+    let bind_addr = config.bind_addr.as_str();
+    let redis_url = config.redis_url.as_str();
+    let queue_codec = config.queue_codec;
+    let jwt = Arc::new(JwtKeys::new(config.jwt_secret.as_bytes()));
     let redis = RedisManager::new(redis_url).await?;
-    let (tx, _rx) = broadcast::channel::<String>(512);
+    let (tx, _rx) = broadcast::channel::<Arc<EventFrame>>(512);
     spawn_redis_forwarder(redis, tx.clone());
+    let commands_redis = Arc::new(RedisManager::new(redis_url).await?);
 
     info!(%bind_addr, "starting ws server");
     HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(WsState {
                 broadcaster: tx.clone(),
+                redis: commands_redis.clone(),
+                queue_codec,
+                jwt: jwt.clone(),
             }))
             .wrap(Logger::default())
             .service(handlers::ws_upgrade)
@@ -33,24 +51,130 @@ pub async fn run(bind_addr: &str, redis_url: &str) -> Result<(), CexError> {
     .map_err(|e| CexError::Internal(format!("server error: {e}")))
 }
 
-fn spawn_redis_forwarder(manager: RedisManager, broadcaster: broadcast::Sender<String>) {
+/// Forwards the event feed to the sessions, resubscribing with backoff if
+/// Redis drops the connection. The sequence tracker outlives each
+/// subscription, and every (re)subscribe first catches up from the market
+/// logs so nothing published in between is lost.
+fn spawn_redis_forwarder(manager: RedisManager, broadcaster: broadcast::Sender<Arc<EventFrame>>) {
     tokio::spawn(async move {
-        match manager.subscribe_events().await {
-            Ok(sub) => {
-                let mut stream = redis::manager::subscriber_stream(sub);
-                while let Some(msg) = stream.next().await {
-                    match msg {
-                        Ok(payload) => {
-                            let _ = broadcaster.send(payload);
-                        }
-                        Err(err) => {
-                            tracing::error!("redis subscriber error: {err}");
-                            break;
+        let mut sequences = SequenceTracker::new();
+        let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_secs(5));
+        loop {
+            match manager.subscribe_events().await {
+                Ok(sub) => {
+                    backoff.reset();
+                    catch_up(&manager, &mut sequences, &broadcaster).await;
+                    let mut stream = redis::manager::subscriber_stream(sub);
+                    while let Some(msg) = stream.next().await {
+                        match msg {
+                            Ok(payload) => {
+                                forward_event(&manager, &mut sequences, &broadcaster, payload)
+                                    .await;
+                            }
+                            Err(err) => {
+                                tracing::error!("redis subscriber error: {err}");
+                                break;
+                            }
                         }
                     }
+                    tracing::warn!("event subscription ended, resubscribing");
                 }
+                Err(err) => tracing::error!("failed to subscribe to events: {err}"),
             }
-            Err(err) => tracing::error!("failed to subscribe to events: {err}"),
+            tokio::time::sleep(backoff.next_delay()).await;
         }
     });
 }
+
+/// Brings the tracker level with the market logs. Markets seen before have
+/// whatever they missed replayed; new ones, including every market on the
+/// first call after a restart, start from their log's tail. A log that is
+/// now behind the tracker was lost and its numbering starts over.
+async fn catch_up(
+    manager: &RedisManager,
+    sequences: &mut SequenceTracker,
+    broadcaster: &broadcast::Sender<Arc<EventFrame>>,
+) {
+    let markets = match manager.markets_with_logs().await {
+        Ok(markets) => markets,
+        Err(err) => {
+            tracing::error!("failed to list market logs: {err}");
+            return;
+        }
+    };
+    for market in markets {
+        let latest = match manager.last_market_sequence(&market).await {
+            Ok(Some(latest)) => latest,
+            Ok(None) => continue,
+            Err(err) => {
+                tracing::error!(%market, "failed to read market log: {err}");
+                continue;
+            }
+        };
+        match sequences.last(&market) {
+            Some(last) if last < latest => {
+                replay(manager, &market, last + 1, latest, broadcaster).await;
+                sequences.set(&market, latest);
+            }
+            Some(last) if last > latest => {
+                tracing::warn!(%market, last, latest, "market log went backwards, following it");
+                sequences.set(&market, latest);
+            }
+            Some(_) => {}
+            None => sequences.set(&market, latest),
+        }
+    }
+}
+
+async fn replay(
+    manager: &RedisManager,
+    market: &str,
+    from: u64,
+    to: u64,
+    broadcaster: &broadcast::Sender<Arc<EventFrame>>,
+) {
+    match manager.read_market_range(market, from, to).await {
+        Ok(entries) => {
+            for entry in entries {
+                let _ = broadcaster.send(Arc::new(EventFrame::new(entry.payload)));
+            }
+        }
+        Err(err) => tracing::error!(%market, "failed to replay events: {err}"),
+    }
+}
+
+/// Drops redelivered events and, when pub/sub skipped some, replays the
+/// missing range from the market's log so clients see every event in order.
+async fn forward_event(
+    manager: &RedisManager,
+    sequences: &mut SequenceTracker,
+    broadcaster: &broadcast::Sender<Arc<EventFrame>>,
+    payload: String,
+) {
+    let frame = EventFrame::new(payload);
+    let position = frame
+        .envelope
+        .as_ref()
+        .and_then(|envelope| envelope.position().map(|(m, s)| (m.to_string(), s)));
+    if let Some((market, sequence)) = position {
+        match sequences.check(&market, sequence) {
+            SequenceCheck::Duplicate => return,
+            SequenceCheck::Gap { missing } => {
+                tracing::warn!(%market, ?missing, "event gap, replaying from market log");
+                replay(
+                    manager,
+                    &market,
+                    *missing.start(),
+                    *missing.end(),
+                    broadcaster,
+                )
+                .await;
+            }
+            SequenceCheck::Reset { previous } => {
+                tracing::warn!(%market, previous, "market sequence restarted at 1");
+            }
+            SequenceCheck::First | SequenceCheck::Next => {}
+        }
+    }
+    let _ = broadcaster.send(Arc::new(frame));
+}
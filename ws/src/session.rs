@@ -1,16 +1,225 @@
-use actix::{Actor, ActorContext, AsyncContext, StreamHandler};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use actix::{Actor, ActorContext, ActorFutureExt, AsyncContext, StreamHandler, WrapFuture};
 use actix_web_actors::ws;
+use redis::RedisManager;
+use shared::auth::{JwtKeys, TokenKind};
+use shared::types::{OrderId, OrderStatus, UserId};
+use shared::{Codec, Event};
 use tokio::sync::broadcast;
 use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
 use tokio_stream::wrappers::BroadcastStream;
 
+use crate::commands::{self, ClientCommand, CommandReply, Replacement};
+use crate::frames::EventFrame;
+
+/// How often the server pings, and how long a silent client is kept.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(15);
+/// How long an amend waits for the engine to confirm its cancel.
+const AMEND_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// An amend whose cancel has been queued but not yet confirmed.
+struct PendingAmend {
+    req_id: Option<String>,
+    replacement: Replacement,
+}
+
 pub struct WsSession {
-    rx: broadcast::Receiver<String>,
+    rx: broadcast::Receiver<Arc<EventFrame>>,
+    redis: Arc<RedisManager>,
+    queue_codec: Codec,
+    jwt: Arc<JwtKeys>,
+    /// Encoding negotiated for this connection, used for events and replies.
+    codec: Codec,
+    /// Set by a successful `auth` command; order commands are rejected until then.
+    user_id: Option<UserId>,
+    /// Opted into with `auth`; pulls the user's orders when the session ends.
+    cancel_on_disconnect: bool,
+    /// Amends in flight, by the order they replace.
+    amends: HashMap<OrderId, PendingAmend>,
+    last_seen: Instant,
 }
 
 impl WsSession {
-    pub fn new(rx: broadcast::Receiver<String>) -> Self {
-        Self { rx }
+    pub fn new(
+        rx: broadcast::Receiver<Arc<EventFrame>>,
+        redis: Arc<RedisManager>,
+        queue_codec: Codec,
+        jwt: Arc<JwtKeys>,
+        codec: Codec,
+    ) -> Self {
+        Self {
+            rx,
+            redis,
+            queue_codec,
+            jwt,
+            codec,
+            user_id: None,
+            cancel_on_disconnect: false,
+            amends: HashMap::new(),
+            last_seen: Instant::now(),
+        }
+    }
+
+    fn reply(&self, ctx: &mut ws::WebsocketContext<Self>, reply: &CommandReply) {
+        match self.codec.encode(reply) {
+            Ok(body) if self.codec.is_binary() => ctx.binary(body),
+            Ok(body) => ctx.text(String::from_utf8_lossy(&body).into_owned()),
+            Err(err) => tracing::error!("failed to encode reply: {err}"),
+        }
+    }
+
+    fn handle_command(&mut self, raw: &[u8], codec: Codec, ctx: &mut ws::WebsocketContext<Self>) {
+        let cmd: ClientCommand = match codec.decode(raw) {
+            Ok(cmd) => cmd,
+            Err(err) => {
+                self.reply(ctx, &CommandReply::error(None, err));
+                return;
+            }
+        };
+
+        if let ClientCommand::Auth {
+            req_id,
+            token,
+            cancel_on_disconnect,
+        } = cmd
+        {
+            match self.jwt.verify(&token, TokenKind::Access) {
+                Ok(claims) => {
+                    self.user_id = Some(claims.sub);
+                    self.cancel_on_disconnect = cancel_on_disconnect;
+                    self.reply(ctx, &CommandReply::ack(req_id, "auth", None));
+                }
+                Err(err) => self.reply(ctx, &CommandReply::error(req_id, err)),
+            }
+            return;
+        }
+
+        let user_id = match self.user_id {
+            Some(id) => id,
+            None => {
+                self.reply(ctx, &CommandReply::error(cmd.req_id(), "not authenticated"));
+                return;
+            }
+        };
+
+        if let ClientCommand::Amend {
+            req_id,
+            order_id,
+            pair,
+            side,
+            order_type,
+            price,
+            quantity,
+        } = cmd
+        {
+            let replacement = Replacement {
+                pair,
+                side,
+                order_type,
+                price,
+                quantity,
+            };
+            self.start_amend(user_id, req_id, order_id, replacement, ctx);
+            return;
+        }
+
+        let req_id = cmd.req_id();
+        let redis = self.redis.clone();
+        let queue_codec = self.queue_codec;
+        let fut = async move { commands::submit(&redis, queue_codec, user_id, cmd).await };
+        ctx.spawn(fut.into_actor(self).map(move |res, act, ctx| {
+            let reply = res.unwrap_or_else(|err| CommandReply::error(req_id, err));
+            act.reply(ctx, &reply);
+        }));
+    }
+
+    /// Queues the cancel half of an amend. The replacement is only submitted
+    /// once the engine confirms the cancel, so both orders are never live.
+    fn start_amend(
+        &mut self,
+        user_id: UserId,
+        req_id: Option<String>,
+        order_id: OrderId,
+        replacement: Replacement,
+        ctx: &mut ws::WebsocketContext<Self>,
+    ) {
+        if let Err(err) = replacement.validate() {
+            self.reply(ctx, &CommandReply::error(req_id, err));
+            return;
+        }
+        if self.amends.contains_key(&order_id) {
+            let message = format!("an amend of {order_id} is already in progress");
+            self.reply(ctx, &CommandReply::error(req_id, message));
+            return;
+        }
+        let pair = replacement.pair.clone();
+        self.amends.insert(
+            order_id,
+            PendingAmend {
+                req_id,
+                replacement,
+            },
+        );
+
+        let redis = self.redis.clone();
+        let codec = self.queue_codec;
+        let fut =
+            async move { commands::push_cancel(&redis, codec, user_id, order_id, pair).await };
+        ctx.spawn(fut.into_actor(self).map(move |res, act, ctx| {
+            if let Err(err) = res {
+                act.fail_amend(order_id, err, ctx);
+            }
+        }));
+        ctx.run_later(AMEND_TIMEOUT, move |act, ctx| {
+            let message = format!("cancel of {order_id} was not confirmed, replacement not placed");
+            act.fail_amend(order_id, message, ctx);
+        });
+    }
+
+    fn fail_amend(
+        &mut self,
+        order_id: OrderId,
+        err: impl ToString,
+        ctx: &mut ws::WebsocketContext<Self>,
+    ) {
+        if let Some(amend) = self.amends.remove(&order_id) {
+            self.reply(ctx, &CommandReply::error(amend.req_id, err));
+        }
+    }
+
+    /// Finishes an amend when the engine answers its cancel.
+    fn settle_amend(&mut self, event: &Event, ctx: &mut ws::WebsocketContext<Self>) {
+        let Some(user_id) = self.user_id else { return };
+        match event {
+            Event::OrderUpdate(update)
+                if update.user_id == user_id && update.status == OrderStatus::Cancelled =>
+            {
+                let Some(amend) = self.amends.remove(&update.order_id) else {
+                    return;
+                };
+                let replaced = update.order_id;
+                let redis = self.redis.clone();
+                let codec = self.queue_codec;
+                let fut = async move { amend.replacement.submit(&redis, codec, user_id).await };
+                let req_id = amend.req_id;
+                ctx.spawn(fut.into_actor(self).map(move |res, act, ctx| {
+                    let reply = match res {
+                        Ok(order_id) => CommandReply::amended(req_id, order_id, replaced),
+                        Err(err) => CommandReply::error(req_id, err),
+                    };
+                    act.reply(ctx, &reply);
+                }));
+            }
+            Event::CancelRejected(rejected) if rejected.user_id == user_id => {
+                let message = format!("cancel rejected: {}", rejected.reason.as_str());
+                self.fail_amend(rejected.order_id, message, ctx);
+            }
+            _ => {}
+        }
     }
 }
 
@@ -20,14 +229,39 @@ impl Actor for WsSession {
     fn started(&mut self, ctx: &mut Self::Context) {
         let stream = BroadcastStream::new(self.rx.resubscribe());
         ctx.add_stream(stream);
+        // A dropped connection may never send a close frame; silence counts too.
+        ctx.run_interval(HEARTBEAT_INTERVAL, |act, ctx| {
+            if act.last_seen.elapsed() > CLIENT_TIMEOUT {
+                ctx.stop();
+            } else {
+                ctx.ping(b"");
+            }
+        });
+    }
+
+    /// Queues a cancel-all for the user, so orders placed over REST or on
+    /// other sessions go too.
+    fn stopped(&mut self, _ctx: &mut Self::Context) {
+        let Some(user_id) = self.user_id.filter(|_| self.cancel_on_disconnect) else {
+            return;
+        };
+        let redis = self.redis.clone();
+        let codec = self.queue_codec;
+        actix::spawn(async move {
+            if let Err(err) = commands::push_cancel_all(&redis, codec, user_id).await {
+                tracing::error!(%user_id, "cancel on disconnect failed: {err}");
+            }
+        });
     }
 }
 
 impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsSession {
     fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        self.last_seen = Instant::now();
         match msg {
             Ok(ws::Message::Ping(msg)) => ctx.pong(&msg),
-            Ok(ws::Message::Text(text)) => ctx.text(text),
+            Ok(ws::Message::Text(text)) => self.handle_command(text.as_bytes(), Codec::Json, ctx),
+            Ok(ws::Message::Binary(bytes)) => self.handle_command(&bytes, Codec::MsgPack, ctx),
             Ok(ws::Message::Close(reason)) => {
                 ctx.close(reason);
                 ctx.stop();
@@ -37,10 +271,26 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsSession {
     }
 }
 
-impl StreamHandler<Result<String, BroadcastStreamRecvError>> for WsSession {
-    fn handle(&mut self, msg: Result<String, BroadcastStreamRecvError>, ctx: &mut Self::Context) {
-        if let Ok(payload) = msg {
-            ctx.text(payload);
+impl StreamHandler<Result<Arc<EventFrame>, BroadcastStreamRecvError>> for WsSession {
+    fn handle(
+        &mut self,
+        msg: Result<Arc<EventFrame>, BroadcastStreamRecvError>,
+        ctx: &mut Self::Context,
+    ) {
+        let Ok(frame) = msg else { return };
+        if !frame.visible_to(self.user_id) {
+            return;
+        }
+        match self.codec {
+            Codec::Json => ctx.text(frame.json.clone()),
+            Codec::MsgPack => {
+                if let Some(bytes) = frame.msgpack() {
+                    ctx.binary(bytes.to_vec());
+                }
+            }
+        }
+        if let Some(envelope) = frame.envelope.as_ref().filter(|_| !self.amends.is_empty()) {
+            self.settle_amend(&envelope.event, ctx);
         }
     }
 }
@@ -0,0 +1,147 @@
+use redis::RedisManager;
+use rust_decimal::Decimal;
+use serde_json::json;
+use shared::to_json;
+use shared::types::{OrderSide, OrderType};
+use shared::{CexError, Codec};
+use uuid::Uuid;
+use ws::commands::{self, ClientCommand, CommandReply, Replacement};
+
+#[test]
+fn parses_place_command() {
+    let raw = json!({
+        "op": "place",
+        "req_id": "42",
+        "pair": "SOLUSDC",
+        "side": "buy",
+        "order_type": "limit",
+        "price": "10.5",
+        "quantity": "2"
+    });
+    let cmd: ClientCommand = serde_json::from_value(raw).unwrap();
+    assert_eq!(cmd.op(), "place");
+    assert_eq!(cmd.req_id().as_deref(), Some("42"));
+}
+
+#[test]
+fn parses_amend_command() {
+    let order_id = Uuid::new_v4();
+    let raw = json!({
+        "op": "amend",
+        "order_id": order_id,
+        "pair": "SOLUSDC",
+        "side": "sell",
+        "order_type": "limit",
+        "price": "11",
+        "quantity": "1"
+    });
+    let cmd: ClientCommand = serde_json::from_value(raw).unwrap();
+    match cmd {
+        ClientCommand::Amend { order_id: id, .. } => assert_eq!(id, order_id),
+        other => panic!("unexpected command {other:?}"),
+    }
+}
+
+#[test]
+fn ack_carries_req_id_and_order_id() {
+    let order_id = Uuid::new_v4();
+    let reply = CommandReply::ack(Some("7".to_string()), "cancel", Some(order_id));
+    let body: serde_json::Value = serde_json::from_str(&to_json(&reply).unwrap()).unwrap();
+    assert_eq!(body["type"], "ack");
+    assert_eq!(body["req_id"], "7");
+    assert_eq!(body["op"], "cancel");
+    assert_eq!(body["order_id"], order_id.to_string());
+}
+
+#[test]
+fn cancel_on_disconnect_is_opt_in() {
+    let parse = |raw| match serde_json::from_value(raw).unwrap() {
+        ClientCommand::Auth {
+            cancel_on_disconnect,
+            ..
+        } => cancel_on_disconnect,
+        other => panic!("unexpected command {other:?}"),
+    };
+    assert!(!parse(json!({ "op": "auth", "token": "t" })));
+    assert!(parse(
+        json!({ "op": "auth", "token": "t", "cancel_on_disconnect": true })
+    ));
+}
+
+#[test]
+fn amend_ack_names_both_orders() {
+    let (new_id, old_id) = (Uuid::new_v4(), Uuid::new_v4());
+    let reply = CommandReply::amended(Some("4".to_string()), new_id, old_id);
+    let body: serde_json::Value = serde_json::from_str(&to_json(&reply).unwrap()).unwrap();
+    assert_eq!(body["op"], "amend");
+    assert_eq!(body["order_id"], new_id.to_string());
+    assert_eq!(body["replaced_order_id"], old_id.to_string());
+}
+
+#[test]
+fn replacement_is_validated_before_the_cancel_goes_out() {
+    let replacement = |quantity| Replacement {
+        pair: "SOLUSDC".to_string(),
+        side: OrderSide::Buy,
+        order_type: OrderType::Limit,
+        price: Decimal::TEN,
+        quantity,
+    };
+    assert!(replacement(Decimal::ONE).validate().is_ok());
+    assert!(replacement(Decimal::ZERO).validate().is_err());
+}
+
+#[test]
+fn limit_order_without_a_price_is_rejected() {
+    let raw = json!({
+        "op": "amend",
+        "order_id": Uuid::new_v4(),
+        "pair": "SOLUSDC",
+        "side": "buy",
+        "order_type": "limit",
+        "quantity": "1"
+    });
+    let cmd: ClientCommand = serde_json::from_value(raw).unwrap();
+    let ClientCommand::Amend {
+        pair,
+        side,
+        order_type,
+        price,
+        quantity,
+        ..
+    } = cmd
+    else {
+        panic!("unexpected command {cmd:?}");
+    };
+    let replacement = Replacement {
+        pair,
+        side,
+        order_type,
+        price,
+        quantity,
+    };
+    assert!(replacement.validate().is_err());
+    let market = Replacement {
+        order_type: OrderType::Market,
+        ..replacement
+    };
+    assert!(market.validate().is_ok());
+}
+
+#[tokio::test]
+async fn limit_place_without_a_price_is_rejected_before_it_is_queued() {
+    let raw = json!({
+        "op": "place",
+        "pair": "SOLUSDC",
+        "side": "buy",
+        "order_type": "limit",
+        "quantity": "1"
+    });
+    let cmd: ClientCommand = serde_json::from_value(raw).unwrap();
+    // Never reached: validation fails before anything is pushed.
+    let redis = RedisManager::new("redis://127.0.0.1/").await.unwrap();
+    let err = commands::submit(&redis, Codec::Json, Uuid::new_v4(), cmd)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, CexError::Validation(_)), "{err:?}");
+}
@@ -0,0 +1,58 @@
+use chrono::Utc;
+use shared::types::{CancelRejectReason, CancelRejected, CountdownCancel, MarketResume};
+use shared::{to_json, Codec, Envelope, Event};
+use uuid::Uuid;
+use ws::frames::EventFrame;
+
+#[test]
+fn msgpack_form_decodes_to_the_same_event() {
+    let envelope = Envelope::new(
+        "engine",
+        Event::CountdownCancel(CountdownCancel {
+            user_id: Uuid::new_v4(),
+            timeout_ms: 5_000,
+        }),
+    );
+    let frame = EventFrame::new(to_json(&envelope).unwrap());
+    let bytes = frame.msgpack().expect("event re-encodes");
+    assert_eq!(Codec::detect(bytes), Codec::MsgPack);
+    assert_eq!(Envelope::decode(bytes).unwrap().event_id, envelope.event_id);
+}
+
+#[test]
+fn undecodable_events_have_no_msgpack_form() {
+    let frame = EventFrame::new("not an envelope".to_string());
+    assert!(frame.envelope.is_none());
+    assert!(frame.msgpack().is_none());
+}
+
+fn frame(event: Event) -> EventFrame {
+    EventFrame::new(to_json(&Envelope::new("engine", event)).unwrap())
+}
+
+#[test]
+fn order_events_only_reach_their_user() {
+    let (owner, other) = (Uuid::new_v4(), Uuid::new_v4());
+    let rejected = frame(Event::CancelRejected(CancelRejected {
+        order_id: Uuid::new_v4(),
+        user_id: owner,
+        pair: "SOLUSDC".to_string(),
+        reason: CancelRejectReason::UnknownOrder,
+        ts: Utc::now(),
+    }));
+    assert!(rejected.visible_to(Some(owner)));
+    assert!(!rejected.visible_to(Some(other)));
+    assert!(!rejected.visible_to(None));
+
+    let resumed = frame(Event::MarketResumed(MarketResume {
+        pair: "SOLUSDC".to_string(),
+        ts: Utc::now(),
+    }));
+    assert!(resumed.visible_to(None));
+
+    let countdown = frame(Event::CountdownCancel(CountdownCancel {
+        user_id: owner,
+        timeout_ms: 0,
+    }));
+    assert!(!countdown.visible_to(Some(owner)));
+}